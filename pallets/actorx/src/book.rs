@@ -0,0 +1,141 @@
+//! Execution of ActorX orders against a resource book
+//!
+//! Each recipient of ActorX messages acts as a resource provider with an
+//! available capacity. Fill-type messages are orders for a quantity of that
+//! capacity and execute as in an order book:
+//!
+//! - `Fill` takes what is available and rests with the remainder. A resting
+//!   order is `Pending` until something is filled and `InProgress` after,
+//!   and can be executed again once the provider has more capacity; it
+//!   completes when fully filled.
+//! - `Kill` does not take capacity. It cancels a resting order of the same
+//!   sender and completes.
+//! - `FillOrKill` fills completely or, if the capacity is short, is
+//!   cancelled without filling anything.
+//! - `FillAndKill` (immediate-or-cancel) takes what is available and
+//!   cancels the remainder. It completes only if it was filled in full.
+
+use crate::{ActorXOperation, MessageStatus};
+
+/// Result of executing an order once
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Execution {
+    /// Quantity filled by this execution
+    pub filled: u128,
+    /// Status of the order afterwards
+    pub status: MessageStatus,
+}
+
+/// Quantity an order is booked with, `None` if a fill-type order is for nothing
+///
+/// A `Kill` takes no capacity and is booked with zero whatever it asked for.
+pub fn order_quantity(operation: ActorXOperation, quantity: u128) -> Option<u128> {
+    match operation {
+        ActorXOperation::Kill => Some(0),
+        _ if quantity == 0 => None,
+        _ => Some(quantity),
+    }
+}
+
+/// Execute an order for `quantity`, of which `filled` is already filled, against `available` capacity
+pub fn execute(operation: ActorXOperation, quantity: u128, filled: u128, available: u128) -> Execution {
+    let remaining = quantity.saturating_sub(filled);
+    let partial = remaining.min(available);
+    match operation {
+        ActorXOperation::Fill => Execution {
+            filled: partial,
+            status: if partial == remaining {
+                MessageStatus::Completed
+            } else if filled.saturating_add(partial) > 0 {
+                MessageStatus::InProgress
+            } else {
+                MessageStatus::Pending
+            },
+        },
+        ActorXOperation::Kill => Execution { filled: 0, status: MessageStatus::Completed },
+        ActorXOperation::FillOrKill => {
+            if available >= remaining {
+                Execution { filled: remaining, status: MessageStatus::Completed }
+            } else {
+                Execution { filled: 0, status: MessageStatus::Cancelled }
+            }
+        }
+        ActorXOperation::FillAndKill => Execution {
+            filled: partial,
+            status: if partial == remaining { MessageStatus::Completed } else { MessageStatus::Cancelled },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Execute a resting order against each capacity in turn, returning every execution
+    fn run(operation: ActorXOperation, quantity: u128, capacities: &[u128]) -> Vec<Execution> {
+        let mut filled = 0;
+        capacities
+            .iter()
+            .map(|available| {
+                let execution = execute(operation, quantity, filled, *available);
+                filled += execution.filled;
+                execution
+            })
+            .collect()
+    }
+
+    fn execution(filled: u128, status: MessageStatus) -> Execution {
+        Execution { filled, status }
+    }
+
+    #[test]
+    fn fill_rests_until_filled_in_full() {
+        assert_eq!(
+            run(ActorXOperation::Fill, 100, &[0, 30, 0, 50, 200]),
+            vec![
+                execution(0, MessageStatus::Pending),
+                execution(30, MessageStatus::InProgress),
+                execution(0, MessageStatus::InProgress),
+                execution(50, MessageStatus::InProgress),
+                execution(20, MessageStatus::Completed),
+            ]
+        );
+    }
+
+    #[test]
+    fn fill_completes_at_once_with_enough_capacity() {
+        assert_eq!(execute(ActorXOperation::Fill, 100, 0, 100), execution(100, MessageStatus::Completed));
+    }
+
+    #[test]
+    fn kill_completes_without_taking_capacity() {
+        for available in [0, 100] {
+            assert_eq!(execute(ActorXOperation::Kill, 0, 0, available), execution(0, MessageStatus::Completed));
+        }
+    }
+
+    #[test]
+    fn fill_or_kill_fills_in_full_or_not_at_all() {
+        assert_eq!(execute(ActorXOperation::FillOrKill, 100, 0, 150), execution(100, MessageStatus::Completed));
+        assert_eq!(execute(ActorXOperation::FillOrKill, 100, 0, 100), execution(100, MessageStatus::Completed));
+        assert_eq!(execute(ActorXOperation::FillOrKill, 100, 0, 99), execution(0, MessageStatus::Cancelled));
+        assert_eq!(execute(ActorXOperation::FillOrKill, 100, 0, 0), execution(0, MessageStatus::Cancelled));
+    }
+
+    #[test]
+    fn fill_and_kill_cancels_the_remainder() {
+        assert_eq!(execute(ActorXOperation::FillAndKill, 100, 0, 40), execution(40, MessageStatus::Cancelled));
+        assert_eq!(execute(ActorXOperation::FillAndKill, 100, 0, 0), execution(0, MessageStatus::Cancelled));
+        assert_eq!(execute(ActorXOperation::FillAndKill, 100, 0, 100), execution(100, MessageStatus::Completed));
+    }
+
+    #[test]
+    fn zero_quantity_fill_orders_are_rejected() {
+        for operation in [ActorXOperation::Fill, ActorXOperation::FillOrKill, ActorXOperation::FillAndKill] {
+            assert_eq!(order_quantity(operation, 0), None);
+            assert_eq!(order_quantity(operation, 5), Some(5));
+        }
+        assert_eq!(order_quantity(ActorXOperation::Kill, 0), Some(0));
+        assert_eq!(order_quantity(ActorXOperation::Kill, 5), Some(0));
+    }
+}
@@ -2,19 +2,65 @@
 //!
 //! This module implements ActorX Fill and Kill operations with quantum keys
 //! for the Matrix-Magiq ecosystem.
+//!
+//! Senders submit messages addressed to a recipient with `send_message`,
+//! signed with a CRYSTALS-Dilithium key over the SCALE-encoded message body.
+//! Each message is identified by the hash of its sender, the sender's nonce
+//! and its payload, and is stored until it is processed, cancelled or
+//! expires. The recipient executes a pending message with `process_message`
+//! and the sender can withdraw it with `cancel_message` while it is still
+//! resting. Every status change emits its own event.
+//!
+//! Recipients act as resource providers. Each publishes its available
+//! capacity in the resource book with `set_capacity`, and fill-type messages
+//! are orders for a quantity of it, executed with order-book semantics (see
+//! `book`). Partial fills are recorded on the message.
+//!
+//! Payloads are bounded by `MaxPayloadLen` and must decode as an
+//! `ActorXPayload` that fits the message's operation (see `payload`).
+//!
+//! Sending a message reserves a deposit of `MessageDepositBase` plus
+//! `MessageDepositPerByte` for every payload byte, released once the message
+//! reaches a terminal status. A sender can have at most
+//! `MaxPendingPerSender` messages resting at a time. Messages may carry a
+//! priority fee, withdrawn when they are sent; every block the
+//! `MaxProcessedPerBlock` queued messages with the highest fees are executed
+//! in fee order, and the rest wait for a later block or for their recipient
+//! to execute them with `process_message`.
+//!
+//! Every message is indexed in its recipient's `Inbox` and its sender's
+//! `Outbox` until it is pruned. The `ActorXApi` runtime API pages through
+//! them with status and operation filters (see `runtime_api`).
+//!
+//! Resting messages are indexed by their expiry block. In `on_idle` a
+//! sweeper marks those past their expiry as `Expired`, and messages that
+//! reached a terminal status are pruned `RetentionPeriod` blocks later. The
+//! sweeper stops when the idle weight runs out and resumes from where it
+//! left off.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub use pallet::*;
+
+pub mod book;
+pub mod payload;
+pub mod quantum;
+pub mod runtime_api;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+use codec::{Decode, Encode, MaxEncodedLen};
 use frame_support::{
-    dispatch::DispatchResult,
-    pallet_prelude::*,
-    traits::{Get, Currency},
+    traits::Get, BoundedVec, CloneNoBound, EqNoBound, Parameter, PartialEqNoBound, RuntimeDebugNoBound,
 };
-use frame_system::pallet_prelude::*;
-use sp_runtime::{traits::Hash, RuntimeDebug};
-use sp_std::prelude::*;
-use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+use crate::quantum::{QuantumKey, QuantumSignature};
 
 /// ActorX operation types
 #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
@@ -42,11 +88,34 @@ pub enum MessageStatus {
     Failed,
     /// Expired
     Expired,
+    /// Cancelled before it was filled in full
+    Cancelled,
+}
+
+impl MessageStatus {
+    /// Whether the message can no longer change status
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, MessageStatus::Pending | MessageStatus::InProgress)
+    }
 }
 
 /// ActorX message
-#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
-pub struct ActorXMessage<AccountId, Hash, BlockNumber> {
+#[derive(Encode, Decode, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[codec(mel_bound(
+    AccountId: MaxEncodedLen,
+    Balance: MaxEncodedLen,
+    Hash: MaxEncodedLen,
+    BlockNumber: MaxEncodedLen,
+    MaxPayloadLen: Get<u32>
+))]
+#[scale_info(skip_type_params(MaxPayloadLen))]
+pub struct ActorXMessage<
+    AccountId: Parameter,
+    Balance: Parameter,
+    Hash: Parameter,
+    BlockNumber: Parameter,
+    MaxPayloadLen: Get<u32>,
+> {
     /// Message ID
     pub id: Hash,
     /// Sender account
@@ -55,73 +124,803 @@ pub struct ActorXMessage<AccountId, Hash, BlockNumber> {
     pub recipient: AccountId,
     /// Operation type
     pub operation: ActorXOperation,
-    /// Message payload
-    pub payload: Vec<u8>,
+    /// Quantity ordered from the recipient's capacity, zero for `Kill`
+    pub quantity: u128,
+    /// Quantity filled so far
+    pub filled: u128,
+    /// SCALE-encoded `ActorXPayload`
+    pub payload: BoundedVec<u8, MaxPayloadLen>,
     /// Quantum key (CRYSTALS-Dilithium)
-    pub quantum_key: [u8; 64],
+    pub quantum_key: QuantumKey,
+    /// Signature of the message body under `quantum_key`
+    pub signature: QuantumSignature,
     /// Message status
     pub status: MessageStatus,
+    /// Deposit reserved from the sender until the message is terminal
+    pub deposit: Balance,
+    /// Fee paid by the sender to be processed ahead of lower fees
+    pub priority_fee: Balance,
     /// Created at block
     pub created_at: BlockNumber,
     /// Expires at block
     pub expires_at: BlockNumber,
 }
 
-/// Generate a quantum key for ActorX operations
-pub fn generate_quantum_key() -> [u8; 64] {
-    // Implementation would use CRYSTALS-Dilithium
-    // This is a placeholder for the actual implementation
-    [0u8; 64]
+/// Part of an ActorX message signed by its sender
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct ActorXMessageBody<AccountId, BlockNumber> {
+    /// Sender account
+    pub sender: AccountId,
+    /// Recipient account
+    pub recipient: AccountId,
+    /// Operation type
+    pub operation: ActorXOperation,
+    /// Quantity ordered from the recipient's capacity
+    pub quantity: u128,
+    /// SCALE-encoded `ActorXPayload`
+    pub payload: Vec<u8>,
+    /// Sender's nonce, so that a signature cannot be replayed
+    pub nonce: u64,
+    /// Blocks the message stays valid
+    pub lifetime: BlockNumber,
 }
 
-/// Verify a quantum key
-pub fn verify_quantum_key(key: &[u8; 64], data: &[u8]) -> bool {
-    // Implementation would verify using CRYSTALS-Dilithium
-    // This is a placeholder for the actual implementation
-    true
+/// Verify a quantum signature over the SCALE encoding of a message body
+pub fn verify_quantum_key<AccountId: Encode, BlockNumber: Encode>(
+    key: &QuantumKey,
+    body: &ActorXMessageBody<AccountId, BlockNumber>,
+    signature: &[u8],
+) -> bool {
+    body.using_encoded(|data| key.verify(data, signature))
 }
 
-/// Send an ActorX message
-pub fn send_message<T: frame_system::Config>(
-    message: ActorXMessage<T::AccountId, T::Hash, T::BlockNumber>,
-) -> DispatchResult {
-    // Implementation would send the message
-    // This is a placeholder for the actual implementation
-    Ok(())
-}
+#[frame_support::pallet]
+pub mod pallet {
+    use super::*;
+    use crate::{
+        payload::{ActorXPayload, PayloadError},
+        runtime_api::MessagePage,
+    };
+    use frame_support::{
+        pallet_prelude::*,
+        traits::{Currency, ExistenceRequirement, OnUnbalanced, ReservableCurrency, WithdrawReasons},
+    };
+    use frame_system::pallet_prelude::*;
+    use sp_runtime::traits::{Hash, One, Saturating, Zero};
 
-/// Process an ActorX Fill operation
-pub fn process_fill<T: frame_system::Config>(
-    message_id: T::Hash,
-) -> DispatchResult {
-    // Implementation would process a Fill operation
-    // This is a placeholder for the actual implementation
-    Ok(())
-}
+    /// Alias for balance type
+    pub type BalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
-/// Process an ActorX Kill operation
-pub fn process_kill<T: frame_system::Config>(
-    message_id: T::Hash,
-) -> DispatchResult {
-    // Implementation would process a Kill operation
-    // This is a placeholder for the actual implementation
-    Ok(())
-}
+    /// Alias for negative imbalance type
+    pub type NegativeImbalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::NegativeImbalance;
 
-/// Process an ActorX Fill or Kill operation
-pub fn process_fill_or_kill<T: frame_system::Config>(
-    message_id: T::Hash,
-) -> DispatchResult {
-    // Implementation would process a Fill or Kill operation
-    // This is a placeholder for the actual implementation
-    Ok(())
-}
+    /// Alias for the message type stored by the pallet
+    pub type ActorXMessageOf<T> = ActorXMessage<
+        <T as frame_system::Config>::AccountId,
+        BalanceOf<T>,
+        <T as frame_system::Config>::Hash,
+        <T as frame_system::Config>::BlockNumber,
+        <T as Config>::MaxPayloadLen,
+    >;
+
+    /// Alias for the decoded payload of a message
+    pub type ActorXPayloadOf<T> = ActorXPayload<
+        <T as frame_system::Config>::AccountId,
+        <T as frame_system::Config>::Hash,
+        <T as frame_system::Config>::BlockNumber,
+    >;
+
+    /// Max messages returned in a page
+    pub const MAX_PAGE_LEN: u32 = 100;
+
+    /// Max index entries looked at for a page
+    pub const MAX_PAGE_SCAN: u32 = 1_000;
+
+    #[pallet::pallet]
+    pub struct Pallet<T>(_);
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// The overarching event type
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+        /// Max blocks a message stays valid after it is sent
+        #[pallet::constant]
+        type MaxMessageLifetime: Get<Self::BlockNumber>;
+
+        /// Blocks a message is kept after reaching a terminal status
+        #[pallet::constant]
+        type RetentionPeriod: Get<Self::BlockNumber>;
+
+        /// Max size of a message payload in bytes
+        #[pallet::constant]
+        type MaxPayloadLen: Get<u32>;
+
+        /// Weight of verifying a Dilithium5 (ML-DSA-87) signature
+        #[pallet::constant]
+        type SignatureVerifyWeight: Get<Weight>;
+
+        /// Currency used for deposits and priority fees
+        type Currency: ReservableCurrency<Self::AccountId>;
+
+        /// Handler for priority fees
+        type PriorityFeeDestination: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+        /// Deposit reserved for every message
+        #[pallet::constant]
+        type MessageDepositBase: Get<BalanceOf<Self>>;
+
+        /// Deposit reserved for every byte of a message's payload
+        #[pallet::constant]
+        type MessageDepositPerByte: Get<BalanceOf<Self>>;
+
+        /// Max resting messages per sender
+        #[pallet::constant]
+        type MaxPendingPerSender: Get<u32>;
+
+        /// Max messages waiting in the processing queue
+        #[pallet::constant]
+        type MaxQueueLen: Get<u32>;
+
+        /// Max queued messages executed per block
+        #[pallet::constant]
+        type MaxProcessedPerBlock: Get<u32>;
+    }
+
+    /// Capacity each provider has available to fill orders
+    #[pallet::storage]
+    pub type ResourceBook<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        u128,
+        ValueQuery,
+    >;
+
+    /// Messages by ID
+    #[pallet::storage]
+    pub type Messages<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::Hash,
+        ActorXMessageOf<T>,
+    >;
+
+    /// Messages by recipient
+    #[pallet::storage]
+    pub type Inbox<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::Hash,
+        (),
+    >;
+
+    /// Messages by sender
+    #[pallet::storage]
+    pub type Outbox<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::Hash,
+        (),
+    >;
+
+    /// Number of messages sent by each account, used to derive message IDs
+    #[pallet::storage]
+    pub type Nonces<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        u64,
+        ValueQuery,
+    >;
+
+    /// Resting messages by sender
+    #[pallet::storage]
+    pub type PendingCount<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        u32,
+        ValueQuery,
+    >;
+
+    /// Messages waiting to be executed, by descending priority fee
+    #[pallet::storage]
+    pub type ProcessingQueue<T: Config> = StorageValue<
+        _,
+        BoundedVec<(BalanceOf<T>, T::Hash), T::MaxQueueLen>,
+        ValueQuery,
+    >;
+
+    /// Resting messages by expiry block
+    #[pallet::storage]
+    pub type ExpiryIndex<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        T::Hash,
+        (),
+    >;
+
+    /// Terminal messages by the block from which they can be pruned
+    #[pallet::storage]
+    pub type PruneIndex<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        T::Hash,
+        (),
+    >;
+
+    /// Next block of `ExpiryIndex` to sweep
+    #[pallet::storage]
+    pub type NextExpirySweep<T: Config> = StorageValue<_, T::BlockNumber>;
+
+    /// Next block of `PruneIndex` to sweep
+    #[pallet::storage]
+    pub type NextPruneSweep<T: Config> = StorageValue<_, T::BlockNumber>;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// A message was sent and is pending
+        MessageSent {
+            id: T::Hash,
+            sender: T::AccountId,
+            recipient: T::AccountId,
+            operation: ActorXOperation,
+            quantity: u128,
+            deposit: BalanceOf<T>,
+            priority_fee: BalanceOf<T>,
+            expires_at: T::BlockNumber,
+        },
+
+        /// An order was partly filled and rests with the remainder
+        MessageInProgress {
+            id: T::Hash,
+        },
+
+        /// Part or all of an order was filled from the recipient's capacity
+        MessageFilled {
+            id: T::Hash,
+            amount: u128,
+            filled: u128,
+            remaining: u128,
+        },
+
+        /// A message was executed successfully
+        MessageCompleted {
+            id: T::Hash,
+        },
+
+        /// A message could not be executed
+        MessageFailed {
+            id: T::Hash,
+            error: DispatchError,
+        },
+
+        /// A message expired before it was executed
+        MessageExpired {
+            id: T::Hash,
+        },
+
+        /// A message was cancelled by its sender, a `Kill` or its unfilled remainder
+        MessageCancelled {
+            id: T::Hash,
+        },
+
+        /// A provider set its available capacity
+        CapacitySet {
+            provider: T::AccountId,
+            capacity: u128,
+        },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// No message with this ID
+        MessageNotFound,
+
+        /// A message with this ID already exists
+        DuplicateMessage,
+
+        /// Only the sender can do this
+        NotSender,
+
+        /// Only the recipient can do this
+        NotRecipient,
+
+        /// The message's status does not allow this
+        InvalidStatus,
+
+        /// Lifetime is zero or above `MaxMessageLifetime`
+        InvalidLifetime,
+
+        /// The quantum key does not have the size of its level
+        InvalidQuantumKey,
+
+        /// The message is not signed
+        MissingSignature,
+
+        /// The signature does not verify the message body under the quantum key
+        InvalidSignature,
+
+        /// Fill-type orders need a nonzero quantity
+        ZeroQuantity,
+
+        /// `Kill` payload is not the ID of a resting order of the same sender and recipient
+        InvalidKillTarget,
+
+        /// Payload is not an `ActorXPayload`
+        UndecodablePayload,
+
+        /// Payload is not allowed for the operation
+        PayloadMismatch,
+
+        /// Restake instruction has no lock duration
+        ZeroLockDuration,
+
+        /// Sender has `MaxPendingPerSender` resting messages
+        TooManyPending,
+
+        /// Processing queue is full of messages with at least this priority fee
+        QueueFull,
+
+        /// Sender cannot pay the deposit or the priority fee
+        InsufficientBalance,
+    }
+
+    impl<T> From<PayloadError> for Error<T> {
+        fn from(error: PayloadError) -> Self {
+            match error {
+                PayloadError::Undecodable => Error::<T>::UndecodablePayload,
+                PayloadError::OperationMismatch => Error::<T>::PayloadMismatch,
+                PayloadError::ZeroLockDuration => Error::<T>::ZeroLockDuration,
+            }
+        }
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(_now: BlockNumberFor<T>) -> Weight {
+            Self::process_queue()
+        }
+
+        fn on_idle(now: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            Self::sweep(now, remaining_weight)
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Send a message to `recipient`, valid for `lifetime` blocks
+        ///
+        /// Fill-type messages order `quantity` of the recipient's capacity. A
+        /// `Kill` names the order to cancel with a `ValidatorKill` payload and
+        /// ignores `quantity`.
+        ///
+        /// `signature` must be a Dilithium signature under `quantum_key` of
+        /// the SCALE-encoded `ActorXMessageBody` with the sender's current
+        /// nonce.
+        ///
+        /// A deposit proportional to the payload size is reserved until the
+        /// message is terminal. `priority_fee` is withdrawn from the sender
+        /// and orders the message in the processing queue.
+        #[pallet::call_index(0)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(5, 9).saturating_add(T::SignatureVerifyWeight::get()))]
+        pub fn send_message(
+            origin: OriginFor<T>,
+            recipient: T::AccountId,
+            operation: ActorXOperation,
+            quantity: u128,
+            payload: BoundedVec<u8, T::MaxPayloadLen>,
+            quantum_key: QuantumKey,
+            signature: QuantumSignature,
+            lifetime: T::BlockNumber,
+            priority_fee: BalanceOf<T>,
+        ) -> DispatchResult {
+            let sender = ensure_signed(origin)?;
+            ensure!(
+                !lifetime.is_zero() && lifetime <= T::MaxMessageLifetime::get(),
+                Error::<T>::InvalidLifetime
+            );
+            let order_quantity = book::order_quantity(operation, quantity).ok_or(Error::<T>::ZeroQuantity)?;
+            ActorXPayloadOf::<T>::parse(&payload, operation).map_err(Error::<T>::from)?;
+            ensure!(!signature.is_empty(), Error::<T>::MissingSignature);
+            ensure!(quantum_key.is_well_formed(), Error::<T>::InvalidQuantumKey);
+
+            let nonce = Nonces::<T>::get(&sender);
+            let body = ActorXMessageBody {
+                sender: sender.clone(),
+                recipient: recipient.clone(),
+                operation,
+                quantity,
+                payload: payload.to_vec(),
+                nonce,
+                lifetime,
+            };
+            ensure!(verify_quantum_key(&quantum_key, &body, &signature), Error::<T>::InvalidSignature);
+
+            let pending = PendingCount::<T>::get(&sender);
+            ensure!(pending < T::MaxPendingPerSender::get(), Error::<T>::TooManyPending);
+
+            let id = T::Hashing::hash_of(&(&sender, nonce, &payload));
+            ensure!(!Messages::<T>::contains_key(id), Error::<T>::DuplicateMessage);
+            Self::enqueue(id, priority_fee)?;
+
+            let deposit = T::MessageDepositBase::get()
+                .saturating_add(T::MessageDepositPerByte::get().saturating_mul((payload.len() as u32).into()));
+            T::Currency::reserve(&sender, deposit).map_err(|_| Error::<T>::InsufficientBalance)?;
+            if !priority_fee.is_zero() {
+                let fee = T::Currency::withdraw(
+                    &sender,
+                    priority_fee,
+                    WithdrawReasons::TIP,
+                    ExistenceRequirement::KeepAlive,
+                )
+                .map_err(|_| Error::<T>::InsufficientBalance)?;
+                T::PriorityFeeDestination::on_unbalanced(fee);
+            }
+
+            let now = frame_system::Pallet::<T>::block_number();
+            let expires_at = now.saturating_add(lifetime);
+            Messages::<T>::insert(id, ActorXMessage {
+                id,
+                sender: sender.clone(),
+                recipient: recipient.clone(),
+                operation,
+                quantity: order_quantity,
+                filled: 0,
+                payload,
+                quantum_key,
+                signature,
+                status: MessageStatus::Pending,
+                deposit,
+                priority_fee,
+                created_at: now,
+                expires_at,
+            });
+            ExpiryIndex::<T>::insert(expires_at, id, ());
+            NextExpirySweep::<T>::mutate(|next| {
+                if next.is_none() {
+                    *next = Some(now);
+                }
+            });
+            Inbox::<T>::insert(&recipient, id, ());
+            Outbox::<T>::insert(&sender, id, ());
+            PendingCount::<T>::insert(&sender, pending.saturating_add(1));
+            Nonces::<T>::insert(&sender, nonce.wrapping_add(1));
+
+            Self::deposit_event(Event::MessageSent {
+                id,
+                sender,
+                recipient,
+                operation,
+                quantity: order_quantity,
+                deposit,
+                priority_fee,
+                expires_at,
+            });
+            Ok(())
+        }
+
+        /// Execute a message addressed to the caller against the caller's capacity
+        ///
+        /// Resting `Fill` orders can be executed again to fill more of their
+        /// remainder. A message past its expiry is marked expired instead.
+        #[pallet::call_index(1)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(7, 12))]
+        pub fn process_message(origin: OriginFor<T>, id: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut message = Messages::<T>::get(id).ok_or(Error::<T>::MessageNotFound)?;
+            ensure!(message.recipient == who, Error::<T>::NotRecipient);
+            ensure!(!message.status.is_terminal(), Error::<T>::InvalidStatus);
+
+            Self::process(&mut message);
+            Messages::<T>::insert(id, message);
+            Ok(())
+        }
+
+        /// Withdraw a resting message sent by the caller
+        #[pallet::call_index(2)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 7))]
+        pub fn cancel_message(origin: OriginFor<T>, id: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut message = Messages::<T>::get(id).ok_or(Error::<T>::MessageNotFound)?;
+            ensure!(message.sender == who, Error::<T>::NotSender);
+            ensure!(!message.status.is_terminal(), Error::<T>::InvalidStatus);
+
+            Self::transition(&mut message, MessageStatus::Cancelled, None);
+            Messages::<T>::insert(id, message);
+            Ok(())
+        }
+
+        /// Set the capacity the caller has available to fill orders
+        #[pallet::call_index(3)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_capacity(origin: OriginFor<T>, capacity: u128) -> DispatchResult {
+            let provider = ensure_signed(origin)?;
+            ResourceBook::<T>::insert(&provider, capacity);
+            Self::deposit_event(Event::CapacitySet { provider, capacity });
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Set a message's status and emit the event for the new status
+        ///
+        /// Every status change goes through here so that a message reaching
+        /// a terminal status is always settled. `error` is why a message
+        /// failed, carried by `MessageFailed`.
+        fn transition(message: &mut ActorXMessageOf<T>, status: MessageStatus, error: Option<DispatchError>) {
+            message.status = status;
+            let id = message.id;
+            match status {
+                MessageStatus::InProgress => Self::deposit_event(Event::MessageInProgress { id }),
+                MessageStatus::Completed => Self::deposit_event(Event::MessageCompleted { id }),
+                MessageStatus::Failed => Self::deposit_event(Event::MessageFailed {
+                    id,
+                    error: error.unwrap_or(DispatchError::Other("unknown")),
+                }),
+                MessageStatus::Expired => Self::deposit_event(Event::MessageExpired { id }),
+                MessageStatus::Cancelled => Self::deposit_event(Event::MessageCancelled { id }),
+                MessageStatus::Pending => {}
+            }
+            if status.is_terminal() {
+                Self::settle(message);
+            }
+        }
+
+        /// Release a terminal message's deposit and pending slot, take it out
+        /// of the queue and the expiry index, and schedule its pruning
+        fn settle(message: &ActorXMessageOf<T>) {
+            T::Currency::unreserve(&message.sender, message.deposit);
+            PendingCount::<T>::mutate(&message.sender, |pending| *pending = pending.saturating_sub(1));
+            ProcessingQueue::<T>::mutate(|queue| queue.retain(|(_, id)| *id != message.id));
+            ExpiryIndex::<T>::remove(message.expires_at, message.id);
+            let now = frame_system::Pallet::<T>::block_number();
+            PruneIndex::<T>::insert(now.saturating_add(T::RetentionPeriod::get()), message.id, ());
+            NextPruneSweep::<T>::mutate(|next| {
+                if next.is_none() {
+                    *next = Some(now);
+                }
+            });
+        }
+
+        /// Expire resting messages and prune terminal ones that are due, within `remaining_weight`
+        ///
+        /// Each index is swept one block at a time from its cursor up to
+        /// `now`. A cursor starts at the block its first entry was indexed in
+        /// and only moves past a block once all of that block's entries are
+        /// handled, so a sweep cut short by the weight limit resumes where it
+        /// stopped.
+        fn sweep(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+            // Read the index entry and the message, update the message, both indices and the event
+            let per_expiry = T::DbWeight::get().reads_writes(2, 4);
+            // Read the index entry and the message, remove them and the inbox and outbox entries
+            let per_prune = T::DbWeight::get().reads_writes(2, 4);
+            let per_block = T::DbWeight::get().reads(1);
+
+            let mut consumed = T::DbWeight::get().reads_writes(2, 2);
+            if remaining_weight.any_lt(consumed.saturating_add(per_expiry)) {
+                return Weight::zero();
+            }
+
+            if let Some(mut cursor) = NextExpirySweep::<T>::get() {
+                while cursor <= now && !remaining_weight.any_lt(consumed.saturating_add(per_expiry)) {
+                    match ExpiryIndex::<T>::iter_key_prefix(cursor).next() {
+                        Some(id) => {
+                            consumed = consumed.saturating_add(per_expiry);
+                            ExpiryIndex::<T>::remove(cursor, id);
+                            if let Some(mut message) = Messages::<T>::get(id) {
+                                if !message.status.is_terminal() {
+                                    Self::transition(&mut message, MessageStatus::Expired, None);
+                                    Messages::<T>::insert(id, message);
+                                }
+                            }
+                        }
+                        None => {
+                            consumed = consumed.saturating_add(per_block);
+                            cursor = cursor.saturating_add(One::one());
+                        }
+                    }
+                }
+                NextExpirySweep::<T>::put(cursor);
+            }
+
+            if let Some(mut cursor) = NextPruneSweep::<T>::get() {
+                while cursor <= now && !remaining_weight.any_lt(consumed.saturating_add(per_prune)) {
+                    match PruneIndex::<T>::iter_key_prefix(cursor).next() {
+                        Some(id) => {
+                            consumed = consumed.saturating_add(per_prune);
+                            PruneIndex::<T>::remove(cursor, id);
+                            if let Some(message) = Messages::<T>::take(id) {
+                                Inbox::<T>::remove(&message.recipient, id);
+                                Outbox::<T>::remove(&message.sender, id);
+                            }
+                        }
+                        None => {
+                            consumed = consumed.saturating_add(per_block);
+                            cursor = cursor.saturating_add(One::one());
+                        }
+                    }
+                }
+                NextPruneSweep::<T>::put(cursor);
+            }
+
+            consumed
+        }
+
+        /// Insert a message into the processing queue behind messages with the same or a higher fee
+        ///
+        /// A full queue drops its lowest-fee message to make room, which
+        /// stays resting for its recipient to execute. Fails if no queued
+        /// message has a lower fee.
+        fn enqueue(id: T::Hash, priority_fee: BalanceOf<T>) -> DispatchResult {
+            ProcessingQueue::<T>::try_mutate(|queue| {
+                let position = queue.iter().position(|(fee, _)| *fee < priority_fee).unwrap_or(queue.len());
+                queue.force_insert_keep_left(position, (priority_fee, id)).map_err(|_| Error::<T>::QueueFull)?;
+                Ok(())
+            })
+        }
+
+        /// Execute the queued messages with the highest fees, in fee order
+        ///
+        /// Each message leaves the queue when it is executed. A `Fill` that
+        /// rests afterwards, unfilled or partly filled, is not queued again;
+        /// its recipient fills the rest with `process_message`.
+        fn process_queue() -> Weight {
+            let per_message = T::DbWeight::get().reads_writes(6, 11);
+            let mut queue = ProcessingQueue::<T>::get().into_inner();
+            if queue.is_empty() {
+                return T::DbWeight::get().reads(1);
+            }
+
+            let rest = queue.split_off(queue.len().min(T::MaxProcessedPerBlock::get() as usize));
+            ProcessingQueue::<T>::put(BoundedVec::truncate_from(rest));
+
+            let mut consumed = T::DbWeight::get().reads_writes(1, 1);
+            for (_, id) in queue {
+                consumed = consumed.saturating_add(per_message);
+                if let Some(mut message) = Messages::<T>::get(id) {
+                    if !message.status.is_terminal() {
+                        Self::process(&mut message);
+                        Messages::<T>::insert(id, message);
+                    }
+                }
+            }
+            consumed
+        }
+
+        /// Execute a resting message, or expire it if it is past its expiry
+        fn process(message: &mut ActorXMessageOf<T>) {
+            if frame_system::Pallet::<T>::block_number() >= message.expires_at {
+                Self::transition(message, MessageStatus::Expired, None);
+            } else if let Err(error) = Self::execute(message) {
+                Self::transition(message, MessageStatus::Failed, Some(error));
+            }
+        }
+
+        /// Run a message's operation and record the fill and resulting status
+        ///
+        /// Nothing is written if the operation fails.
+        fn execute(message: &mut ActorXMessageOf<T>) -> DispatchResult {
+            if message.operation == ActorXOperation::Kill {
+                Self::process_kill(message)?;
+            }
+
+            let available = ResourceBook::<T>::get(&message.recipient);
+            let execution = book::execute(message.operation, message.quantity, message.filled, available);
+
+            if execution.filled > 0 {
+                ResourceBook::<T>::insert(&message.recipient, available.saturating_sub(execution.filled));
+                message.filled = message.filled.saturating_add(execution.filled);
+                Self::deposit_event(Event::MessageFilled {
+                    id: message.id,
+                    amount: execution.filled,
+                    filled: message.filled,
+                    remaining: message.quantity.saturating_sub(message.filled),
+                });
+            }
+            if execution.status != message.status {
+                Self::transition(message, execution.status, None);
+            }
+            Ok(())
+        }
+
+        /// Messages addressed to `recipient` that match the filters, starting after `cursor`
+        pub fn inbox(
+            recipient: &T::AccountId,
+            status: Option<MessageStatus>,
+            operation: Option<ActorXOperation>,
+            cursor: Option<T::Hash>,
+            limit: u32,
+        ) -> MessagePage<T::Hash, ActorXMessageOf<T>> {
+            let ids = match cursor {
+                Some(cursor) => {
+                    Inbox::<T>::iter_key_prefix_from(recipient, Inbox::<T>::hashed_key_for(recipient, cursor))
+                }
+                None => Inbox::<T>::iter_key_prefix(recipient),
+            };
+            Self::page(ids, status, operation, limit)
+        }
+
+        /// Messages sent by `sender` that match the filters, starting after `cursor`
+        pub fn outbox(
+            sender: &T::AccountId,
+            status: Option<MessageStatus>,
+            operation: Option<ActorXOperation>,
+            cursor: Option<T::Hash>,
+            limit: u32,
+        ) -> MessagePage<T::Hash, ActorXMessageOf<T>> {
+            let ids = match cursor {
+                Some(cursor) => {
+                    Outbox::<T>::iter_key_prefix_from(sender, Outbox::<T>::hashed_key_for(sender, cursor))
+                }
+                None => Outbox::<T>::iter_key_prefix(sender),
+            };
+            Self::page(ids, status, operation, limit)
+        }
+
+        /// Collect up to `limit` matching messages from `ids`
+        ///
+        /// At most `MAX_PAGE_SCAN` IDs are looked at, so a page can hold
+        /// fewer than `limit` messages and still have a next page.
+        fn page(
+            ids: impl Iterator<Item = T::Hash>,
+            status: Option<MessageStatus>,
+            operation: Option<ActorXOperation>,
+            limit: u32,
+        ) -> MessagePage<T::Hash, ActorXMessageOf<T>> {
+            let limit = limit.clamp(1, MAX_PAGE_LEN) as usize;
+            let mut messages = Vec::new();
+            let mut next = None;
+            for (scanned, id) in ids.enumerate() {
+                if messages.len() == limit || scanned == MAX_PAGE_SCAN as usize {
+                    return MessagePage { messages, next };
+                }
+                next = Some(id);
+                if let Some(message) = Messages::<T>::get(id) {
+                    if status.map_or(true, |status| message.status == status)
+                        && operation.map_or(true, |operation| message.operation == operation)
+                    {
+                        messages.push(message);
+                    }
+                }
+            }
+            MessagePage { messages, next: None }
+        }
+
+        /// Decoded payload of a message
+        pub fn payload_of(message: &ActorXMessageOf<T>) -> Result<ActorXPayloadOf<T>, Error<T>> {
+            ActorXPayloadOf::<T>::parse(&message.payload, message.operation).map_err(Error::<T>::from)
+        }
+
+        /// Cancel the resting order named by a `Kill` message's payload
+        fn process_kill(message: &ActorXMessageOf<T>) -> DispatchResult {
+            let ActorXPayload::ValidatorKill { target: target_id } = Self::payload_of(message)? else {
+                return Err(Error::<T>::InvalidKillTarget.into());
+            };
+            let mut target = Messages::<T>::get(target_id).ok_or(Error::<T>::InvalidKillTarget)?;
+            ensure!(
+                target.sender == message.sender
+                    && target.recipient == message.recipient
+                    && target.operation != ActorXOperation::Kill
+                    && !target.status.is_terminal(),
+                Error::<T>::InvalidKillTarget
+            );
 
-/// Process an ActorX Fill and Kill operation
-pub fn process_fill_and_kill<T: frame_system::Config>(
-    message_id: T::Hash,
-) -> DispatchResult {
-    // Implementation would process a Fill and Kill operation
-    // This is a placeholder for the actual implementation
-    Ok(())
+            Self::transition(&mut target, MessageStatus::Cancelled, None);
+            Messages::<T>::insert(target_id, target);
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,227 @@
+//! Mock runtime for ActorX tests
+
+use crate as pallet_actorx;
+use crate::{
+    payload::ActorXPayload,
+    quantum::{DilithiumLevel, QuantumKey},
+    ActorXMessageBody, ActorXOperation,
+};
+use codec::Encode;
+use fips204::{
+    ml_dsa_44,
+    traits::{KeyGen, SerDes, Signer},
+};
+use frame_support::{
+    construct_runtime, parameter_types,
+    traits::{ConstU128, ConstU32, ConstU64, Everything, GenesisBuild, Hooks},
+    weights::Weight,
+    BoundedVec,
+};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Hash, IdentityLookup},
+    DispatchResult,
+};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CHARLIE: AccountId = 3;
+
+/// Deposit of a message with an empty `Custom` payload, which encodes to two bytes
+pub const DEPOSIT: Balance = 10 + 2;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        ActorX: pallet_actorx,
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = BlockNumber;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type WeightInfo = ();
+    type Balance = Balance;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type ReserveIdentifier = [u8; 8];
+    type HoldIdentifier = ();
+    type FreezeIdentifier = ();
+    type MaxLocks = ConstU32<10>;
+    type MaxReserves = ConstU32<10>;
+    type MaxHolds = ConstU32<0>;
+    type MaxFreezes = ConstU32<0>;
+}
+
+parameter_types! {
+    pub SignatureVerifyWeight: Weight = Weight::from_parts(1_000_000, 0);
+}
+
+impl pallet_actorx::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type MaxMessageLifetime = ConstU64<100>;
+    type RetentionPeriod = ConstU64<5>;
+    type MaxPayloadLen = ConstU32<256>;
+    type SignatureVerifyWeight = SignatureVerifyWeight;
+    type Currency = Balances;
+    type PriorityFeeDestination = ();
+    type MessageDepositBase = ConstU128<10>;
+    type MessageDepositPerByte = ConstU128<1>;
+    type MaxPendingPerSender = ConstU32<2>;
+    type MaxQueueLen = ConstU32<4>;
+    type MaxProcessedPerBlock = ConstU32<2>;
+}
+
+/// Funded accounts at block 1
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> { balances: vec![(ALICE, 10_000), (BOB, 10_000), (CHARLIE, 10_000)] }
+        .assimilate_storage(&mut storage)
+        .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
+
+/// Advance to block `n`, running `on_initialize` and giving every block's `on_idle` all the weight it wants
+pub fn run_to_block(n: BlockNumber) {
+    while System::block_number() < n {
+        System::set_block_number(System::block_number() + 1);
+        let now = System::block_number();
+        ActorX::on_initialize(now);
+        ActorX::on_idle(now, Weight::MAX);
+    }
+}
+
+/// Sign `body` with a Dilithium2 (ML-DSA-44) key derived from a fixed seed
+pub fn sign(body: &ActorXMessageBody<AccountId, BlockNumber>) -> (QuantumKey, Vec<u8>) {
+    let (public_key, private_key) = ml_dsa_44::KG::keygen_from_seed(&[7; 32]);
+    let key = QuantumKey {
+        level: DilithiumLevel::Dilithium2,
+        public_key: BoundedVec::truncate_from(public_key.into_bytes().to_vec()),
+    };
+    let signature = body.using_encoded(|data| private_key.try_sign_with_seed(&[1; 32], data, &[]).unwrap());
+    (key, signature.to_vec())
+}
+
+/// Encoded `Custom` payload carrying `data`
+pub fn custom(data: Vec<u8>) -> Vec<u8> {
+    ActorXPayload::<AccountId, H256, BlockNumber>::Custom(data).encode()
+}
+
+/// Encoded `ValidatorKill` payload naming `target`
+pub fn validator_kill(target: H256) -> Vec<u8> {
+    ActorXPayload::<AccountId, H256, BlockNumber>::ValidatorKill { target }.encode()
+}
+
+/// Sign an order carrying the raw `payload` bytes with the sender's current nonce and submit it
+pub fn try_send(
+    sender: AccountId,
+    recipient: AccountId,
+    operation: ActorXOperation,
+    quantity: u128,
+    payload: Vec<u8>,
+    lifetime: BlockNumber,
+) -> DispatchResult {
+    try_send_with_fee(sender, recipient, operation, quantity, payload, lifetime, 0)
+}
+
+/// Like `try_send`, paying `priority_fee` to be executed sooner
+pub fn try_send_with_fee(
+    sender: AccountId,
+    recipient: AccountId,
+    operation: ActorXOperation,
+    quantity: u128,
+    payload: Vec<u8>,
+    lifetime: BlockNumber,
+    priority_fee: Balance,
+) -> DispatchResult {
+    let nonce = crate::Nonces::<Test>::get(sender);
+    let (key, signature) = sign(&ActorXMessageBody {
+        sender,
+        recipient,
+        operation,
+        quantity,
+        payload: payload.clone(),
+        nonce,
+        lifetime,
+    });
+    ActorX::send_message(
+        RuntimeOrigin::signed(sender),
+        recipient,
+        operation,
+        quantity,
+        BoundedVec::truncate_from(payload),
+        key,
+        BoundedVec::truncate_from(signature),
+        lifetime,
+        priority_fee,
+    )
+}
+
+/// Send a signed order from `sender` to `recipient`, returning its ID
+pub fn send(
+    sender: AccountId,
+    recipient: AccountId,
+    operation: ActorXOperation,
+    quantity: u128,
+    payload: Vec<u8>,
+    lifetime: BlockNumber,
+) -> H256 {
+    let nonce = crate::Nonces::<Test>::get(sender);
+    try_send(sender, recipient, operation, quantity, payload.clone(), lifetime).unwrap();
+    BlakeTwo256::hash_of(&(&sender, nonce, &payload))
+}
+
+/// Send a signed `Fill` for `quantity` from `sender` to `recipient` with `priority_fee`, returning its ID
+pub fn send_fill_with_fee(sender: AccountId, recipient: AccountId, quantity: u128, priority_fee: Balance) -> H256 {
+    let (nonce, payload) = (crate::Nonces::<Test>::get(sender), custom(Vec::new()));
+    try_send_with_fee(sender, recipient, ActorXOperation::Fill, quantity, payload.clone(), 10, priority_fee).unwrap();
+    BlakeTwo256::hash_of(&(&sender, nonce, &payload))
+}
+
+/// Send a signed `Fill` for `quantity` from `sender` to `recipient`, returning its ID
+pub fn send_fill(sender: AccountId, recipient: AccountId, quantity: u128, lifetime: BlockNumber) -> H256 {
+    send(sender, recipient, ActorXOperation::Fill, quantity, custom(Vec::new()), lifetime)
+}
@@ -0,0 +1,140 @@
+//! Payload schemas of ActorX messages
+//!
+//! A message's payload is the SCALE encoding of an `ActorXPayload`. The
+//! well-known actions get their own variants; anything else travels as
+//! `Custom` bytes. Payloads are decoded and checked against the message's
+//! operation when the message is sent, so handlers always find a payload
+//! that decodes.
+
+use codec::{Decode, DecodeAll, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{traits::Zero, RuntimeDebug};
+use sp_std::prelude::*;
+
+use crate::ActorXOperation;
+
+/// Decoded ActorX message payload
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum ActorXPayload<AccountId, Hash, BlockNumber> {
+    /// Grant the ordered quantity of the recipient's resources to a validator
+    ValidatorFill {
+        /// Validator receiving the resources
+        validator: AccountId,
+        /// Parachain the validator serves with them
+        parachain_id: u32,
+    },
+    /// Revoke a resting order, such as an earlier validator fill
+    ValidatorKill {
+        /// ID of the order to revoke
+        target: Hash,
+    },
+    /// Ask the recipient to restake the ordered quantity for a parachain
+    RestakeInstruction {
+        /// Parachain to restake for
+        parachain_id: u32,
+        /// Lock duration of the restake in blocks
+        lock_duration: BlockNumber,
+    },
+    /// Application-defined data
+    Custom(Vec<u8>),
+}
+
+/// Reasons a payload does not fit its message
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum PayloadError {
+    /// The bytes are not an `ActorXPayload`
+    Undecodable,
+    /// The payload is not allowed for the operation
+    OperationMismatch,
+    /// A restake instruction has no lock duration
+    ZeroLockDuration,
+}
+
+impl<AccountId, Hash, BlockNumber: Zero> ActorXPayload<AccountId, Hash, BlockNumber> {
+    /// Whether the payload can be carried by a message with `operation`
+    ///
+    /// `Kill` messages must name the order they revoke with
+    /// `ValidatorKill`, which no other operation may carry.
+    pub fn validate(&self, operation: ActorXOperation) -> Result<(), PayloadError> {
+        let is_kill = operation == ActorXOperation::Kill;
+        match self {
+            ActorXPayload::ValidatorKill { .. } if !is_kill => Err(PayloadError::OperationMismatch),
+            ActorXPayload::ValidatorFill { .. } | ActorXPayload::RestakeInstruction { .. } | ActorXPayload::Custom(_)
+                if is_kill =>
+            {
+                Err(PayloadError::OperationMismatch)
+            }
+            ActorXPayload::RestakeInstruction { lock_duration, .. } if lock_duration.is_zero() => {
+                Err(PayloadError::ZeroLockDuration)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<AccountId: Decode, Hash: Decode, BlockNumber: Decode + Zero> ActorXPayload<AccountId, Hash, BlockNumber> {
+    /// Decode a payload and check it against `operation`
+    pub fn parse(bytes: &[u8], operation: ActorXOperation) -> Result<Self, PayloadError> {
+        let payload = Self::decode_all(&mut &bytes[..]).map_err(|_| PayloadError::Undecodable)?;
+        payload.validate(operation)?;
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Payload = ActorXPayload<u64, [u8; 32], u64>;
+
+    const FILLS: [ActorXOperation; 3] =
+        [ActorXOperation::Fill, ActorXOperation::FillOrKill, ActorXOperation::FillAndKill];
+
+    #[test]
+    fn payloads_round_trip() {
+        let fill = Payload::ValidatorFill { validator: 7, parachain_id: 2000 };
+        assert_eq!(Payload::parse(&fill.encode(), ActorXOperation::Fill), Ok(fill));
+
+        let kill = Payload::ValidatorKill { target: [1; 32] };
+        assert_eq!(Payload::parse(&kill.encode(), ActorXOperation::Kill), Ok(kill));
+    }
+
+    #[test]
+    fn undecodable_bytes_are_rejected() {
+        assert_eq!(Payload::parse(&[], ActorXOperation::Fill), Err(PayloadError::Undecodable));
+        assert_eq!(Payload::parse(&[9], ActorXOperation::Fill), Err(PayloadError::Undecodable));
+
+        // Trailing bytes after a valid payload
+        let mut bytes = Payload::Custom(vec![1]).encode();
+        bytes.push(0);
+        assert_eq!(Payload::parse(&bytes, ActorXOperation::Fill), Err(PayloadError::Undecodable));
+    }
+
+    #[test]
+    fn validator_kills_are_only_carried_by_kills() {
+        let kill = Payload::ValidatorKill { target: [1; 32] };
+        for operation in FILLS {
+            assert_eq!(kill.validate(operation), Err(PayloadError::OperationMismatch));
+        }
+    }
+
+    #[test]
+    fn kills_only_carry_validator_kills() {
+        for payload in [
+            Payload::ValidatorFill { validator: 7, parachain_id: 2000 },
+            Payload::RestakeInstruction { parachain_id: 2000, lock_duration: 10 },
+            Payload::Custom(Vec::new()),
+        ] {
+            assert_eq!(payload.validate(ActorXOperation::Kill), Err(PayloadError::OperationMismatch));
+            for operation in FILLS {
+                assert_eq!(payload.validate(operation), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn restake_instructions_need_a_lock_duration() {
+        let payload = Payload::RestakeInstruction { parachain_id: 2000, lock_duration: 0 };
+        assert_eq!(payload.validate(ActorXOperation::Fill), Err(PayloadError::ZeroLockDuration));
+    }
+}
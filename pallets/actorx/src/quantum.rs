@@ -0,0 +1,181 @@
+//! CRYSTALS-Dilithium keys and signatures of ActorX messages
+//!
+//! Senders sign the SCALE encoding of a message's body with a Dilithium key
+//! at any of the standard security levels, as standardized in FIPS 204:
+//! Dilithium2, 3 and 5 are ML-DSA-44, 65 and 87. Signatures use an empty
+//! context string. Keys and signatures are bounded by the sizes of the
+//! largest level, Dilithium5, and must have exactly the size of the level
+//! they declare.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use fips204::{
+    ml_dsa_44, ml_dsa_65, ml_dsa_87,
+    traits::{SerDes, Verifier},
+};
+use frame_support::{traits::ConstU32, BoundedVec};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+
+/// Public key size of Dilithium2
+pub const DILITHIUM2_PUBLIC_KEY_LEN: u32 = ml_dsa_44::PK_LEN as u32;
+/// Signature size of Dilithium2
+pub const DILITHIUM2_SIGNATURE_LEN: u32 = ml_dsa_44::SIG_LEN as u32;
+/// Public key size of Dilithium3
+pub const DILITHIUM3_PUBLIC_KEY_LEN: u32 = ml_dsa_65::PK_LEN as u32;
+/// Signature size of Dilithium3
+pub const DILITHIUM3_SIGNATURE_LEN: u32 = ml_dsa_65::SIG_LEN as u32;
+/// Public key size of Dilithium5
+pub const DILITHIUM5_PUBLIC_KEY_LEN: u32 = ml_dsa_87::PK_LEN as u32;
+/// Signature size of Dilithium5
+pub const DILITHIUM5_SIGNATURE_LEN: u32 = ml_dsa_87::SIG_LEN as u32;
+
+/// Max public key size, that of Dilithium5
+pub type MaxPublicKeyLen = ConstU32<DILITHIUM5_PUBLIC_KEY_LEN>;
+/// Max signature size, that of Dilithium5
+pub type MaxSignatureLen = ConstU32<DILITHIUM5_SIGNATURE_LEN>;
+
+/// Dilithium signature
+pub type QuantumSignature = BoundedVec<u8, MaxSignatureLen>;
+
+/// Dilithium security level
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum DilithiumLevel {
+    /// NIST security level 2
+    Dilithium2,
+    /// NIST security level 3
+    Dilithium3,
+    /// NIST security level 5
+    Dilithium5,
+}
+
+impl DilithiumLevel {
+    /// Public key size at this level
+    pub fn public_key_len(&self) -> u32 {
+        match self {
+            DilithiumLevel::Dilithium2 => DILITHIUM2_PUBLIC_KEY_LEN,
+            DilithiumLevel::Dilithium3 => DILITHIUM3_PUBLIC_KEY_LEN,
+            DilithiumLevel::Dilithium5 => DILITHIUM5_PUBLIC_KEY_LEN,
+        }
+    }
+
+    /// Signature size at this level
+    pub fn signature_len(&self) -> u32 {
+        match self {
+            DilithiumLevel::Dilithium2 => DILITHIUM2_SIGNATURE_LEN,
+            DilithiumLevel::Dilithium3 => DILITHIUM3_SIGNATURE_LEN,
+            DilithiumLevel::Dilithium5 => DILITHIUM5_SIGNATURE_LEN,
+        }
+    }
+}
+
+/// Dilithium public key
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct QuantumKey {
+    /// Security level of the key
+    pub level: DilithiumLevel,
+    /// Encoded public key
+    pub public_key: BoundedVec<u8, MaxPublicKeyLen>,
+}
+
+impl QuantumKey {
+    /// Whether the public key has the size of its level
+    pub fn is_well_formed(&self) -> bool {
+        self.public_key.len() as u32 == self.level.public_key_len()
+    }
+
+    /// Whether `signature` is a valid signature of `data` under this key
+    pub fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        if !self.is_well_formed() || signature.len() as u32 != self.level.signature_len() {
+            return false;
+        }
+        match self.level {
+            DilithiumLevel::Dilithium2 => verify::<ml_dsa_44::PublicKey, _, _>(&self.public_key, data, signature),
+            DilithiumLevel::Dilithium3 => verify::<ml_dsa_65::PublicKey, _, _>(&self.public_key, data, signature),
+            DilithiumLevel::Dilithium5 => verify::<ml_dsa_87::PublicKey, _, _>(&self.public_key, data, signature),
+        }
+    }
+}
+
+/// Verify `signature` of `data` under an ML-DSA public key of type `PublicKey`
+fn verify<PublicKey, Key, Signature>(public_key: &[u8], data: &[u8], signature: &[u8]) -> bool
+where
+    PublicKey: SerDes<ByteArray = Key> + Verifier<Signature = Signature>,
+    Key: for<'a> TryFrom<&'a [u8]>,
+    Signature: for<'a> TryFrom<&'a [u8]>,
+{
+    let (Ok(public_key), Ok(signature)) = (Key::try_from(public_key), Signature::try_from(signature)) else {
+        return false;
+    };
+    match PublicKey::try_from_bytes(public_key) {
+        Ok(public_key) => public_key.verify(data, &signature, &[]),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fips204::traits::{KeyGen, Signer};
+
+    const DATA: &[u8] = b"actorx message body";
+
+    /// Key and signature of `DATA` at `level`, from fixed seeds
+    fn signed(level: DilithiumLevel) -> (QuantumKey, Vec<u8>) {
+        let (public_key, signature) = match level {
+            DilithiumLevel::Dilithium2 => {
+                let (public_key, private_key) = ml_dsa_44::KG::keygen_from_seed(&[7; 32]);
+                let signature = private_key.try_sign_with_seed(&[1; 32], DATA, &[]).unwrap();
+                (public_key.into_bytes().to_vec(), signature.to_vec())
+            }
+            DilithiumLevel::Dilithium3 => {
+                let (public_key, private_key) = ml_dsa_65::KG::keygen_from_seed(&[7; 32]);
+                let signature = private_key.try_sign_with_seed(&[1; 32], DATA, &[]).unwrap();
+                (public_key.into_bytes().to_vec(), signature.to_vec())
+            }
+            DilithiumLevel::Dilithium5 => {
+                let (public_key, private_key) = ml_dsa_87::KG::keygen_from_seed(&[7; 32]);
+                let signature = private_key.try_sign_with_seed(&[1; 32], DATA, &[]).unwrap();
+                (public_key.into_bytes().to_vec(), signature.to_vec())
+            }
+        };
+        (QuantumKey { level, public_key: BoundedVec::truncate_from(public_key) }, signature)
+    }
+
+    const LEVELS: [DilithiumLevel; 3] =
+        [DilithiumLevel::Dilithium2, DilithiumLevel::Dilithium3, DilithiumLevel::Dilithium5];
+
+    #[test]
+    fn valid_signatures_verify_at_every_level() {
+        for level in LEVELS {
+            let (key, signature) = signed(level);
+            assert!(key.is_well_formed());
+            assert_eq!(signature.len() as u32, level.signature_len());
+            assert!(key.verify(DATA, &signature), "{:?}", level);
+        }
+    }
+
+    #[test]
+    fn tampered_signatures_are_rejected_at_every_level() {
+        for level in LEVELS {
+            let (key, signature) = signed(level);
+
+            let mut tampered = signature.clone();
+            tampered[0] ^= 1;
+            assert!(!key.verify(DATA, &tampered), "{:?}", level);
+            assert!(!key.verify(b"another message body", &signature), "{:?}", level);
+            assert!(!key.verify(DATA, &signature[1..]), "{:?}", level);
+
+            let mut other_key = key.clone();
+            other_key.public_key[0] ^= 1;
+            assert!(!other_key.verify(DATA, &signature), "{:?}", level);
+        }
+    }
+
+    #[test]
+    fn keys_must_match_their_level() {
+        let (mut key, signature) = signed(DilithiumLevel::Dilithium2);
+        key.level = DilithiumLevel::Dilithium3;
+        assert!(!key.is_well_formed());
+        assert!(!key.verify(DATA, &signature));
+    }
+}
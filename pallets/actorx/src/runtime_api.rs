@@ -0,0 +1,45 @@
+//! Runtime API for querying ActorX messages
+
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+use crate::{ActorXOperation, MessageStatus};
+
+/// Page of messages and the cursor to pass for the next page
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MessagePage<Hash, Message> {
+    /// Messages matching the filters
+    pub messages: Vec<Message>,
+    /// Last message ID looked at, `None` once all messages were looked at
+    pub next: Option<Hash>,
+}
+
+sp_api::decl_runtime_apis! {
+    /// ActorX queries for wallets and providers
+    pub trait ActorXApi<AccountId, Hash, Message>
+    where
+        AccountId: Codec,
+        Hash: Codec,
+        Message: Codec,
+    {
+        /// Messages addressed to `recipient`, starting after `cursor`
+        fn inbox(
+            recipient: AccountId,
+            status: Option<MessageStatus>,
+            operation: Option<ActorXOperation>,
+            cursor: Option<Hash>,
+            limit: u32,
+        ) -> MessagePage<Hash, Message>;
+
+        /// Messages sent by `sender`, starting after `cursor`
+        fn outbox(
+            sender: AccountId,
+            status: Option<MessageStatus>,
+            operation: Option<ActorXOperation>,
+            cursor: Option<Hash>,
+            limit: u32,
+        ) -> MessagePage<Hash, Message>;
+    }
+}
@@ -0,0 +1,587 @@
+//! ActorX tests
+
+use crate::{
+    mock::*, payload::ActorXPayload, quantum::QuantumKey, runtime_api::MessagePage, ActorXMessageBody,
+    ActorXOperation, Error, Event, ExpiryIndex, Inbox, Messages, MessageStatus, Nonces, Outbox, PendingCount,
+    ProcessingQueue, PruneIndex, ResourceBook, MAX_PAGE_SCAN,
+};
+use codec::Encode;
+use frame_support::{assert_noop, assert_ok, traits::Hooks, weights::Weight, BoundedVec};
+use sp_core::H256;
+use sp_runtime::DispatchResult;
+
+fn status(id: H256) -> Option<MessageStatus> {
+    Messages::<Test>::get(id).map(|message| message.status)
+}
+
+/// ActorX events deposited so far, oldest first
+fn events() -> Vec<Event<Test>> {
+    System::events()
+        .into_iter()
+        .filter_map(|record| match record.event {
+            RuntimeEvent::ActorX(event) => Some(event),
+            _ => None,
+        })
+        .collect()
+}
+
+fn set_capacity(provider: AccountId, capacity: u128) {
+    assert_ok!(ActorX::set_capacity(RuntimeOrigin::signed(provider), capacity));
+}
+
+fn process(id: H256) {
+    assert_ok!(ActorX::process_message(RuntimeOrigin::signed(BOB), id));
+}
+
+#[test]
+fn sending_stores_a_pending_message() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 10);
+        let message = Messages::<Test>::get(id).unwrap();
+        assert_eq!(message.status, MessageStatus::Pending);
+        assert_eq!((message.quantity, message.filled), (100, 0));
+        assert_eq!((message.created_at, message.expires_at), (1, 11));
+        assert_eq!(Nonces::<Test>::get(ALICE), 1);
+        System::assert_last_event(
+            Event::MessageSent {
+                id,
+                sender: ALICE,
+                recipient: BOB,
+                operation: ActorXOperation::Fill,
+                quantity: 100,
+                deposit: DEPOSIT,
+                priority_fee: 0,
+                expires_at: 11,
+            }
+            .into(),
+        );
+
+        // The same payload gets a new ID with the next nonce
+        assert_ne!(send_fill(ALICE, BOB, 100, 10), id);
+    });
+}
+
+#[test]
+fn lifetimes_must_be_positive_and_bounded() {
+    new_test_ext().execute_with(|| {
+        for lifetime in [0, 101] {
+            assert_noop!(
+                try_send(ALICE, BOB, ActorXOperation::Fill, 100, custom(Vec::new()), lifetime),
+                Error::<Test>::InvalidLifetime
+            );
+        }
+    });
+}
+
+#[test]
+fn fill_orders_need_a_quantity() {
+    new_test_ext().execute_with(|| {
+        for operation in [ActorXOperation::Fill, ActorXOperation::FillOrKill, ActorXOperation::FillAndKill] {
+            assert_noop!(try_send(ALICE, BOB, operation, 0, custom(Vec::new()), 10), Error::<Test>::ZeroQuantity);
+        }
+    });
+}
+
+/// Body of a `Fill` for 100 from ALICE to BOB with ALICE's current nonce
+fn fill_body() -> ActorXMessageBody<AccountId, BlockNumber> {
+    ActorXMessageBody {
+        sender: ALICE,
+        recipient: BOB,
+        operation: ActorXOperation::Fill,
+        quantity: 100,
+        payload: custom(Vec::new()),
+        nonce: Nonces::<Test>::get(ALICE),
+        lifetime: 10,
+    }
+}
+
+fn send_signed(key: QuantumKey, signature: Vec<u8>) -> DispatchResult {
+    ActorX::send_message(
+        RuntimeOrigin::signed(ALICE),
+        BOB,
+        ActorXOperation::Fill,
+        100,
+        BoundedVec::truncate_from(custom(Vec::new())),
+        key,
+        BoundedVec::truncate_from(signature),
+        10,
+        0,
+    )
+}
+
+#[test]
+fn messages_need_a_signature_by_a_well_formed_key() {
+    new_test_ext().execute_with(|| {
+        let (key, signature) = sign(&fill_body());
+        assert_noop!(send_signed(key.clone(), Vec::new()), Error::<Test>::MissingSignature);
+
+        let mut truncated = key.clone();
+        truncated.public_key.pop();
+        assert_noop!(send_signed(truncated, signature.clone()), Error::<Test>::InvalidQuantumKey);
+
+        assert_ok!(send_signed(key, signature));
+    });
+}
+
+#[test]
+fn signatures_must_cover_the_message_body() {
+    new_test_ext().execute_with(|| {
+        let (key, signature) = sign(&ActorXMessageBody { quantity: 99, ..fill_body() });
+        assert_noop!(send_signed(key, signature), Error::<Test>::InvalidSignature);
+
+        // A signature is only good for the nonce it was made with
+        let (key, signature) = sign(&fill_body());
+        assert_ok!(send_signed(key.clone(), signature.clone()));
+        assert_noop!(send_signed(key, signature), Error::<Test>::InvalidSignature);
+    });
+}
+
+#[test]
+fn payloads_must_fit_the_operation() {
+    new_test_ext().execute_with(|| {
+        let restake = ActorXPayload::<AccountId, H256, BlockNumber>::RestakeInstruction {
+            parachain_id: 2000,
+            lock_duration: 0,
+        };
+        let cases = [
+            (ActorXOperation::Fill, vec![9], Error::<Test>::UndecodablePayload),
+            (ActorXOperation::Fill, validator_kill(H256::repeat_byte(1)), Error::<Test>::PayloadMismatch),
+            (ActorXOperation::Kill, custom(Vec::new()), Error::<Test>::PayloadMismatch),
+            (ActorXOperation::Fill, restake.encode(), Error::<Test>::ZeroLockDuration),
+        ];
+        for (operation, payload, error) in cases {
+            assert_noop!(try_send(ALICE, BOB, operation, 100, payload, 10), error);
+        }
+    });
+}
+
+#[test]
+fn processing_fills_from_the_recipients_capacity() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 10);
+        set_capacity(BOB, 60);
+        System::reset_events();
+
+        assert_noop!(ActorX::process_message(RuntimeOrigin::signed(ALICE), id), Error::<Test>::NotRecipient);
+        process(id);
+        assert_eq!(ResourceBook::<Test>::get(BOB), 0);
+        assert_eq!(Messages::<Test>::get(id).unwrap().filled, 60);
+        assert_eq!(status(id), Some(MessageStatus::InProgress));
+        assert_eq!(
+            events(),
+            vec![Event::MessageFilled { id, amount: 60, filled: 60, remaining: 40 }, Event::MessageInProgress { id }]
+        );
+
+        // The resting remainder fills once the provider has more capacity
+        set_capacity(BOB, 50);
+        System::reset_events();
+        process(id);
+        assert_eq!(ResourceBook::<Test>::get(BOB), 10);
+        assert_eq!(status(id), Some(MessageStatus::Completed));
+        assert_eq!(
+            events(),
+            vec![Event::MessageFilled { id, amount: 40, filled: 100, remaining: 0 }, Event::MessageCompleted { id }]
+        );
+
+        assert_noop!(ActorX::process_message(RuntimeOrigin::signed(BOB), id), Error::<Test>::InvalidStatus);
+    });
+}
+
+#[test]
+fn fill_orders_without_capacity_keep_resting() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 10);
+        System::reset_events();
+
+        process(id);
+        assert_eq!(status(id), Some(MessageStatus::Pending));
+        assert!(events().is_empty());
+    });
+}
+
+#[test]
+fn immediate_orders_take_capacity_or_are_cancelled() {
+    new_test_ext().execute_with(|| {
+        set_capacity(BOB, 60);
+        let fill_or_kill = send(ALICE, BOB, ActorXOperation::FillOrKill, 100, custom(vec![1]), 10);
+        let fill_and_kill = send(ALICE, BOB, ActorXOperation::FillAndKill, 100, custom(vec![2]), 10);
+        System::reset_events();
+
+        process(fill_or_kill);
+        assert_eq!(status(fill_or_kill), Some(MessageStatus::Cancelled));
+        assert_eq!(ResourceBook::<Test>::get(BOB), 60);
+        assert_eq!(events(), vec![Event::MessageCancelled { id: fill_or_kill }]);
+
+        System::reset_events();
+        process(fill_and_kill);
+        assert_eq!(status(fill_and_kill), Some(MessageStatus::Cancelled));
+        assert_eq!(Messages::<Test>::get(fill_and_kill).unwrap().filled, 60);
+        assert_eq!(ResourceBook::<Test>::get(BOB), 0);
+        assert_eq!(
+            events(),
+            vec![
+                Event::MessageFilled { id: fill_and_kill, amount: 60, filled: 60, remaining: 40 },
+                Event::MessageCancelled { id: fill_and_kill },
+            ]
+        );
+    });
+}
+
+#[test]
+fn kill_cancels_a_resting_order() {
+    new_test_ext().execute_with(|| {
+        let order = send_fill(ALICE, BOB, 100, 10);
+        let kill = send(ALICE, BOB, ActorXOperation::Kill, 0, validator_kill(order), 10);
+        System::reset_events();
+
+        process(kill);
+        assert_eq!(status(order), Some(MessageStatus::Cancelled));
+        assert_eq!(status(kill), Some(MessageStatus::Completed));
+        assert_eq!(events(), vec![Event::MessageCancelled { id: order }, Event::MessageCompleted { id: kill }]);
+
+        // The cancelled order can no longer be filled
+        set_capacity(BOB, 100);
+        assert_noop!(ActorX::process_message(RuntimeOrigin::signed(BOB), order), Error::<Test>::InvalidStatus);
+    });
+}
+
+#[test]
+fn kill_without_a_valid_target_fails() {
+    new_test_ext().execute_with(|| {
+        let order = send_fill(BOB, BOB, 100, 10);
+        let unknown = send(ALICE, BOB, ActorXOperation::Kill, 0, validator_kill(H256::repeat_byte(9)), 10);
+        let foreign = send(ALICE, BOB, ActorXOperation::Kill, 0, validator_kill(order), 10);
+        System::reset_events();
+
+        for kill in [unknown, foreign] {
+            process(kill);
+            assert_eq!(status(kill), Some(MessageStatus::Failed));
+        }
+        assert_eq!(status(order), Some(MessageStatus::Pending));
+        let error = Error::<Test>::InvalidKillTarget.into();
+        assert_eq!(
+            events(),
+            vec![Event::MessageFailed { id: unknown, error }, Event::MessageFailed { id: foreign, error }]
+        );
+    });
+}
+
+#[test]
+fn processing_an_expired_message_expires_it() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 10);
+        System::reset_events();
+
+        System::set_block_number(11);
+        process(id);
+        assert_eq!(status(id), Some(MessageStatus::Expired));
+        assert_eq!(events(), vec![Event::MessageExpired { id }]);
+    });
+}
+
+#[test]
+fn only_the_sender_cancels_resting_messages() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 10);
+        System::reset_events();
+
+        assert_noop!(ActorX::cancel_message(RuntimeOrigin::signed(BOB), id), Error::<Test>::NotSender);
+        assert_ok!(ActorX::cancel_message(RuntimeOrigin::signed(ALICE), id));
+        assert_eq!(status(id), Some(MessageStatus::Cancelled));
+        assert_eq!(events(), vec![Event::MessageCancelled { id }]);
+
+        assert_noop!(ActorX::cancel_message(RuntimeOrigin::signed(ALICE), id), Error::<Test>::InvalidStatus);
+        assert_noop!(ActorX::process_message(RuntimeOrigin::signed(BOB), id), Error::<Test>::InvalidStatus);
+    });
+}
+
+#[test]
+fn messages_expire_at_their_expiry_block() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 10);
+
+        run_to_block(10);
+        assert_eq!(status(id), Some(MessageStatus::Pending));
+
+        run_to_block(11);
+        assert_eq!(status(id), Some(MessageStatus::Expired));
+        assert!(ExpiryIndex::<Test>::iter().next().is_none());
+        System::assert_has_event(Event::MessageExpired { id }.into());
+    });
+}
+
+#[test]
+fn expiry_releases_the_deposit_and_pending_slot() {
+    new_test_ext().execute_with(|| {
+        send_fill(ALICE, BOB, 100, 5);
+        send_fill(ALICE, BOB, 100, 5);
+        assert_eq!(PendingCount::<Test>::get(ALICE), 2);
+        assert_eq!(Balances::reserved_balance(ALICE), 2 * DEPOSIT);
+
+        run_to_block(6);
+        assert_eq!(PendingCount::<Test>::get(ALICE), 0);
+        assert_eq!(Balances::reserved_balance(ALICE), 0);
+        assert_eq!(Balances::free_balance(ALICE), 10_000);
+
+        // The freed slots can be used again
+        send_fill(ALICE, BOB, 100, 5);
+        assert_eq!(PendingCount::<Test>::get(ALICE), 1);
+    });
+}
+
+#[test]
+fn terminal_messages_are_pruned_after_the_retention_period() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 10);
+        run_to_block(11);
+        assert_eq!(status(id), Some(MessageStatus::Expired));
+
+        run_to_block(15);
+        assert!(Messages::<Test>::contains_key(id));
+
+        run_to_block(16);
+        assert!(!Messages::<Test>::contains_key(id));
+        assert!(!Inbox::<Test>::contains_key(BOB, id));
+        assert!(!Outbox::<Test>::contains_key(ALICE, id));
+        assert!(PruneIndex::<Test>::iter().next().is_none());
+    });
+}
+
+#[test]
+fn cancelled_messages_are_settled_and_pruned() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 50);
+        run_to_block(3);
+        assert_ok!(ActorX::cancel_message(RuntimeOrigin::signed(ALICE), id));
+        assert!(ExpiryIndex::<Test>::iter().next().is_none());
+
+        run_to_block(8);
+        assert!(!Messages::<Test>::contains_key(id));
+    });
+}
+
+#[test]
+fn messages_indexed_before_the_first_sweep_still_expire_and_prune() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill(ALICE, BOB, 100, 2);
+
+        // Blocks are full until well past the expiry, so `on_idle` never runs
+        System::set_block_number(6);
+        assert_eq!(status(id), Some(MessageStatus::Pending));
+
+        ActorX::on_idle(6, Weight::MAX);
+        assert_eq!(status(id), Some(MessageStatus::Expired));
+
+        // Pruning is due at 11, and still happens when the sweep first runs later
+        System::set_block_number(20);
+        ActorX::on_idle(20, Weight::MAX);
+        assert!(!Messages::<Test>::contains_key(id));
+    });
+}
+
+#[test]
+fn sending_reserves_a_deposit_and_withdraws_the_priority_fee() {
+    new_test_ext().execute_with(|| {
+        let id = send_fill_with_fee(ALICE, BOB, 100, 7);
+        assert_eq!(Balances::reserved_balance(ALICE), DEPOSIT);
+        assert_eq!(Balances::free_balance(ALICE), 10_000 - DEPOSIT - 7);
+        assert_eq!(Messages::<Test>::get(id).unwrap().priority_fee, 7);
+
+        // Settling releases the deposit but not the fee
+        assert_ok!(ActorX::cancel_message(RuntimeOrigin::signed(ALICE), id));
+        assert_eq!(Balances::reserved_balance(ALICE), 0);
+        assert_eq!(Balances::free_balance(ALICE), 10_000 - 7);
+    });
+}
+
+#[test]
+fn pending_messages_are_capped_per_sender() {
+    new_test_ext().execute_with(|| {
+        let first = send_fill(ALICE, BOB, 100, 10);
+        send_fill(ALICE, BOB, 100, 10);
+        assert_eq!(PendingCount::<Test>::get(ALICE), 2);
+        assert_noop!(
+            try_send(ALICE, BOB, ActorXOperation::Fill, 100, custom(vec![1]), 10),
+            Error::<Test>::TooManyPending
+        );
+
+        // Other senders have their own cap
+        send_fill(CHARLIE, BOB, 100, 10);
+
+        assert_ok!(ActorX::cancel_message(RuntimeOrigin::signed(ALICE), first));
+        assert_eq!(PendingCount::<Test>::get(ALICE), 1);
+        assert_ok!(try_send(ALICE, BOB, ActorXOperation::Fill, 100, custom(vec![1]), 10));
+    });
+}
+
+#[test]
+fn a_full_queue_drops_its_lowest_fee_message() {
+    new_test_ext().execute_with(|| {
+        let five = send_fill_with_fee(ALICE, BOB, 10, 5);
+        let one = send_fill_with_fee(ALICE, BOB, 10, 1);
+        let three = send_fill_with_fee(BOB, BOB, 10, 3);
+        let four = send_fill_with_fee(BOB, BOB, 10, 4);
+        let queued = |queue: Vec<(Balance, H256)>| queue.into_iter().map(|(_, id)| id).collect::<Vec<_>>();
+        assert_eq!(queued(ProcessingQueue::<Test>::get().into_inner()), vec![five, four, three, one]);
+
+        let two = send_fill_with_fee(CHARLIE, BOB, 10, 2);
+        assert_eq!(queued(ProcessingQueue::<Test>::get().into_inner()), vec![five, four, three, two]);
+        // The dropped message stays resting for its recipient
+        assert_eq!(status(one), Some(MessageStatus::Pending));
+
+        // Nothing queued has a lower fee than a free message
+        assert_noop!(
+            try_send(CHARLIE, BOB, ActorXOperation::Fill, 10, custom(vec![1]), 10),
+            Error::<Test>::QueueFull
+        );
+    });
+}
+
+#[test]
+fn queued_messages_are_executed_in_fee_order_on_initialize() {
+    new_test_ext().execute_with(|| {
+        set_capacity(BOB, 100);
+        let low = send_fill_with_fee(ALICE, BOB, 10, 1);
+        let high = send_fill_with_fee(ALICE, BOB, 20, 9);
+        let middle = send_fill_with_fee(CHARLIE, BOB, 30, 5);
+
+        // Two messages are executed per block, highest fees first
+        run_to_block(2);
+        assert_eq!(status(high), Some(MessageStatus::Completed));
+        assert_eq!(status(middle), Some(MessageStatus::Completed));
+        assert_eq!(status(low), Some(MessageStatus::Pending));
+        assert_eq!(ResourceBook::<Test>::get(BOB), 50);
+        assert_eq!(ProcessingQueue::<Test>::get().len(), 1);
+
+        run_to_block(3);
+        assert_eq!(status(low), Some(MessageStatus::Completed));
+        assert_eq!(ResourceBook::<Test>::get(BOB), 40);
+        assert!(ProcessingQueue::<Test>::get().is_empty());
+        assert_eq!(PendingCount::<Test>::get(ALICE), 0);
+    });
+}
+
+#[test]
+fn processing_a_queued_message_takes_it_out_of_the_queue() {
+    new_test_ext().execute_with(|| {
+        set_capacity(BOB, 100);
+        let id = send_fill_with_fee(ALICE, BOB, 30, 1);
+
+        process(id);
+        assert_eq!(ResourceBook::<Test>::get(BOB), 70);
+        assert!(ProcessingQueue::<Test>::get().is_empty());
+
+        run_to_block(2);
+        assert_eq!(ResourceBook::<Test>::get(BOB), 70);
+    });
+}
+
+#[test]
+fn resting_fills_leave_the_queue_once_executed() {
+    new_test_ext().execute_with(|| {
+        set_capacity(BOB, 60);
+        let id = send_fill_with_fee(ALICE, BOB, 100, 1);
+
+        run_to_block(2);
+        assert_eq!(status(id), Some(MessageStatus::InProgress));
+        assert_eq!(Messages::<Test>::get(id).unwrap().filled, 60);
+        assert!(ProcessingQueue::<Test>::get().is_empty());
+
+        // More capacity does not bring the order back to the queue
+        set_capacity(BOB, 100);
+        run_to_block(5);
+        assert_eq!(status(id), Some(MessageStatus::InProgress));
+        assert_eq!(ResourceBook::<Test>::get(BOB), 100);
+
+        // Its recipient fills the rest
+        process(id);
+        assert_eq!(status(id), Some(MessageStatus::Completed));
+        assert_eq!(ResourceBook::<Test>::get(BOB), 60);
+    });
+}
+
+/// IDs of the messages on a page
+fn ids(page: &MessagePage<H256, crate::ActorXMessageOf<Test>>) -> Vec<H256> {
+    page.messages.iter().map(|message| message.id).collect()
+}
+
+#[test]
+fn inbox_and_outbox_filter_by_status_and_operation() {
+    new_test_ext().execute_with(|| {
+        let fill = send_fill(ALICE, BOB, 100, 10);
+        let fill_or_kill = send(ALICE, BOB, ActorXOperation::FillOrKill, 100, custom(vec![1]), 10);
+        let other = send_fill(CHARLIE, ALICE, 100, 10);
+        assert_ok!(ActorX::cancel_message(RuntimeOrigin::signed(ALICE), fill));
+
+        let mut inbox = ids(&ActorX::inbox(&BOB, None, None, None, 10));
+        inbox.sort();
+        let mut expected = vec![fill, fill_or_kill];
+        expected.sort();
+        assert_eq!(inbox, expected);
+        assert_eq!(ids(&ActorX::outbox(&CHARLIE, None, None, None, 10)), vec![other]);
+        assert_eq!(ids(&ActorX::inbox(&ALICE, None, None, None, 10)), vec![other]);
+
+        assert_eq!(ids(&ActorX::inbox(&BOB, Some(MessageStatus::Cancelled), None, None, 10)), vec![fill]);
+        assert_eq!(ids(&ActorX::inbox(&BOB, Some(MessageStatus::Pending), None, None, 10)), vec![fill_or_kill]);
+        assert_eq!(
+            ids(&ActorX::outbox(&ALICE, None, Some(ActorXOperation::FillOrKill), None, 10)),
+            vec![fill_or_kill]
+        );
+        assert!(ActorX::outbox(&ALICE, Some(MessageStatus::Cancelled), Some(ActorXOperation::FillOrKill), None, 10)
+            .messages
+            .is_empty());
+    });
+}
+
+#[test]
+fn pages_follow_the_cursor_until_every_message_was_seen() {
+    new_test_ext().execute_with(|| {
+        let mut sent = Vec::new();
+        for sender in [ALICE, CHARLIE] {
+            sent.push(send_fill(sender, BOB, 100, 10));
+            sent.push(send(sender, BOB, ActorXOperation::FillAndKill, 100, custom(vec![1]), 10));
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let page = ActorX::inbox(&BOB, None, None, cursor, 3);
+            assert!(page.messages.len() <= 3);
+            seen.extend(ids(&page));
+            pages += 1;
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 2);
+        seen.sort();
+        sent.sort();
+        assert_eq!(seen, sent);
+
+        // A zero limit still returns a message
+        assert_eq!(ActorX::inbox(&BOB, None, None, None, 0).messages.len(), 1);
+    });
+}
+
+#[test]
+fn pages_stop_after_scanning_max_page_scan_entries() {
+    new_test_ext().execute_with(|| {
+        // Index entries whose messages are gone, as if pruned mid-way
+        for i in 0..MAX_PAGE_SCAN as u64 {
+            Inbox::<Test>::insert(BOB, H256::from_low_u64_be(i), ());
+        }
+        let id = send_fill(ALICE, BOB, 100, 10);
+
+        let first = ActorX::inbox(&BOB, None, None, None, 10);
+        let next = first.next.expect("the scan stops before the last entry");
+        let second = ActorX::inbox(&BOB, None, None, Some(next), 10);
+        assert_eq!(second.next, None);
+
+        // The message is on exactly one of the pages
+        let mut found = ids(&first);
+        found.extend(ids(&second));
+        assert_eq!(found, vec![id]);
+    });
+}
@@ -2,6 +2,10 @@
 //!
 //! This pallet implements security measures for the entire Matrix-Magiq ecosystem
 //! through validator coordination and restaking mechanisms.
+//!
+//! Sessions last `SessionDuration` blocks. At the start of every block past
+//! the end of the current session, `OnSessionEnd` is notified and the next
+//! session begins.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -11,6 +15,10 @@ pub use pallet::*;
 pub mod pallet {
     use frame_support::pallet_prelude::*;
     use frame_system::pallet_prelude::*;
+    use sp_runtime::{
+        traits::{AtLeast32BitUnsigned, One, Saturating, Zero},
+        Perbill,
+    };
     use sp_std::vec::Vec;
     
     #[pallet::pallet]
@@ -33,11 +41,18 @@ pub mod pallet {
         
         /// Minimum stake amount
         #[pallet::constant]
-        type MinStakeAmount: Get<BalanceOf<T>>;
+        type MinStakeAmount: Get<BalanceOf<Self>>;
         
         /// Session duration in blocks
         #[pallet::constant]
-        type SessionDuration: Get<T::BlockNumber>;
+        type SessionDuration: Get<Self::BlockNumber>;
+
+        /// Max stakers per validator
+        #[pallet::constant]
+        type MaxNominatorsPerValidator: Get<u32>;
+
+        /// Handler called when a session ends
+        type OnSessionEnd: OnSessionEnd;
     }
 
     /// Active validator sets for each parachain
@@ -70,6 +85,16 @@ pub mod pallet {
         StakeInfo<T>,
     >;
     
+    /// Stakers of each validator, indexing `Stakes` by validator
+    #[pallet::storage]
+    pub type ValidatorStakers<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::ValidatorId,
+        BoundedVec<T::AccountId, T::MaxNominatorsPerValidator>,
+        ValueQuery,
+    >;
+
     /// Total stake per validator
     #[pallet::storage]
     pub type TotalStake<T: Config> = StorageMap<
@@ -86,6 +111,7 @@ pub mod pallet {
     
     /// Validator representation
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
     pub struct Validator<T: Config> {
         /// Validator ID
         pub id: T::ValidatorId,
@@ -99,6 +125,7 @@ pub mod pallet {
     
     /// Validator profile
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
     pub struct ValidatorProfile<T: Config> {
         /// Validator ID
         pub id: T::ValidatorId,
@@ -131,6 +158,35 @@ pub mod pallet {
         Exited,
     }
     
+    /// Offences a validator can be slashed for
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum Offence {
+        /// Signed conflicting blocks or votes
+        Equivocation,
+        /// Failed to perform its duties while selected
+        Unavailability,
+        /// Attested to an invalid state or message
+        InvalidAttestation,
+        /// Misused ActorX messaging
+        ActorXMisuse,
+    }
+
+    impl Offence {
+        /// Every offence
+        pub const ALL: [Offence; 4] =
+            [Offence::Equivocation, Offence::Unavailability, Offence::InvalidAttestation, Offence::ActorXMisuse];
+
+        /// Name of the offence, used as a jail reason
+        pub fn name(&self) -> &'static [u8] {
+            match self {
+                Offence::Equivocation => b"equivocation",
+                Offence::Unavailability => b"unavailability",
+                Offence::InvalidAttestation => b"invalid attestation",
+                Offence::ActorXMisuse => b"actorx misuse",
+            }
+        }
+    }
+
     /// Validator performance metrics
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
     pub struct ValidatorPerformance {
@@ -138,8 +194,11 @@ pub mod pallet {
         pub uptime: u16,
         /// Blocks proposed
         pub blocks_proposed: u64,
-        /// Blocks finalized
-        pub blocks_finalized: u64,
+        /// Blocks proposed at least one full session ago
+        ///
+        /// A proxy for blocks finalized: finality is assumed to have caught
+        /// up with a block by the end of the session after it was authored.
+        pub blocks_settled: u64,
         /// Slashes received
         pub slashes: u32,
         /// Performance score (0-10000, representing 0-100.00%)
@@ -148,6 +207,7 @@ pub mod pallet {
     
     /// Stake information
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
     pub struct StakeInfo<T: Config> {
         /// Staker account
         pub staker: T::AccountId,
@@ -178,6 +238,7 @@ pub mod pallet {
     
     /// Session information
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
     pub struct SessionInfo<T: Config> {
         /// Session index
         pub index: u32,
@@ -193,7 +254,7 @@ pub mod pallet {
     /// Currency trait
     pub trait Currency<AccountId> {
         /// Balance type
-        type Balance: Member + Parameter + AtLeast32BitUnsigned + Default + Copy;
+        type Balance: Member + Parameter + AtLeast32BitUnsigned + Default + Copy + MaxEncodedLen;
         
         /// Get free balance
         fn free_balance(who: &AccountId) -> Self::Balance;
@@ -205,8 +266,26 @@ pub mod pallet {
             value: Self::Balance,
             existence_requirement: ExistenceRequirement,
         ) -> DispatchResult;
+
+        /// Slash up to `value` from `who`, returning the amount that could not be slashed
+        ///
+        /// The implementation decides where slashed funds go, e.g. burnt or
+        /// sent to a treasury.
+        fn slash(who: &AccountId, value: Self::Balance) -> Self::Balance;
     }
     
+    /// Handler for the end of a session
+    pub trait OnSessionEnd {
+        /// Session `session_index` ended, returns the weight consumed
+        fn on_session_end(session_index: u32) -> Weight;
+    }
+
+    impl OnSessionEnd for () {
+        fn on_session_end(_session_index: u32) -> Weight {
+            Weight::zero()
+        }
+    }
+
     /// Existence requirement for currency transfers
     pub enum ExistenceRequirement {
         /// Keep alive
@@ -216,7 +295,7 @@ pub mod pallet {
     }
 
     #[pallet::event]
-    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    #[pallet::generate_deposit(pub fn deposit_event)]
     pub enum Event<T: Config> {
         /// A validator was registered
         ValidatorRegistered {
@@ -242,7 +321,7 @@ pub mod pallet {
         ValidatorSlashed {
             validator: T::ValidatorId,
             amount: BalanceOf<T>,
-            reason: Vec<u8>,
+            offence: Offence,
         },
         
         /// A new session started
@@ -290,7 +369,236 @@ pub mod pallet {
         
         /// Invalid parachain ID
         InvalidParachainId,
+
+        /// Validator is already active on the maximum number of parachains
+        TooManyParachains,
+
+        /// Validator already has the maximum number of stakers
+        TooManyNominators,
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            let session = CurrentSession::<T>::get();
+            if session.as_ref().map_or(false, |session| now < session.end) {
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut weight = T::DbWeight::get().reads_writes(1, 1);
+            let index = match session {
+                Some(session) => {
+                    weight = weight.saturating_add(T::OnSessionEnd::on_session_end(session.index));
+                    session.index.saturating_add(1)
+                }
+                None => 0,
+            };
+            Self::start_session(index, now);
+            weight
+        }
     }
 
-    // Implementation code will be added in separate files
+    impl<T: Config> Pallet<T> {
+        /// Start session `index` at block `start`
+        fn start_session(index: u32, start: T::BlockNumber) {
+            let end = start.saturating_add(T::SessionDuration::get().max(One::one()));
+            CurrentSession::<T>::put(SessionInfo { index, start, end });
+            Self::deposit_event(Event::NewSession { session_index: index, start, end });
+        }
+
+        /// Index of the current session, 0 before the first one starts
+        pub fn current_session_index() -> u32 {
+            CurrentSession::<T>::get().map_or(0, |session| session.index)
+        }
+
+        /// Replace the validator set of a parachain
+        ///
+        /// Validators without a profile, jailed or exited are skipped. The
+        /// parachain is added to the `active_parachains` of every member and
+        /// removed from those of validators that left the set. Fails without
+        /// changing anything if a member is already active on
+        /// `MaxValidatorsPerSet` parachains.
+        pub fn set_validator_set(parachain_id: u32, validators: Vec<T::ValidatorId>) -> DispatchResult {
+            let mut set = BoundedVec::<Validator<T>, T::MaxValidatorsPerSet>::default();
+            for id in validators.iter() {
+                let Some(profile) = Validators::<T>::get(id) else { continue };
+                if matches!(profile.status, ValidatorStatus::Jailed | ValidatorStatus::Exited) {
+                    continue;
+                }
+                set.try_push(Validator {
+                    id: *id,
+                    account: profile.account,
+                    total_stake: TotalStake::<T>::get(id),
+                    status: profile.status,
+                })
+                .map_err(|_| Error::<T>::TooManyValidators)?;
+            }
+
+            let now = frame_system::Pallet::<T>::block_number();
+            let mut joined = Vec::new();
+            for member in set.iter() {
+                let Some(mut profile) = Validators::<T>::get(member.id) else { continue };
+                if !profile.active_parachains.contains(&parachain_id) {
+                    profile.active_parachains.try_push(parachain_id).map_err(|_| Error::<T>::TooManyParachains)?;
+                    profile.last_updated = now;
+                    joined.push((member.id, profile));
+                }
+            }
+
+            for old in ValidatorSets::<T>::get(parachain_id).iter() {
+                if !set.iter().any(|v| v.id == old.id) {
+                    Validators::<T>::mutate(old.id, |profile| {
+                        if let Some(profile) = profile {
+                            profile.active_parachains.retain(|id| *id != parachain_id);
+                            profile.last_updated = now;
+                        }
+                    });
+                }
+            }
+            for (id, profile) in joined {
+                Validators::<T>::insert(id, profile);
+            }
+
+            let validator_count = set.len() as u32;
+            ValidatorSets::<T>::insert(parachain_id, set);
+            Self::deposit_event(Event::ValidatorSetUpdated { parachain_id, validator_count });
+            Ok(())
+        }
+
+        /// Remove a validator from every set it is in, returning the parachains it left
+        ///
+        /// Every entry of `ValidatorSets` is checked rather than trusting the
+        /// profile's `active_parachains`.
+        pub fn remove_from_sets(validator: &T::ValidatorId) -> Vec<u32> {
+            let Some(mut profile) = Validators::<T>::get(validator) else { return Vec::new() };
+            let parachains: Vec<u32> = ValidatorSets::<T>::iter()
+                .filter(|(_, set)| set.iter().any(|member| member.id == *validator))
+                .map(|(parachain_id, _)| parachain_id)
+                .collect();
+            for parachain_id in parachains.iter() {
+                let validator_count = ValidatorSets::<T>::mutate(parachain_id, |set| {
+                    set.retain(|member| member.id != *validator);
+                    set.len() as u32
+                });
+                Self::deposit_event(Event::ValidatorSetUpdated { parachain_id: *parachain_id, validator_count });
+            }
+            profile.active_parachains = Default::default();
+            profile.last_updated = frame_system::Pallet::<T>::block_number();
+            Validators::<T>::insert(validator, profile);
+            parachains
+        }
+
+        /// Record `amount` staked by `staker` on a validator
+        ///
+        /// Adds to an existing stake of `staker` on the validator, which
+        /// becomes active again. Fails if the validator has no profile or
+        /// already has `MaxNominatorsPerValidator` stakers.
+        pub fn add_stake(staker: &T::AccountId, validator: &T::ValidatorId, amount: BalanceOf<T>) -> DispatchResult {
+            ensure!(Validators::<T>::contains_key(validator), Error::<T>::ValidatorNotFound);
+            ValidatorStakers::<T>::try_mutate(validator, |stakers| {
+                if !stakers.contains(staker) {
+                    stakers.try_push(staker.clone()).map_err(|_| Error::<T>::TooManyNominators)?;
+                }
+                Ok::<_, DispatchError>(())
+            })?;
+
+            let now = frame_system::Pallet::<T>::block_number();
+            Stakes::<T>::mutate(staker, validator, |stake| match stake {
+                Some(stake) => {
+                    stake.amount = stake.amount.saturating_add(amount);
+                    stake.status = StakeStatus::Active;
+                    stake.unlocked_at = None;
+                }
+                None => {
+                    *stake = Some(StakeInfo {
+                        staker: staker.clone(),
+                        validator: *validator,
+                        amount,
+                        status: StakeStatus::Active,
+                        staked_at: now,
+                        unlocked_at: None,
+                    })
+                }
+            });
+            TotalStake::<T>::mutate(validator, |total| *total = total.saturating_add(amount));
+            Ok(())
+        }
+
+        /// Slash `fraction` of a validator's total stake for `offence`
+        ///
+        /// Every active or unstaking stake on the validator loses `fraction`
+        /// of its amount, slashed from the staker's balance. Stake counted in
+        /// `TotalStake` without a `Stakes` entry, the validator's own bond, is
+        /// slashed from the validator's account. `TotalStake` drops by what
+        /// was actually slashed. Returns the amount slashed.
+        pub fn slash_stake(validator: &T::ValidatorId, fraction: Perbill, offence: Offence) -> BalanceOf<T> {
+            let total = TotalStake::<T>::get(validator);
+            let mut staked = BalanceOf::<T>::zero();
+            let mut amount = BalanceOf::<T>::zero();
+
+            for staker in ValidatorStakers::<T>::get(validator) {
+                let Some(mut stake) = Stakes::<T>::get(&staker, validator) else { continue };
+                if !matches!(stake.status, StakeStatus::Active | StakeStatus::Unstaking) {
+                    continue;
+                }
+                staked = staked.saturating_add(stake.amount);
+                let slashed = Self::slash_balance(&staker, fraction * stake.amount);
+                if slashed.is_zero() {
+                    continue;
+                }
+                stake.amount = stake.amount.saturating_sub(slashed);
+                if stake.amount.is_zero() {
+                    stake.status = StakeStatus::Slashed;
+                }
+                Stakes::<T>::insert(&staker, validator, stake);
+                amount = amount.saturating_add(slashed);
+            }
+
+            let bond = total.saturating_sub(staked);
+            if !bond.is_zero() {
+                if let Some(profile) = Validators::<T>::get(validator) {
+                    amount = amount.saturating_add(Self::slash_balance(&profile.account, fraction * bond));
+                }
+            }
+
+            TotalStake::<T>::insert(validator, total.saturating_sub(amount));
+            Self::deposit_event(Event::ValidatorSlashed { validator: *validator, amount, offence });
+            amount
+        }
+
+        /// Worst-case weight of `slash_stake`, with `MaxNominatorsPerValidator` stakers
+        pub fn slash_stake_weight() -> Weight {
+            let per_staker = T::DbWeight::get().reads_writes(2, 2);
+            T::DbWeight::get()
+                .reads_writes(4, 2)
+                .saturating_add(per_staker.saturating_mul(T::MaxNominatorsPerValidator::get().into()))
+        }
+
+        /// Slash up to `value` from `who`, returning the amount slashed
+        fn slash_balance(who: &T::AccountId, value: BalanceOf<T>) -> BalanceOf<T> {
+            if value.is_zero() {
+                return value;
+            }
+            value.saturating_sub(T::Currency::slash(who, value))
+        }
+
+        /// Count a slash in a validator's performance metrics
+        pub fn note_slash(validator: &T::ValidatorId) {
+            Validators::<T>::mutate(validator, |profile| {
+                if let Some(profile) = profile {
+                    profile.performance.slashes = profile.performance.slashes.saturating_add(1);
+                }
+            });
+        }
+
+        /// Change the status of a validator
+        pub fn set_status(validator: &T::ValidatorId, status: ValidatorStatus) -> DispatchResult {
+            Validators::<T>::try_mutate(validator, |profile| {
+                let profile = profile.as_mut().ok_or(Error::<T>::ValidatorNotFound)?;
+                profile.status = status;
+                profile.last_updated = frame_system::Pallet::<T>::block_number();
+                Ok(())
+            })
+        }
+    }
 }
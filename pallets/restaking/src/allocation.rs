@@ -0,0 +1,124 @@
+//! Allocation math for the restaking pallet
+//!
+//! These functions are free of pallet state so that off-chain tooling can
+//! reproduce exactly what the runtime computes.
+
+use crate::{RestakeAllocation, RestakeStrategy};
+use sp_runtime::{traits::AtLeast32BitUnsigned, Perbill, RuntimeDebug};
+use sp_std::{prelude::*, vec};
+
+/// Basis points representing a full (100.00%) allocation
+pub const FULL_ALLOCATION: u16 = 10_000;
+
+/// Reasons an allocation request can be rejected
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum AllocationError {
+    /// No parachains were given
+    Empty,
+    /// The same parachain appears more than once
+    Duplicate,
+    /// Percentages do not add up to 100.00%
+    InvalidTotal,
+    /// Single parachain strategy with more than one allocation
+    NotSingle,
+}
+
+/// Normalize requested allocations for the given strategy
+///
+/// `Equal` ignores the requested percentages, `Proportional` treats them as
+/// relative weights, `Custom` requires them to add up to exactly 100.00% and
+/// `SingleParachain` requires a single entry.
+pub fn resolve_allocations(
+    strategy: RestakeStrategy,
+    requested: &[RestakeAllocation],
+) -> Result<Vec<RestakeAllocation>, AllocationError> {
+    if requested.is_empty() {
+        return Err(AllocationError::Empty);
+    }
+    for (i, allocation) in requested.iter().enumerate() {
+        if requested[..i].iter().any(|a| a.parachain_id == allocation.parachain_id) {
+            return Err(AllocationError::Duplicate);
+        }
+    }
+
+    match strategy {
+        RestakeStrategy::SingleParachain => {
+            if requested.len() != 1 {
+                return Err(AllocationError::NotSingle);
+            }
+            Ok(vec![RestakeAllocation {
+                parachain_id: requested[0].parachain_id,
+                percentage: FULL_ALLOCATION,
+            }])
+        }
+        RestakeStrategy::Custom => {
+            let total: u32 = requested.iter().map(|a| a.percentage as u32).sum();
+            if total != FULL_ALLOCATION as u32 {
+                return Err(AllocationError::InvalidTotal);
+            }
+            Ok(requested.to_vec())
+        }
+        RestakeStrategy::Equal => {
+            let weights: Vec<(u32, u32)> = requested.iter().map(|a| (a.parachain_id, 1)).collect();
+            normalize_weights(&weights)
+        }
+        RestakeStrategy::Proportional => {
+            let weights: Vec<(u32, u32)> = requested
+                .iter()
+                .map(|a| (a.parachain_id, a.percentage as u32))
+                .collect();
+            normalize_weights(&weights)
+        }
+    }
+}
+
+/// Scale `(parachain_id, weight)` pairs to percentages adding up to 100.00%
+///
+/// Rounding dust goes to the first entry so the total is always exact.
+pub fn normalize_weights(weights: &[(u32, u32)]) -> Result<Vec<RestakeAllocation>, AllocationError> {
+    let total: u64 = weights.iter().map(|(_, w)| *w as u64).sum();
+    if total == 0 {
+        return Err(AllocationError::InvalidTotal);
+    }
+
+    let mut allocations: Vec<RestakeAllocation> = weights
+        .iter()
+        .map(|(parachain_id, weight)| RestakeAllocation {
+            parachain_id: *parachain_id,
+            percentage: (*weight as u64 * FULL_ALLOCATION as u64 / total) as u16,
+        })
+        .collect();
+    let assigned: u16 = allocations.iter().map(|a| a.percentage).sum();
+    allocations[0].percentage += FULL_ALLOCATION - assigned;
+
+    Ok(allocations)
+}
+
+/// Split an amount across allocations
+///
+/// Rounding dust goes to the first allocation so the parts always add up to
+/// `amount` when the percentages add up to 100.00%.
+pub fn split_amount<Balance: AtLeast32BitUnsigned + Copy>(
+    amount: Balance,
+    allocations: &[RestakeAllocation],
+) -> Vec<(u32, Balance)> {
+    let mut parts: Vec<(u32, Balance)> = allocations
+        .iter()
+        .map(|a| (a.parachain_id, share_of(amount, a.percentage)))
+        .collect();
+
+    let total_percentage: u32 = allocations.iter().map(|a| a.percentage as u32).sum();
+    if total_percentage == FULL_ALLOCATION as u32 {
+        let assigned = parts.iter().fold(Balance::zero(), |acc, (_, part)| acc.saturating_add(*part));
+        if let Some((_, first)) = parts.first_mut() {
+            *first = first.saturating_add(amount.saturating_sub(assigned));
+        }
+    }
+
+    parts
+}
+
+/// Portion of `amount` represented by `percentage` basis points
+pub fn share_of<Balance: AtLeast32BitUnsigned + Copy>(amount: Balance, percentage: u16) -> Balance {
+    Perbill::from_rational(percentage as u32, FULL_ALLOCATION as u32) * amount
+}
@@ -2,19 +2,70 @@
 //!
 //! This module implements restaking mechanisms for enhanced security
 //! across the Matrix-Magiq ecosystem.
+//!
+//! Restakers who do not run their own validators can delegate part of their
+//! restaked stake to a registered operator. The operator chooses which
+//! parachains it secures, but a delegator's stake only ever backs the
+//! parachains listed in that delegator's own allocations.
+//!
+//! Every restake is locked for a duration chosen within the configured
+//! `[min_stake_duration, max_stake_duration]` range. Longer locks earn a
+//! reward multiplier given by the runtime's `LockBoostCurve` until the lock
+//! ends. Ended locks are swept in `on_idle`, which re-weights their shares
+//! back to 1x.
+//!
+//! Rewards are paid from the pallet's reward pot. Every `RewardFrequency`
+//! blocks each parachain's budget is added to a reward-per-share accumulator,
+//! where shares are the lock-boosted stake allocated to that parachain.
+//! Restakers settle against the accumulator whenever their shares change, so
+//! distribution never iterates over accounts.
+//!
+//! Restakers may only allocate to registered parachains. When a parachain is
+//! deregistered, or a new one joins while `Proportional` restakers exist, a
+//! rebalance job is queued and processed a few accounts at a time in
+//! `on_idle`. Stake moved off a deregistered parachain stays slashable for
+//! that parachain until the restaker's unbonding period has passed.
+//!
+//! Whitelisted `pallet-assets` tokens, such as LP tokens and bridged assets,
+//! can be restaked alongside the native stake and follow the same
+//! allocations. They add to a parachain's security at their oracle price
+//! minus a per-asset haircut, as long as the price is no older than
+//! `MaxPriceAge`. They earn no rewards of their own, and are slashed in kind
+//! whenever the native stake is slashed.
+//!
+//! Consumer parachains are kept informed over XCM. Allocation changes and
+//! slashes are sent to them as `Transact` calls carrying a
+//! `VersionedRestakingMessage`, and they report misbehaviour or request
+//! reward claims by sending the same messages back to `handle_message`.
+//!
+//! Unstaking is not immediate. Each unstake joins the restaker's withdrawal
+//! queue together with the allocations it leaves, stays reserved and
+//! slashable by those parachains until it matures after the unbonding
+//! period, and can then be withdrawn in batches.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{
-    dispatch::DispatchResult,
-    pallet_prelude::*,
-    traits::{Get, Currency},
-};
-use frame_system::pallet_prelude::*;
-use sp_runtime::{traits::Hash, RuntimeDebug};
-use sp_std::prelude::*;
+pub use pallet::*;
+
+pub mod allocation;
+pub mod messages;
+pub mod oracle;
+pub mod rewards;
+pub mod runtime_api;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+#[cfg(test)]
+mod xcm_mock;
+#[cfg(test)]
+mod xcm_tests;
+
 use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
 
 /// Restake strategy types
 #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
@@ -57,6 +108,10 @@ pub struct RestakingConfig<AccountId, Balance, BlockNumber> {
     pub max_stake_duration: BlockNumber,
     /// Reward distribution frequency in blocks
     pub reward_frequency: BlockNumber,
+    /// Lock duration chosen by the restaker in blocks
+    pub lock_duration: BlockNumber,
+    /// Block until which the stake cannot be unstaked
+    pub locked_until: BlockNumber,
 }
 
 /// Restaking configuration default implementation
@@ -74,55 +129,2233 @@ where
             min_stake_duration: BlockNumber::default(),
             max_stake_duration: BlockNumber::default(),
             reward_frequency: BlockNumber::default(),
+            lock_duration: BlockNumber::default(),
+            locked_until: BlockNumber::default(),
         }
     }
 }
 
-/// Restake assets across multiple parachains
-pub fn restake_assets<T: frame_system::Config>(
-    account: &T::AccountId,
-    amount: T::Balance,
-    strategy: RestakeStrategy,
-    allocations: Vec<RestakeAllocation>,
-) -> DispatchResult {
-    // Implementation would stake assets across parachains
-    // This is a placeholder for the actual implementation
-    Ok(())
-}
+#[frame_support::pallet]
+pub mod pallet {
+    use super::*;
+    use crate::{
+        allocation::{self, AllocationError},
+        messages::{RestakingMessage, VersionedRestakingMessage},
+        oracle::PriceOracle,
+        rewards::{self, LockBoostCurve},
+    };
+    use frame_support::{
+        pallet_prelude::*,
+        traits::{
+            tokens::{
+                fungibles::{self, Mutate as _},
+                Fortitude, Precision, Preservation,
+            },
+            Currency, ExistenceRequirement, OnUnbalanced, ReservableCurrency,
+        },
+        PalletId,
+    };
+    use frame_system::pallet_prelude::*;
+    use sp_std::vec;
+    use xcm::latest::prelude::*;
+    use sp_runtime::{
+        traits::{AccountIdConversion, Convert, One, Saturating, Zero},
+        FixedPointNumber, FixedU128, Perbill,
+    };
 
-/// Unstake assets from multiple parachains
-pub fn unstake_assets<T: frame_system::Config>(
-    account: &T::AccountId,
-    amount: T::Balance,
-) -> DispatchResult {
-    // Implementation would unstake assets from parachains
-    // This is a placeholder for the actual implementation
-    Ok(())
-}
+    /// Alias for balance type
+    pub type BalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
-/// Claim restaking rewards
-pub fn claim_rewards<T: frame_system::Config>(
-    account: &T::AccountId,
-) -> DispatchResult {
-    // Implementation would claim restaking rewards
-    // This is a placeholder for the actual implementation
-    Ok(())
-}
+    /// Alias for negative imbalance type
+    pub type NegativeImbalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::NegativeImbalance;
 
-/// Compute optimal allocation based on parachain security needs
-pub fn compute_optimal_allocation<T: frame_system::Config>(
-    parachains: Vec<u32>,
-) -> Vec<RestakeAllocation> {
-    // Implementation would compute optimal allocation
-    // This is a placeholder for the actual implementation
-    Vec::new()
-}
+    /// Alias for the message type exchanged with consumer parachains
+    pub type VersionedMessageOf<T> =
+        VersionedRestakingMessage<<T as frame_system::Config>::AccountId, BalanceOf<T>>;
+
+    /// Alias for the restaking configuration stored per account
+    pub type RestakingConfigOf<T> = RestakingConfig<
+        <T as frame_system::Config>::AccountId,
+        BalanceOf<T>,
+        <T as frame_system::Config>::BlockNumber,
+    >;
+
+    #[pallet::pallet]
+    #[pallet::without_storage_info]
+    pub struct Pallet<T>(_);
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// The overarching event type
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+        /// Currency used for restaking
+        type Currency: ReservableCurrency<Self::AccountId>;
+
+        /// Handler for slashed funds
+        type Slash: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+        /// Origin allowed to slash operators
+        type SlashOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Origin allowed to set parachain reward budgets
+        type RewardOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Pallet ID, used to derive the reward pot account
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+
+        /// Max parachains or services with a reward budget
+        #[pallet::constant]
+        type MaxRewardedParachains: Get<u32>;
+
+        /// Origin allowed to register and deregister parachains
+        type ParachainOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Max queued rebalance jobs
+        #[pallet::constant]
+        type MaxRebalanceJobs: Get<u32>;
+
+        /// Max pending parachain exits per restaker
+        #[pallet::constant]
+        type MaxParachainExits: Get<u32>;
+
+        /// Max queued withdrawals per restaker
+        #[pallet::constant]
+        type MaxWithdrawals: Get<u32>;
+
+        /// Identifier of restakable assets
+        type AssetId: Member + Parameter + Copy + MaxEncodedLen;
+
+        /// Assets that can be restaked alongside the native currency
+        type Assets: fungibles::Inspect<Self::AccountId, AssetId = Self::AssetId, Balance = BalanceOf<Self>>
+            + fungibles::Mutate<Self::AccountId>;
+
+        /// Prices of restaked assets in the native currency
+        type PriceOracle: PriceOracle<Self::AssetId, Self::BlockNumber>;
+
+        /// Age in blocks after which an asset price is too stale to value the asset
+        #[pallet::constant]
+        type MaxPriceAge: Get<Self::BlockNumber>;
+
+        /// Origin allowed to manage the asset whitelist
+        type AssetOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Max distinct assets restaked per account
+        #[pallet::constant]
+        type MaxRestakedAssets: Get<u32>;
+
+        /// Transport for messages to consumer parachains
+        type XcmSender: SendXcm;
+
+        /// Origin of messages from consumer parachains
+        type XcmOrigin: EnsureOrigin<Self::RuntimeOrigin, Success = MultiLocation>;
+
+        /// Account a location on a consumer parachain belongs to, if any
+        type AccountLocation: Convert<MultiLocation, Option<Self::AccountId>>;
+
+        /// Pallet and call index of the restaking message handler on consumer parachains
+        #[pallet::constant]
+        type ConsumerCallIndex: Get<[u8; 2]>;
+
+        /// Weight limit for the handler call on consumer parachains
+        #[pallet::constant]
+        type ConsumerCallWeight: Get<Weight>;
+
+        /// Minimum restake amount
+        #[pallet::constant]
+        type MinRestake: Get<BalanceOf<Self>>;
+
+        /// Max parachain allocations per restaker or operator
+        #[pallet::constant]
+        type MaxAllocations: Get<u32>;
+
+        /// Bond reserved from an operator on registration
+        #[pallet::constant]
+        type OperatorBond: Get<BalanceOf<Self>>;
+
+        /// Max delegators per operator
+        #[pallet::constant]
+        type MaxDelegators: Get<u32>;
+
+        /// Max pending undelegations per restaker
+        #[pallet::constant]
+        type MaxUndelegations: Get<u32>;
+
+        /// Unbonding period in blocks
+        #[pallet::constant]
+        type UnbondingPeriod: Get<Self::BlockNumber>;
+
+        /// Minimum stake duration in blocks
+        #[pallet::constant]
+        type MinStakeDuration: Get<Self::BlockNumber>;
+
+        /// Maximum stake duration in blocks
+        #[pallet::constant]
+        type MaxStakeDuration: Get<Self::BlockNumber>;
+
+        /// Reward distribution frequency in blocks
+        #[pallet::constant]
+        type RewardFrequency: Get<Self::BlockNumber>;
+
+        /// Reward multiplier curve for lock durations
+        type LockBoost: LockBoostCurve<Self::BlockNumber>;
+    }
+
+    /// Restaking configuration by account
+    #[pallet::storage]
+    pub type Restakers<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        RestakingConfigOf<T>,
+    >;
+
+    /// Total restaked amount allocated to each parachain
+    #[pallet::storage]
+    pub type ParachainStake<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Lock-boosted reward shares by parachain and restaker
+    #[pallet::storage]
+    pub type Shares<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Total reward shares per parachain
+    #[pallet::storage]
+    pub type TotalShares<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Accumulated reward per share for each parachain
+    #[pallet::storage]
+    pub type RewardPerShare<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        FixedU128,
+        ValueQuery,
+    >;
+
+    /// Accumulator value already accounted for, by parachain and restaker
+    #[pallet::storage]
+    pub type RewardDebt<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Settled rewards waiting to be claimed
+    #[pallet::storage]
+    pub type PendingRewards<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Rewards distributed but not yet claimed
+    #[pallet::storage]
+    pub type UnclaimedRewards<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    /// Reward paid out per period to each parachain or service
+    #[pallet::storage]
+    pub type RewardBudgets<T: Config> = StorageValue<
+        _,
+        BoundedVec<(u32, BalanceOf<T>), T::MaxRewardedParachains>,
+        ValueQuery,
+    >;
+
+    /// Restakers by the block their lock ends, swept in `on_idle`
+    #[pallet::storage]
+    pub type LockExpiries<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        T::AccountId,
+        (),
+    >;
+
+    /// Next block of `LockExpiries` to sweep
+    #[pallet::storage]
+    pub type NextLockSweep<T: Config> = StorageValue<_, T::BlockNumber>;
+
+    /// Registered parachains and their security weights
+    #[pallet::storage]
+    pub type Parachains<T: Config> = StorageValue<
+        _,
+        BoundedVec<ParachainInfo, T::MaxAllocations>,
+        ValueQuery,
+    >;
+
+    /// Restakers using the `Proportional` strategy
+    #[pallet::storage]
+    pub type ProportionalRestakers<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        (),
+    >;
+
+    /// Rebalance jobs waiting to be processed in `on_idle`
+    #[pallet::storage]
+    pub type RebalanceQueue<T: Config> = StorageValue<
+        _,
+        BoundedVec<RebalanceJob, T::MaxRebalanceJobs>,
+        ValueQuery,
+    >;
+
+    /// Stake moved off parachains that can still slash it, by restaker
+    #[pallet::storage]
+    pub type ParachainExits<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<ParachainExit<T>, T::MaxParachainExits>,
+        ValueQuery,
+    >;
+
+    /// Restakable assets and the haircut applied to their value
+    #[pallet::storage]
+    pub type AssetWhitelist<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AssetId,
+        Perbill, // Haircut
+    >;
+
+    /// Restaked asset amounts by restaker and asset
+    #[pallet::storage]
+    pub type AssetStakes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AssetId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Restaked asset amounts allocated to each parachain
+    #[pallet::storage]
+    pub type ParachainAssetStake<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        Blake2_128Concat,
+        T::AssetId,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Withdrawal queue by restaker, ordered by maturity
+    #[pallet::storage]
+    pub type Withdrawals<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<WithdrawalEntry<T>, T::MaxWithdrawals>,
+        ValueQuery,
+    >;
+
+    /// Registered operators
+    #[pallet::storage]
+    pub type Operators<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        OperatorInfo<T>,
+    >;
+
+    /// Active delegation by restaker
+    #[pallet::storage]
+    pub type Delegations<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Delegation<T>,
+    >;
+
+    /// Restakers exposed to each operator, either delegated or unbonding
+    #[pallet::storage]
+    pub type OperatorDelegators<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId, // Operator
+        Blake2_128Concat,
+        T::AccountId, // Delegator
+        (),
+    >;
+
+    /// Undelegations waiting out the unbonding period, by restaker
+    #[pallet::storage]
+    pub type Undelegations<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<Undelegation<T>, T::MaxUndelegations>,
+        ValueQuery,
+    >;
+
+    /// Registered parachain
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub struct ParachainInfo {
+        /// Parachain ID
+        pub parachain_id: u32,
+        /// Relative security weight used by the `Proportional` strategy
+        pub security_weight: u32,
+    }
+
+    /// Raw storage key used to resume iteration
+    pub type RebalanceCursor = BoundedVec<u8, ConstU32<128>>;
+
+    /// Rebalance job
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum RebalanceJob {
+        /// Move every restaker's stake off a deregistered parachain
+        ParachainLeft {
+            parachain_id: u32,
+        },
+        /// Spread `Proportional` restakers onto a changed parachain set
+        ParachainJoined {
+            parachain_id: u32,
+            cursor: Option<RebalanceCursor>,
+        },
+    }
+
+    /// Stake that left a parachain but can still be slashed by it
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct ParachainExit<T: Config> {
+        /// Parachain ID
+        pub parachain_id: u32,
+        /// Amount that was allocated to the parachain
+        pub amount: BalanceOf<T>,
+        /// Block at which the parachain can no longer slash the amount
+        pub until: T::BlockNumber,
+    }
+
+    /// Unstaked amount waiting out the unbonding period
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct WithdrawalEntry<T: Config> {
+        /// Amount being withdrawn
+        pub amount: BalanceOf<T>,
+        /// Allocations the amount is leaving, which can still slash it
+        pub allocations: BoundedVec<RestakeAllocation, T::MaxAllocations>,
+        /// Block at which the amount can be withdrawn
+        pub matures_at: T::BlockNumber,
+    }
+
+    /// Operator information
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct OperatorInfo<T: Config> {
+        /// Fee taken from delegator rewards
+        pub fee: Perbill,
+        /// Bond reserved on registration
+        pub bond: BalanceOf<T>,
+        /// Maximum total stake the operator accepts
+        pub delegation_cap: BalanceOf<T>,
+        /// Stake currently delegated to the operator
+        pub total_delegated: BalanceOf<T>,
+        /// Number of restakers exposed to the operator
+        pub delegator_count: u32,
+        /// Parachains the operator secures
+        pub targets: BoundedVec<u32, T::MaxAllocations>,
+    }
+
+    /// Delegation of restaked stake to an operator
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct Delegation<T: Config> {
+        /// Operator account
+        pub operator: T::AccountId,
+        /// Delegated amount
+        pub amount: BalanceOf<T>,
+    }
+
+    /// Delegated stake leaving an operator
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct Undelegation<T: Config> {
+        /// Operator account
+        pub operator: T::AccountId,
+        /// Undelegated amount
+        pub amount: BalanceOf<T>,
+        /// Block at which the amount stops being exposed to the operator
+        pub unlock_at: T::BlockNumber,
+    }
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// Assets were restaked
+        Restaked {
+            account: T::AccountId,
+            amount: BalanceOf<T>,
+            strategy: RestakeStrategy,
+            locked_until: T::BlockNumber,
+        },
+
+        /// Assets were unstaked
+        Unstaked {
+            account: T::AccountId,
+            amount: BalanceOf<T>,
+            matures_at: T::BlockNumber,
+        },
+
+        /// Matured withdrawals were paid out
+        Withdrawn {
+            account: T::AccountId,
+            amount: BalanceOf<T>,
+            entries: u32,
+        },
+
+        /// A queued withdrawal was slashed
+        WithdrawalSlashed {
+            account: T::AccountId,
+            parachain_id: u32,
+            amount: BalanceOf<T>,
+        },
+
+        /// An operator was registered
+        OperatorRegistered {
+            operator: T::AccountId,
+            fee: Perbill,
+            delegation_cap: BalanceOf<T>,
+        },
+
+        /// An operator changed its fee or delegation cap
+        OperatorUpdated {
+            operator: T::AccountId,
+            fee: Perbill,
+            delegation_cap: BalanceOf<T>,
+        },
+
+        /// An operator changed the parachains it secures
+        OperatorTargetsSet {
+            operator: T::AccountId,
+            targets: Vec<u32>,
+        },
+
+        /// An operator was deregistered
+        OperatorDeregistered {
+            operator: T::AccountId,
+        },
+
+        /// Stake was delegated to an operator
+        Delegated {
+            delegator: T::AccountId,
+            operator: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+
+        /// Stake started unbonding from an operator
+        Undelegated {
+            delegator: T::AccountId,
+            operator: T::AccountId,
+            amount: BalanceOf<T>,
+            unlock_at: T::BlockNumber,
+        },
+
+        /// Unbonded stake is no longer exposed to its operator
+        UndelegationWithdrawn {
+            delegator: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+
+        /// An operator was slashed
+        OperatorSlashed {
+            operator: T::AccountId,
+            fraction: Perbill,
+            amount: BalanceOf<T>,
+        },
+
+        /// A delegator was slashed through its operator
+        DelegatorSlashed {
+            delegator: T::AccountId,
+            operator: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+
+        /// A parachain's reward budget was set
+        RewardBudgetSet {
+            parachain_id: u32,
+            per_period: BalanceOf<T>,
+        },
+
+        /// Rewards were distributed to a parachain's restakers
+        RewardsDistributed {
+            parachain_id: u32,
+            amount: BalanceOf<T>,
+        },
+
+        /// A parachain was registered
+        ParachainRegistered {
+            parachain_id: u32,
+            security_weight: u32,
+        },
+
+        /// A parachain was deregistered
+        ParachainDeregistered {
+            parachain_id: u32,
+        },
+
+        /// A restaker's allocations were rebalanced
+        Rebalanced {
+            account: T::AccountId,
+            allocations: Vec<RestakeAllocation>,
+        },
+
+        /// A restaker could not be rebalanced and was skipped
+        RebalanceFailed {
+            account: T::AccountId,
+            parachain_id: u32,
+            error: DispatchError,
+        },
+
+        /// An asset was added to or updated in the whitelist
+        AssetWhitelisted {
+            asset: T::AssetId,
+            haircut: Perbill,
+        },
+
+        /// An asset was removed from the whitelist
+        AssetDelisted {
+            asset: T::AssetId,
+        },
+
+        /// An asset was restaked
+        AssetRestaked {
+            account: T::AccountId,
+            asset: T::AssetId,
+            amount: BalanceOf<T>,
+        },
+
+        /// A restaked asset was unstaked
+        AssetUnstaked {
+            account: T::AccountId,
+            asset: T::AssetId,
+            amount: BalanceOf<T>,
+        },
+
+        /// A restaked asset was slashed
+        AssetSlashed {
+            account: T::AccountId,
+            asset: T::AssetId,
+            amount: BalanceOf<T>,
+        },
+
+        /// A consumer parachain's slash report was applied
+        SlashReportProcessed {
+            parachain_id: u32,
+            offender: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+
+        /// A message could not be sent to a consumer parachain
+        XcmSendFailed {
+            parachain_id: u32,
+        },
+
+        /// Rewards were claimed
+        RewardsClaimed {
+            account: T::AccountId,
+            amount: BalanceOf<T>,
+            operator_fee: BalanceOf<T>,
+            compounded: bool,
+        },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// Restake amount below minimum
+        RestakeBelowMinimum,
+
+        /// Insufficient balance for restaking
+        InsufficientBalance,
+
+        /// No restaking configuration found
+        NotRestaker,
+
+        /// Amount exceeds the unlocked restaked stake
+        InsufficientStake,
+
+        /// Allocation list is empty
+        NoAllocations,
+
+        /// Parachain appears more than once in the allocations
+        DuplicateAllocation,
+
+        /// Allocation percentages are invalid for the strategy
+        InvalidAllocationTotal,
+
+        /// Single parachain strategy requires exactly one allocation
+        NotSingleAllocation,
+
+        /// Lock duration outside the allowed range
+        InvalidLockDuration,
+
+        /// Stake is still locked
+        StakeLocked,
+
+        /// Operator already registered
+        OperatorAlreadyExists,
+
+        /// Operator not found
+        OperatorNotFound,
+
+        /// Operator still has stake exposed to it
+        OperatorHasDelegators,
+
+        /// Delegation would exceed the operator's cap
+        DelegationCapExceeded,
+
+        /// Operator has reached the maximum number of delegators
+        TooManyDelegators,
+
+        /// Stake is already delegated to another operator
+        AlreadyDelegated,
+
+        /// No delegation found
+        NotDelegated,
+
+        /// Too many pending undelegations
+        TooManyUndelegations,
+
+        /// Nothing has finished unbonding
+        NothingToWithdraw,
+
+        /// No rewards to claim
+        NoRewards,
+
+        /// Too many parachains with a reward budget
+        TooManyRewardBudgets,
+
+        /// Parachain is not registered
+        UnknownParachain,
+
+        /// Parachain already registered
+        ParachainAlreadyRegistered,
+
+        /// Too many registered parachains
+        TooManyParachains,
+
+        /// Too many queued rebalance jobs
+        TooManyRebalanceJobs,
+
+        /// Too many parachains can still slash stake that left them
+        TooManyParachainExits,
+
+        /// Asset is not whitelisted for restaking
+        AssetNotWhitelisted,
+
+        /// Too many distinct assets restaked
+        TooManyAssets,
+
+        /// Restaked assets must be unstaked before the native stake
+        AssetsStillRestaked,
+
+        /// Message did not come from a parachain
+        NotParachainOrigin,
+
+        /// Message is not accepted from consumer parachains
+        UnexpectedMessage,
+
+        /// Message did not come from the account it concerns
+        NotAccountOrigin,
+
+        /// Too many queued withdrawals
+        TooManyWithdrawals,
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            let frequency = T::RewardFrequency::get();
+            if frequency.is_zero() || !(now % frequency).is_zero() {
+                return Weight::zero();
+            }
+
+            let budgets = RewardBudgets::<T>::get();
+            let count = budgets.len() as u64;
+            Self::distribute_rewards(budgets.into_inner());
+            T::DbWeight::get().reads_writes(3 + 2 * count, 1 + count)
+        }
+
+        fn on_idle(now: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            let consumed = Self::sweep_lock_expiries(now, remaining_weight);
+            consumed.saturating_add(Self::process_rebalance_queue(remaining_weight.saturating_sub(consumed)))
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Restake assets, or top up an existing restake and replace its allocations
+        ///
+        /// The whole restake is locked for `lock_duration` blocks from now. A
+        /// top-up never shortens an existing lock.
+        ///
+        /// `Proportional` restakes ignore `allocations` and follow the
+        /// security weights of the registered parachains.
+        #[pallet::call_index(0)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3)
+            .saturating_add(T::DbWeight::get().reads_writes(2, 2).saturating_mul(T::MaxAllocations::get().into())))]
+        pub fn restake(
+            origin: OriginFor<T>,
+            amount: BalanceOf<T>,
+            strategy: RestakeStrategy,
+            allocations: BoundedVec<RestakeAllocation, T::MaxAllocations>,
+            lock_duration: T::BlockNumber,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::restake_assets(&who, amount, strategy, allocations.into_inner(), lock_duration)
+        }
+
+        /// Queue unlocked stake that is neither delegated nor unbonding for withdrawal
+        ///
+        /// Any part of the stake can be unstaked. The amount matures after the
+        /// restaker's unbonding period and is paid out by `withdraw_unbonded`.
+        #[pallet::call_index(1)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3)
+            .saturating_add(T::DbWeight::get().reads_writes(2, 2).saturating_mul(T::MaxAllocations::get().into())))]
+        pub fn unstake(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::unstake_assets(&who, amount)
+        }
+
+        /// Pay out up to `max_entries` matured withdrawals, oldest first
+        #[pallet::call_index(19)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1)
+            .saturating_add(T::DbWeight::get().reads_writes(1, 1).saturating_mul((*max_entries).into())))]
+        pub fn withdraw_unbonded(origin: OriginFor<T>, max_entries: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let now = frame_system::Pallet::<T>::block_number();
+
+            let mut queue = Withdrawals::<T>::get(&who).into_inner();
+            let matured = queue
+                .iter()
+                .take(max_entries as usize)
+                .take_while(|entry| entry.matures_at <= now)
+                .count();
+            ensure!(matured > 0, Error::<T>::NothingToWithdraw);
+
+            let amount = queue
+                .drain(..matured)
+                .fold(BalanceOf::<T>::zero(), |acc, entry| acc.saturating_add(entry.amount));
+            if queue.is_empty() {
+                Withdrawals::<T>::remove(&who);
+            } else {
+                Withdrawals::<T>::insert(&who, BoundedVec::truncate_from(queue));
+            }
+            T::Currency::unreserve(&who, amount);
+
+            Self::deposit_event(Event::Withdrawn { account: who, amount, entries: matured as u32 });
+            Ok(())
+        }
+
+        /// Register as an operator
+        #[pallet::call_index(2)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 2))]
+        pub fn register_operator(
+            origin: OriginFor<T>,
+            fee: Perbill,
+            delegation_cap: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(!Operators::<T>::contains_key(&who), Error::<T>::OperatorAlreadyExists);
+
+            let bond = T::OperatorBond::get();
+            T::Currency::reserve(&who, bond).map_err(|_| Error::<T>::InsufficientBalance)?;
+
+            Operators::<T>::insert(&who, OperatorInfo {
+                fee,
+                bond,
+                delegation_cap,
+                total_delegated: Zero::zero(),
+                delegator_count: 0,
+                targets: BoundedVec::default(),
+            });
+
+            Self::deposit_event(Event::OperatorRegistered { operator: who, fee, delegation_cap });
+            Ok(())
+        }
+
+        /// Update an operator's fee and delegation cap
+        ///
+        /// Lowering the cap below the currently delegated stake only blocks
+        /// new delegations.
+        #[pallet::call_index(3)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn update_operator(
+            origin: OriginFor<T>,
+            fee: Perbill,
+            delegation_cap: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Operators::<T>::try_mutate(&who, |maybe_operator| -> DispatchResult {
+                let operator = maybe_operator.as_mut().ok_or(Error::<T>::OperatorNotFound)?;
+                operator.fee = fee;
+                operator.delegation_cap = delegation_cap;
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::OperatorUpdated { operator: who, fee, delegation_cap });
+            Ok(())
+        }
+
+        /// Choose the parachains an operator secures
+        #[pallet::call_index(4)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_operator_targets(
+            origin: OriginFor<T>,
+            targets: BoundedVec<u32, T::MaxAllocations>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            for (i, target) in targets.iter().enumerate() {
+                ensure!(!targets[..i].contains(target), Error::<T>::DuplicateAllocation);
+            }
+
+            Operators::<T>::try_mutate(&who, |maybe_operator| -> DispatchResult {
+                let operator = maybe_operator.as_mut().ok_or(Error::<T>::OperatorNotFound)?;
+                operator.targets = targets.clone();
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::OperatorTargetsSet { operator: who, targets: targets.into_inner() });
+            Ok(())
+        }
+
+        /// Deregister an operator once no stake is exposed to it
+        #[pallet::call_index(5)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 2))]
+        pub fn deregister_operator(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let operator = Operators::<T>::get(&who).ok_or(Error::<T>::OperatorNotFound)?;
+            ensure!(operator.delegator_count == 0, Error::<T>::OperatorHasDelegators);
+
+            T::Currency::unreserve(&who, operator.bond);
+            Operators::<T>::remove(&who);
+
+            Self::deposit_event(Event::OperatorDeregistered { operator: who });
+            Ok(())
+        }
+
+        /// Delegate restaked stake to an operator
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(5, 3))]
+        pub fn delegate(
+            origin: OriginFor<T>,
+            operator: T::AccountId,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let config = Restakers::<T>::get(&who).ok_or(Error::<T>::NotRestaker)?;
+            ensure!(amount <= Self::free_stake(&who, &config), Error::<T>::InsufficientStake);
+
+            let mut delegation = match Delegations::<T>::get(&who) {
+                Some(existing) => {
+                    ensure!(existing.operator == operator, Error::<T>::AlreadyDelegated);
+                    existing
+                }
+                None => Delegation { operator: operator.clone(), amount: Zero::zero() },
+            };
+
+            Operators::<T>::try_mutate(&operator, |maybe_operator| -> DispatchResult {
+                let info = maybe_operator.as_mut().ok_or(Error::<T>::OperatorNotFound)?;
+                let total_delegated = info.total_delegated.saturating_add(amount);
+                ensure!(total_delegated <= info.delegation_cap, Error::<T>::DelegationCapExceeded);
+
+                if !OperatorDelegators::<T>::contains_key(&operator, &who) {
+                    ensure!(info.delegator_count < T::MaxDelegators::get(), Error::<T>::TooManyDelegators);
+                    info.delegator_count += 1;
+                    OperatorDelegators::<T>::insert(&operator, &who, ());
+                }
+                info.total_delegated = total_delegated;
+                Ok(())
+            })?;
+
+            delegation.amount = delegation.amount.saturating_add(amount);
+            Delegations::<T>::insert(&who, delegation);
+
+            Self::deposit_event(Event::Delegated { delegator: who, operator, amount });
+            Ok(())
+        }
+
+        /// Start unbonding delegated stake from its operator
+        ///
+        /// The amount stays exposed to the operator's slashes until the
+        /// restaker's unbonding period has passed.
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 3))]
+        pub fn undelegate(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let config = Restakers::<T>::get(&who).ok_or(Error::<T>::NotRestaker)?;
+            let mut delegation = Delegations::<T>::get(&who).ok_or(Error::<T>::NotDelegated)?;
+            ensure!(amount <= delegation.amount, Error::<T>::InsufficientStake);
+
+            let unlock_at = frame_system::Pallet::<T>::block_number().saturating_add(config.unbonding_period);
+            Undelegations::<T>::try_mutate(&who, |pending| {
+                pending.try_push(Undelegation { operator: delegation.operator.clone(), amount, unlock_at })
+            })
+            .map_err(|_| Error::<T>::TooManyUndelegations)?;
 
-/// Calculate restaking rewards
-pub fn calculate_rewards<T: frame_system::Config>(
-    account: &T::AccountId,
-) -> T::Balance {
-    // Implementation would calculate rewards
-    // This is a placeholder for the actual implementation
-    T::Balance::default()
+            Operators::<T>::mutate(&delegation.operator, |maybe_operator| {
+                if let Some(info) = maybe_operator {
+                    info.total_delegated = info.total_delegated.saturating_sub(amount);
+                }
+            });
+
+            let operator = delegation.operator.clone();
+            delegation.amount = delegation.amount.saturating_sub(amount);
+            if delegation.amount.is_zero() {
+                Delegations::<T>::remove(&who);
+            } else {
+                Delegations::<T>::insert(&who, delegation);
+            }
+
+            Self::deposit_event(Event::Undelegated { delegator: who, operator, amount, unlock_at });
+            Ok(())
+        }
+
+        /// Release undelegations whose unbonding period has passed
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3)
+            .saturating_add(T::DbWeight::get().reads_writes(2, 2).saturating_mul(T::MaxUndelegations::get().into())))]
+        pub fn withdraw_undelegated(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let now = frame_system::Pallet::<T>::block_number();
+
+            let mut pending = Undelegations::<T>::get(&who);
+            let mut released = Vec::new();
+            pending.retain(|entry| {
+                if entry.unlock_at <= now {
+                    released.push(entry.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+            ensure!(!released.is_empty(), Error::<T>::NothingToWithdraw);
+            Undelegations::<T>::insert(&who, pending);
+
+            let mut amount = BalanceOf::<T>::zero();
+            for entry in released {
+                amount = amount.saturating_add(entry.amount);
+                Self::release_exposure(&entry.operator, &who);
+            }
+
+            Self::deposit_event(Event::UndelegationWithdrawn { delegator: who, amount });
+            Ok(())
+        }
+
+        /// Slash an operator and, through it, every restaker exposed to it
+        #[pallet::call_index(9)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 2)
+            .saturating_add(T::DbWeight::get().reads_writes(5, 4).saturating_mul(T::MaxDelegators::get().into())))]
+        pub fn slash_operator(
+            origin: OriginFor<T>,
+            operator: T::AccountId,
+            fraction: Perbill,
+        ) -> DispatchResult {
+            T::SlashOrigin::ensure_origin(origin)?;
+            Self::do_slash_operator(&operator, fraction, None)?;
+            Ok(())
+        }
+
+        /// Set the reward paid to a parachain's restakers each period
+        ///
+        /// A zero budget removes the parachain from distribution.
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_reward_budget(
+            origin: OriginFor<T>,
+            parachain_id: u32,
+            per_period: BalanceOf<T>,
+        ) -> DispatchResult {
+            T::RewardOrigin::ensure_origin(origin)?;
+
+            RewardBudgets::<T>::try_mutate(|budgets| -> DispatchResult {
+                budgets.retain(|(id, _)| *id != parachain_id);
+                if !per_period.is_zero() {
+                    budgets
+                        .try_push((parachain_id, per_period))
+                        .map_err(|_| Error::<T>::TooManyRewardBudgets)?;
+                }
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::RewardBudgetSet { parachain_id, per_period });
+            Ok(())
+        }
+
+        /// Claim accrued rewards, optionally restaking them
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(8, 6)
+            .saturating_add(T::DbWeight::get().reads_writes(6, 6).saturating_mul(T::MaxAllocations::get().into())))]
+        pub fn claim_rewards(origin: OriginFor<T>, compound: bool) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::do_claim_rewards(&who, compound)
+        }
+
+        /// Register a parachain that restakers can allocate to
+        ///
+        /// `Proportional` restakers are spread onto the new parachain in
+        /// `on_idle`.
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 2))]
+        pub fn register_parachain(
+            origin: OriginFor<T>,
+            parachain_id: u32,
+            security_weight: u32,
+        ) -> DispatchResult {
+            T::ParachainOrigin::ensure_origin(origin)?;
+
+            Parachains::<T>::try_mutate(|parachains| -> DispatchResult {
+                ensure!(
+                    !parachains.iter().any(|info| info.parachain_id == parachain_id),
+                    Error::<T>::ParachainAlreadyRegistered
+                );
+                parachains
+                    .try_push(ParachainInfo { parachain_id, security_weight })
+                    .map_err(|_| Error::<T>::TooManyParachains)?;
+                Ok(())
+            })?;
+            Self::queue_rebalance(RebalanceJob::ParachainJoined { parachain_id, cursor: None })?;
+
+            Self::deposit_event(Event::ParachainRegistered { parachain_id, security_weight });
+            Ok(())
+        }
+
+        /// Deregister a parachain and move restaked stake off it in `on_idle`
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3))]
+        pub fn deregister_parachain(origin: OriginFor<T>, parachain_id: u32) -> DispatchResult {
+            T::ParachainOrigin::ensure_origin(origin)?;
+
+            Parachains::<T>::try_mutate(|parachains| -> DispatchResult {
+                let before = parachains.len();
+                parachains.retain(|info| info.parachain_id != parachain_id);
+                ensure!(parachains.len() < before, Error::<T>::UnknownParachain);
+                Ok(())
+            })?;
+            RewardBudgets::<T>::mutate(|budgets| budgets.retain(|(id, _)| *id != parachain_id));
+            Self::queue_rebalance(RebalanceJob::ParachainLeft { parachain_id })?;
+
+            Self::deposit_event(Event::ParachainDeregistered { parachain_id });
+            Ok(())
+        }
+
+        /// Whitelist an asset for restaking, or update its haircut
+        #[pallet::call_index(14)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(0, 1))]
+        pub fn whitelist_asset(
+            origin: OriginFor<T>,
+            asset: T::AssetId,
+            haircut: Perbill,
+        ) -> DispatchResult {
+            T::AssetOrigin::ensure_origin(origin)?;
+            AssetWhitelist::<T>::insert(asset, haircut);
+
+            Self::deposit_event(Event::AssetWhitelisted { asset, haircut });
+            Ok(())
+        }
+
+        /// Remove an asset from the whitelist
+        ///
+        /// Existing restakes of the asset stay in place but stop contributing
+        /// to security. They can still be unstaked.
+        #[pallet::call_index(15)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn delist_asset(origin: OriginFor<T>, asset: T::AssetId) -> DispatchResult {
+            T::AssetOrigin::ensure_origin(origin)?;
+            ensure!(AssetWhitelist::<T>::contains_key(asset), Error::<T>::AssetNotWhitelisted);
+            AssetWhitelist::<T>::remove(asset);
+
+            Self::deposit_event(Event::AssetDelisted { asset });
+            Ok(())
+        }
+
+        /// Restake a whitelisted asset under the account's current allocations
+        #[pallet::call_index(16)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(5, 4)
+            .saturating_add(T::DbWeight::get().reads_writes(1, 1).saturating_mul(
+                T::MaxAllocations::get().saturating_add(T::MaxRestakedAssets::get()).into()
+            )))]
+        pub fn restake_asset(
+            origin: OriginFor<T>,
+            asset: T::AssetId,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(AssetWhitelist::<T>::contains_key(asset), Error::<T>::AssetNotWhitelisted);
+            let config = Restakers::<T>::get(&who).ok_or(Error::<T>::NotRestaker)?;
+
+            if !AssetStakes::<T>::contains_key(&who, asset) {
+                let count = AssetStakes::<T>::iter_key_prefix(&who).count() as u32;
+                ensure!(count < T::MaxRestakedAssets::get(), Error::<T>::TooManyAssets);
+            }
+
+            T::Assets::transfer(asset, &who, &Self::asset_vault(), amount, Preservation::Preserve)?;
+
+            Self::remove_asset_stake(&config, asset);
+            AssetStakes::<T>::mutate(&who, asset, |staked| *staked = staked.saturating_add(amount));
+            Self::add_asset_stake(&config, asset);
+
+            Self::deposit_event(Event::AssetRestaked { account: who, asset, amount });
+            Ok(())
+        }
+
+        /// Unstake a restaked asset once the account's lock has ended
+        #[pallet::call_index(17)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 4)
+            .saturating_add(T::DbWeight::get().reads_writes(1, 1).saturating_mul(T::MaxAllocations::get().into())))]
+        pub fn unstake_asset(
+            origin: OriginFor<T>,
+            asset: T::AssetId,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let config = Restakers::<T>::get(&who).ok_or(Error::<T>::NotRestaker)?;
+            ensure!(
+                frame_system::Pallet::<T>::block_number() >= config.locked_until,
+                Error::<T>::StakeLocked
+            );
+            ensure!(amount <= AssetStakes::<T>::get(&who, asset), Error::<T>::InsufficientStake);
+
+            Self::remove_asset_stake(&config, asset);
+            AssetStakes::<T>::mutate_exists(&who, asset, |staked| {
+                let remaining = staked.unwrap_or_default().saturating_sub(amount);
+                *staked = if remaining.is_zero() { None } else { Some(remaining) };
+            });
+            Self::add_asset_stake(&config, asset);
+
+            T::Assets::transfer(asset, &Self::asset_vault(), &who, amount, Preservation::Expendable)?;
+
+            Self::deposit_event(Event::AssetUnstaked { account: who, asset, amount });
+            Ok(())
+        }
+
+        /// Handle a restaking message sent by a registered consumer parachain
+        ///
+        /// Consumer parachains may report misbehaviour and request reward
+        /// payouts. A slash report only reaches stake that secures, or
+        /// recently left, the reporting parachain. A payout is only accepted
+        /// from the account's own location on that parachain, and is never
+        /// compounded, since only a restaker may choose to restake its rewards.
+        #[pallet::call_index(18)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(10, 8)
+            .saturating_add(T::DbWeight::get().reads_writes(6, 6).saturating_mul(
+                T::MaxAllocations::get().saturating_add(T::MaxDelegators::get()).into()
+            )))]
+        pub fn handle_message(origin: OriginFor<T>, message: VersionedMessageOf<T>) -> DispatchResult {
+            let location = T::XcmOrigin::ensure_origin(origin)?;
+            let parachain_id = match location {
+                MultiLocation { parents: 1, interior: X1(Parachain(id)) } => id,
+                MultiLocation { parents: 1, interior: X2(Parachain(id), _) } => id,
+                _ => return Err(Error::<T>::NotParachainOrigin.into()),
+            };
+            ensure!(Self::is_registered(parachain_id), Error::<T>::UnknownParachain);
+
+            let VersionedRestakingMessage::V1(message) = message;
+            match message {
+                RestakingMessage::SlashReport { offender, fraction } => {
+                    ensure!(location.interior == X1(Parachain(parachain_id)), Error::<T>::NotParachainOrigin);
+                    Self::process_slash_report(parachain_id, &offender, fraction);
+                    Ok(())
+                }
+                RestakingMessage::RewardClaim { account } => {
+                    ensure!(
+                        T::AccountLocation::convert(location).as_ref() == Some(&account),
+                        Error::<T>::NotAccountOrigin
+                    );
+                    Self::do_claim_rewards(&account, false)
+                }
+                _ => Err(Error::<T>::UnexpectedMessage.into()),
+            }
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Restake assets across multiple parachains
+        pub fn restake_assets(
+            account: &T::AccountId,
+            amount: BalanceOf<T>,
+            strategy: RestakeStrategy,
+            allocations: Vec<RestakeAllocation>,
+            lock_duration: T::BlockNumber,
+        ) -> DispatchResult {
+            let allocations = if strategy == RestakeStrategy::Proportional {
+                let parachains = Parachains::<T>::get().iter().map(|info| info.parachain_id).collect();
+                Self::compute_optimal_allocation(parachains)
+            } else {
+                allocations
+            };
+            let allocations = allocation::resolve_allocations(strategy, &allocations)
+                .map_err(Error::<T>::from)?;
+            ensure!(
+                allocations.iter().all(|a| Self::is_registered(a.parachain_id)),
+                Error::<T>::UnknownParachain
+            );
+
+            let existing = Restakers::<T>::get(account);
+            let total_staked = existing
+                .as_ref()
+                .map(|config| config.total_staked)
+                .unwrap_or_else(Zero::zero)
+                .saturating_add(amount);
+            ensure!(total_staked >= T::MinRestake::get(), Error::<T>::RestakeBelowMinimum);
+
+            let mut config = existing.unwrap_or_else(|| RestakingConfig {
+                account: account.clone(),
+                strategy,
+                allocations: Vec::new(),
+                total_staked: Zero::zero(),
+                unbonding_period: T::UnbondingPeriod::get(),
+                min_stake_duration: T::MinStakeDuration::get(),
+                max_stake_duration: T::MaxStakeDuration::get(),
+                reward_frequency: T::RewardFrequency::get(),
+                lock_duration: Zero::zero(),
+                locked_until: Zero::zero(),
+            });
+            ensure!(
+                lock_duration >= config.min_stake_duration && lock_duration <= config.max_stake_duration,
+                Error::<T>::InvalidLockDuration
+            );
+
+            T::Currency::reserve(account, amount).map_err(|_| Error::<T>::InsufficientBalance)?;
+
+            let now = frame_system::Pallet::<T>::block_number();
+            let previous_lock = config.locked_until;
+            let previous = config.allocations.clone();
+            Self::remove_parachain_stake(&config);
+            config.strategy = strategy;
+            config.allocations = allocations;
+            config.total_staked = total_staked;
+            config.lock_duration = lock_duration;
+            config.locked_until = config.locked_until.max(now.saturating_add(lock_duration));
+            Self::add_parachain_stake(&config);
+            let locked_until = config.locked_until;
+            if locked_until != previous_lock {
+                LockExpiries::<T>::remove(previous_lock, account);
+                LockExpiries::<T>::insert(locked_until, account, ());
+                NextLockSweep::<T>::mutate(|next| {
+                    if next.is_none() {
+                        *next = Some(now);
+                    }
+                });
+            }
+            Restakers::<T>::insert(account, config);
+            if strategy == RestakeStrategy::Proportional {
+                ProportionalRestakers::<T>::insert(account, ());
+            } else {
+                ProportionalRestakers::<T>::remove(account);
+            }
+            Self::notify_allocations(account, &previous);
+
+            Self::deposit_event(Event::Restaked { account: account.clone(), amount, strategy, locked_until });
+            Ok(())
+        }
+
+        /// Unstake assets from multiple parachains into the withdrawal queue
+        pub fn unstake_assets(account: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+            let mut config = Restakers::<T>::get(account).ok_or(Error::<T>::NotRestaker)?;
+            ensure!(
+                frame_system::Pallet::<T>::block_number() >= config.locked_until,
+                Error::<T>::StakeLocked
+            );
+            ensure!(amount <= Self::free_stake(account, &config), Error::<T>::InsufficientStake);
+
+            let remaining = config.total_staked.saturating_sub(amount);
+            ensure!(
+                remaining.is_zero() || remaining >= T::MinRestake::get(),
+                Error::<T>::RestakeBelowMinimum
+            );
+            ensure!(
+                !remaining.is_zero() || AssetStakes::<T>::iter_key_prefix(account).next().is_none(),
+                Error::<T>::AssetsStillRestaked
+            );
+
+            let matures_at = frame_system::Pallet::<T>::block_number().saturating_add(config.unbonding_period);
+            let allocations = BoundedVec::try_from(config.allocations.clone())
+                .map_err(|_| Error::<T>::TooManyParachains)?;
+            Withdrawals::<T>::try_append(account, WithdrawalEntry { amount, allocations, matures_at })
+                .map_err(|_| Error::<T>::TooManyWithdrawals)?;
+
+            let previous = config.allocations.clone();
+            Self::remove_parachain_stake(&config);
+            config.total_staked = remaining;
+            if remaining.is_zero() {
+                Restakers::<T>::remove(account);
+                ProportionalRestakers::<T>::remove(account);
+                LockExpiries::<T>::remove(config.locked_until, account);
+            } else {
+                Self::add_parachain_stake(&config);
+                Restakers::<T>::insert(account, config);
+            }
+            Self::notify_allocations(account, &previous);
+
+            Self::deposit_event(Event::Unstaked { account: account.clone(), amount, matures_at });
+            Ok(())
+        }
+
+        /// Claim restaking rewards
+        ///
+        /// When part of the stake is delegated, the operator's fee is taken
+        /// from the matching share of the rewards. With `compound` the rest is
+        /// restaked under the account's current allocations.
+        pub fn do_claim_rewards(account: &T::AccountId, compound: bool) -> DispatchResult {
+            let config = Restakers::<T>::get(account);
+            ensure!(!compound || config.is_some(), Error::<T>::NotRestaker);
+            if let Some(config) = &config {
+                for allocation in &config.allocations {
+                    Self::settle_rewards(allocation.parachain_id, account);
+                }
+            }
+
+            let amount = PendingRewards::<T>::get(account);
+            ensure!(!amount.is_zero(), Error::<T>::NoRewards);
+
+            let pot = Self::reward_pot();
+            let mut operator_fee = BalanceOf::<T>::zero();
+            if let (Some(config), Some(delegation)) = (&config, Delegations::<T>::get(account)) {
+                if let Some(operator) = Operators::<T>::get(&delegation.operator) {
+                    let delegated_share = Perbill::from_rational(delegation.amount, config.total_staked);
+                    operator_fee = operator.fee * (delegated_share * amount);
+                    T::Currency::transfer(
+                        &pot,
+                        &delegation.operator,
+                        operator_fee,
+                        ExistenceRequirement::KeepAlive,
+                    )?;
+                }
+            }
+
+            let payout = amount.saturating_sub(operator_fee);
+            T::Currency::transfer(&pot, account, payout, ExistenceRequirement::KeepAlive)?;
+            PendingRewards::<T>::remove(account);
+            UnclaimedRewards::<T>::mutate(|unclaimed| *unclaimed = unclaimed.saturating_sub(amount));
+
+            if let (true, Some(mut config)) = (compound, config) {
+                T::Currency::reserve(account, payout).map_err(|_| Error::<T>::InsufficientBalance)?;
+                Self::remove_parachain_stake(&config);
+                config.total_staked = config.total_staked.saturating_add(payout);
+                Self::add_parachain_stake(&config);
+                let previous = config.allocations.clone();
+                Restakers::<T>::insert(account, config);
+                Self::notify_allocations(account, &previous);
+            }
+
+            Self::deposit_event(Event::RewardsClaimed {
+                account: account.clone(),
+                amount: payout,
+                operator_fee,
+                compounded: compound,
+            });
+            Ok(())
+        }
+
+        /// Advance each budgeted parachain's reward accumulator by one period
+        ///
+        /// Budgets are only committed while the reward pot can cover them on
+        /// top of the rewards already distributed but not yet claimed.
+        pub fn distribute_rewards(budgets: Vec<(u32, BalanceOf<T>)>) {
+            let pot_balance = T::Currency::free_balance(&Self::reward_pot())
+                .saturating_sub(T::Currency::minimum_balance());
+            let mut unclaimed = UnclaimedRewards::<T>::get();
+
+            for (parachain_id, per_period) in budgets {
+                let total_shares = TotalShares::<T>::get(parachain_id);
+                if total_shares.is_zero() {
+                    continue;
+                }
+
+                let amount = per_period.min(pot_balance.saturating_sub(unclaimed));
+                if amount.is_zero() {
+                    break;
+                }
+
+                RewardPerShare::<T>::mutate(parachain_id, |acc| {
+                    *acc = acc.saturating_add(rewards::reward_per_share(amount, total_shares))
+                });
+                unclaimed = unclaimed.saturating_add(amount);
+                Self::deposit_event(Event::RewardsDistributed { parachain_id, amount });
+            }
+
+            UnclaimedRewards::<T>::put(unclaimed);
+        }
+
+        /// Account holding the reward pot
+        pub fn reward_pot() -> T::AccountId {
+            T::PalletId::get().into_account_truncating()
+        }
+
+        /// Compute optimal allocation based on parachain security needs
+        ///
+        /// Each registered parachain gets a share proportional to its security
+        /// weight. Unregistered parachains are ignored.
+        pub fn compute_optimal_allocation(parachains: Vec<u32>) -> Vec<RestakeAllocation> {
+            let registered = Parachains::<T>::get();
+            let weights: Vec<(u32, u32)> = parachains
+                .into_iter()
+                .filter_map(|parachain_id| {
+                    registered
+                        .iter()
+                        .find(|info| info.parachain_id == parachain_id)
+                        .map(|info| (parachain_id, info.security_weight))
+                })
+                .collect();
+            allocation::normalize_weights(&weights).unwrap_or_default()
+        }
+
+        /// Whether a parachain is registered
+        pub fn is_registered(parachain_id: u32) -> bool {
+            Parachains::<T>::get().iter().any(|info| info.parachain_id == parachain_id)
+        }
+
+        /// Process queued rebalance jobs, one account at a time, within a weight budget
+        pub fn process_rebalance_queue(remaining_weight: Weight) -> Weight {
+            let per_account = T::DbWeight::get().reads_writes(8, 6).saturating_add(
+                T::DbWeight::get()
+                    .reads_writes(8, 8)
+                    .saturating_mul(T::MaxAllocations::get().into()),
+            );
+            let mut consumed = T::DbWeight::get().reads_writes(1, 1);
+            if remaining_weight.any_lt(consumed.saturating_add(per_account)) {
+                return Weight::zero();
+            }
+
+            let mut queue = RebalanceQueue::<T>::get();
+            if queue.is_empty() {
+                return T::DbWeight::get().reads(1);
+            }
+
+            while let Some(job) = queue.get_mut(0) {
+                if remaining_weight.any_lt(consumed.saturating_add(per_account)) {
+                    break;
+                }
+                consumed = consumed.saturating_add(per_account);
+
+                let done = match job {
+                    RebalanceJob::ParachainLeft { parachain_id } => {
+                        let parachain_id = *parachain_id;
+                        match Shares::<T>::iter_key_prefix(parachain_id).next() {
+                            Some(account) => {
+                                Self::try_rebalance(&account, Some(parachain_id), parachain_id);
+                                // Entries left behind by a stale allocation or a failed
+                                // rebalance must not stall the job
+                                Self::release_shares(parachain_id, &account);
+                                false
+                            }
+                            None => true,
+                        }
+                    }
+                    RebalanceJob::ParachainJoined { parachain_id, cursor } => {
+                        let next = match cursor {
+                            Some(raw_key) => ProportionalRestakers::<T>::iter_keys_from(raw_key.to_vec()).next(),
+                            None => ProportionalRestakers::<T>::iter_keys().next(),
+                        };
+                        match next {
+                            Some(account) => {
+                                Self::try_rebalance(&account, None, *parachain_id);
+                                *cursor = BoundedVec::try_from(ProportionalRestakers::<T>::hashed_key_for(&account)).ok();
+                                cursor.is_none()
+                            }
+                            None => true,
+                        }
+                    }
+                };
+                if done {
+                    queue.remove(0);
+                }
+            }
+
+            RebalanceQueue::<T>::put(queue);
+            consumed
+        }
+
+        /// Recompute an account's allocations after the parachain set changed
+        ///
+        /// `Proportional` restakers follow the registry. Other restakers drop
+        /// the `left` parachain and keep the relative weights of the rest. The
+        /// stake that was allocated to `left` stays slashable by it for the
+        /// restaker's unbonding period, merged into any exit from the same
+        /// parachain. Fails without changes if the restaker has no room for
+        /// another exit.
+        fn rebalance_account(account: &T::AccountId, left: Option<u32>) -> Result<(), Error<T>> {
+            let mut config = match Restakers::<T>::get(account) {
+                Some(config) => config,
+                None => return Ok(()),
+            };
+
+            let allocations = if config.strategy == RestakeStrategy::Proportional {
+                let parachains = Parachains::<T>::get().iter().map(|info| info.parachain_id).collect();
+                Self::compute_optimal_allocation(parachains)
+            } else {
+                let weights: Vec<(u32, u32)> = config
+                    .allocations
+                    .iter()
+                    .filter(|a| Some(a.parachain_id) != left)
+                    .map(|a| (a.parachain_id, a.percentage as u32))
+                    .collect();
+                allocation::normalize_weights(&weights).unwrap_or_default()
+            };
+            if allocations == config.allocations {
+                return Ok(());
+            }
+
+            let now = frame_system::Pallet::<T>::block_number();
+            let until = now.saturating_add(config.unbonding_period);
+            let mut exits = ParachainExits::<T>::get(account);
+            exits.retain(|exit| exit.until > now);
+            for (parachain_id, amount) in allocation::split_amount(config.total_staked, &config.allocations) {
+                if allocations.iter().any(|a| a.parachain_id == parachain_id) || amount.is_zero() {
+                    continue;
+                }
+                match exits.iter_mut().find(|exit| exit.parachain_id == parachain_id) {
+                    Some(exit) => {
+                        exit.amount = exit.amount.saturating_add(amount);
+                        exit.until = until;
+                    }
+                    None => exits
+                        .try_push(ParachainExit { parachain_id, amount, until })
+                        .map_err(|_| Error::<T>::TooManyParachainExits)?,
+                }
+            }
+            ParachainExits::<T>::insert(account, exits);
+
+            let previous = config.allocations.clone();
+            Self::remove_parachain_stake(&config);
+            config.allocations = allocations.clone();
+            Self::add_parachain_stake(&config);
+            Restakers::<T>::insert(account, config);
+            Self::notify_allocations(account, &previous);
+
+            Self::deposit_event(Event::Rebalanced { account: account.clone(), allocations });
+            Ok(())
+        }
+
+        /// Rebalance an account, recording a failure instead of stalling the job
+        fn try_rebalance(account: &T::AccountId, left: Option<u32>, parachain_id: u32) {
+            if let Err(error) = Self::rebalance_account(account, left) {
+                Self::deposit_event(Event::RebalanceFailed {
+                    account: account.clone(),
+                    parachain_id,
+                    error: error.into(),
+                });
+            }
+        }
+
+        fn queue_rebalance(job: RebalanceJob) -> DispatchResult {
+            RebalanceQueue::<T>::try_append(job).map_err(|_| Error::<T>::TooManyRebalanceJobs.into())
+        }
+
+        /// Calculate restaking rewards claimable by an account
+        pub fn calculate_rewards(account: &T::AccountId) -> BalanceOf<T> {
+            let pending = PendingRewards::<T>::get(account);
+            match Restakers::<T>::get(account) {
+                Some(config) => config.allocations.iter().fold(pending, |acc, allocation| {
+                    acc.saturating_add(Self::unsettled_rewards(allocation.parachain_id, account))
+                }),
+                None => pending,
+            }
+        }
+
+        /// Reward multiplier an account's lock currently earns
+        pub fn reward_multiplier(account: &T::AccountId) -> FixedU128 {
+            match Restakers::<T>::get(account) {
+                Some(config) => Self::lock_multiplier(&config),
+                None => FixedU128::zero(),
+            }
+        }
+
+        /// Reward multiplier of a restake, 1x once its lock has ended
+        fn lock_multiplier(config: &RestakingConfigOf<T>) -> FixedU128 {
+            if frame_system::Pallet::<T>::block_number() >= config.locked_until {
+                return FixedU128::one();
+            }
+            T::LockBoost::multiplier(config.lock_duration, config.min_stake_duration, config.max_stake_duration)
+        }
+
+        /// Drop the boost of restakers whose lock has ended, within a weight budget
+        ///
+        /// Rewards accrued under the boost are settled before the shares are
+        /// re-weighted.
+        pub fn sweep_lock_expiries(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+            let per_account = T::DbWeight::get()
+                .reads_writes(4, 2)
+                .saturating_add(T::DbWeight::get().reads_writes(8, 8).saturating_mul(T::MaxAllocations::get().into()));
+            let per_block = T::DbWeight::get().reads(1);
+
+            let mut consumed = T::DbWeight::get().reads_writes(1, 1);
+            if remaining_weight.any_lt(consumed.saturating_add(per_account)) {
+                return Weight::zero();
+            }
+
+            let mut cursor = match NextLockSweep::<T>::get() {
+                Some(cursor) if cursor <= now => cursor,
+                _ => return T::DbWeight::get().reads(1),
+            };
+            while cursor <= now && !remaining_weight.any_lt(consumed.saturating_add(per_account)) {
+                match LockExpiries::<T>::iter_key_prefix(cursor).next() {
+                    Some(account) => {
+                        consumed = consumed.saturating_add(per_account);
+                        LockExpiries::<T>::remove(cursor, &account);
+                        if let Some(config) = Restakers::<T>::get(&account) {
+                            Self::remove_parachain_stake(&config);
+                            Self::add_parachain_stake(&config);
+                        }
+                    }
+                    None => {
+                        consumed = consumed.saturating_add(per_block);
+                        cursor = cursor.saturating_add(One::one());
+                    }
+                }
+            }
+            NextLockSweep::<T>::put(cursor);
+
+            consumed
+        }
+
+        /// Blocks left until an account's restake can be unstaked
+        pub fn remaining_lock(account: &T::AccountId) -> T::BlockNumber {
+            Restakers::<T>::get(account)
+                .map(|config| config.locked_until.saturating_sub(frame_system::Pallet::<T>::block_number()))
+                .unwrap_or_else(Zero::zero)
+        }
+
+        /// Stake exposed to an operator, per parachain it secures
+        ///
+        /// A delegator's stake only counts towards the parachains that are
+        /// both targeted by the operator and allowed by the delegator's
+        /// allocations.
+        pub fn operator_exposure(operator: &T::AccountId) -> Vec<(u32, BalanceOf<T>)> {
+            let targets = match Operators::<T>::get(operator) {
+                Some(info) => info.targets,
+                None => return Vec::new(),
+            };
+
+            let mut exposure: Vec<(u32, BalanceOf<T>)> =
+                targets.iter().map(|parachain_id| (*parachain_id, Zero::zero())).collect();
+            for delegator in OperatorDelegators::<T>::iter_key_prefix(operator) {
+                let delegated = Self::exposure_to(operator, &delegator);
+                let config = match Restakers::<T>::get(&delegator) {
+                    Some(config) => config,
+                    None => continue,
+                };
+                for (parachain_id, part) in allocation::split_amount(delegated, &config.allocations) {
+                    if let Some((_, total)) = exposure.iter_mut().find(|(id, _)| *id == parachain_id) {
+                        *total = total.saturating_add(part);
+                    }
+                }
+            }
+
+            exposure
+        }
+
+        /// Stake of a restaker that is neither delegated nor unbonding
+        pub fn free_stake(account: &T::AccountId, config: &RestakingConfigOf<T>) -> BalanceOf<T> {
+            let delegated = Delegations::<T>::get(account)
+                .map(|delegation| delegation.amount)
+                .unwrap_or_else(Zero::zero);
+            let unbonding = Undelegations::<T>::get(account)
+                .iter()
+                .fold(BalanceOf::<T>::zero(), |acc, entry| acc.saturating_add(entry.amount));
+            config.total_staked.saturating_sub(delegated).saturating_sub(unbonding)
+        }
+
+        /// Slash an operator's bond and pass the slash through to its delegators
+        ///
+        /// With a `parachain_id`, delegators only lose `fraction` of the part
+        /// of their delegated and unbonding stake they allocate to that
+        /// parachain. Returns the total amount slashed.
+        pub fn do_slash_operator(
+            operator: &T::AccountId,
+            fraction: Perbill,
+            parachain_id: Option<u32>,
+        ) -> Result<BalanceOf<T>, DispatchError> {
+            let mut info = Operators::<T>::get(operator).ok_or(Error::<T>::OperatorNotFound)?;
+
+            let bond_slash = fraction * info.bond;
+            let (imbalance, _) = T::Currency::slash_reserved(operator, bond_slash);
+            T::Slash::on_unbalanced(imbalance);
+            info.bond = info.bond.saturating_sub(bond_slash);
+            let mut total = bond_slash;
+
+            let delegators: Vec<T::AccountId> = OperatorDelegators::<T>::iter_key_prefix(operator).collect();
+            for delegator in delegators {
+                let config = Restakers::<T>::get(&delegator);
+                let allocations = config.as_ref().map(|config| config.allocations.clone()).unwrap_or_default();
+                let exposed = |amount: BalanceOf<T>| match parachain_id {
+                    Some(parachain_id) => Self::allocated_to(amount, &allocations, parachain_id),
+                    None => amount,
+                };
+                let mut slashed = BalanceOf::<T>::zero();
+
+                if let Some(mut delegation) = Delegations::<T>::get(&delegator) {
+                    if &delegation.operator == operator {
+                        let part = fraction * exposed(delegation.amount);
+                        delegation.amount = delegation.amount.saturating_sub(part);
+                        info.total_delegated = info.total_delegated.saturating_sub(part);
+                        slashed = slashed.saturating_add(part);
+                        Delegations::<T>::insert(&delegator, delegation);
+                    }
+                }
+
+                Undelegations::<T>::mutate(&delegator, |pending| {
+                    for entry in pending.iter_mut().filter(|entry| &entry.operator == operator) {
+                        let part = fraction * exposed(entry.amount);
+                        entry.amount = entry.amount.saturating_sub(part);
+                        slashed = slashed.saturating_add(part);
+                    }
+                });
+
+                // Restaked assets lose the share the native stake secures the parachain with
+                let scope = parachain_id.map(|parachain_id| {
+                    let allocated = config
+                        .as_ref()
+                        .map(|config| Self::allocated_to(config.total_staked, &allocations, parachain_id))
+                        .unwrap_or_else(Zero::zero);
+                    (parachain_id, Perbill::from_rational(slashed, allocated))
+                });
+                let slashed = Self::slash_stake(&delegator, slashed, scope);
+                total = total.saturating_add(slashed);
+                Self::deposit_event(Event::DelegatorSlashed {
+                    delegator,
+                    operator: operator.clone(),
+                    amount: slashed,
+                });
+            }
+
+            Operators::<T>::insert(operator, info);
+            Self::deposit_event(Event::OperatorSlashed { operator: operator.clone(), fraction, amount: total });
+            Ok(total)
+        }
+
+        /// Slash restaked stake, returning the amount actually slashed
+        ///
+        /// The delegation loses the slashed amount, and pending undelegations
+        /// lose whatever would still exceed the remaining stake, so a restaker
+        /// never has more delegated or unbonding than it has staked.
+        ///
+        /// A slash `scope` of `(parachain_id, fraction)` limits the slash of
+        /// restaked assets to `fraction` of the part allocated to that
+        /// parachain. Without a scope, every asset loses the same share as the
+        /// native stake.
+        pub fn slash_restaker(
+            account: &T::AccountId,
+            amount: BalanceOf<T>,
+            scope: Option<(u32, Perbill)>,
+        ) -> BalanceOf<T> {
+            let amount = Self::slash_stake(account, amount, scope);
+            if amount.is_zero() {
+                return amount;
+            }
+
+            if let Some(mut delegation) = Delegations::<T>::get(account) {
+                let part = amount.min(delegation.amount);
+                Operators::<T>::mutate(&delegation.operator, |maybe_operator| {
+                    if let Some(info) = maybe_operator {
+                        info.total_delegated = info.total_delegated.saturating_sub(part);
+                    }
+                });
+                delegation.amount = delegation.amount.saturating_sub(part);
+                if delegation.amount.is_zero() {
+                    Delegations::<T>::remove(account);
+                    Self::release_exposure(&delegation.operator, account);
+                } else {
+                    Delegations::<T>::insert(account, delegation);
+                }
+            }
+
+            if let Some(config) = Restakers::<T>::get(account) {
+                let delegated = Delegations::<T>::get(account)
+                    .map(|delegation| delegation.amount)
+                    .unwrap_or_else(Zero::zero);
+                let mut excess = Undelegations::<T>::get(account)
+                    .iter()
+                    .fold(delegated, |acc, entry| acc.saturating_add(entry.amount))
+                    .saturating_sub(config.total_staked);
+                if !excess.is_zero() {
+                    Undelegations::<T>::mutate(account, |pending| {
+                        for entry in pending.iter_mut().rev() {
+                            let part = excess.min(entry.amount);
+                            entry.amount = entry.amount.saturating_sub(part);
+                            excess = excess.saturating_sub(part);
+                        }
+                    });
+                }
+            }
+
+            amount
+        }
+
+        /// Slash the reserved stake of a restaker without touching its delegation
+        fn slash_stake(
+            account: &T::AccountId,
+            amount: BalanceOf<T>,
+            scope: Option<(u32, Perbill)>,
+        ) -> BalanceOf<T> {
+            let mut config = match Restakers::<T>::get(account) {
+                Some(config) => config,
+                None => return Zero::zero(),
+            };
+            let amount = amount.min(config.total_staked);
+            if amount.is_zero() {
+                return amount;
+            }
+            let (parachain_id, asset_fraction) = match scope {
+                Some((parachain_id, fraction)) => (Some(parachain_id), fraction),
+                None => (None, Perbill::from_rational(amount, config.total_staked)),
+            };
+
+            let (imbalance, _) = T::Currency::slash_reserved(account, amount);
+            T::Slash::on_unbalanced(imbalance);
+
+            Self::remove_parachain_stake(&config);
+            config.total_staked = config.total_staked.saturating_sub(amount);
+            Self::add_parachain_stake(&config);
+            Self::slash_assets(&config, asset_fraction, parachain_id);
+            for allocation in &config.allocations {
+                Self::send_to_parachain(
+                    allocation.parachain_id,
+                    RestakingMessage::SlashNotification { account: account.clone(), amount },
+                );
+            }
+            Restakers::<T>::insert(account, config);
+
+            amount
+        }
+
+        /// Apply a slash reported by a consumer parachain
+        ///
+        /// An operator securing the parachain is slashed together with the
+        /// stake its delegators allocate to the parachain. A restaker loses
+        /// `fraction` of the stake allocated to the parachain, including stake
+        /// that left it or is queued for withdrawal less than an unbonding
+        /// period ago.
+        pub fn process_slash_report(parachain_id: u32, offender: &T::AccountId, fraction: Perbill) {
+            let mut amount = Self::slash_withdrawals(offender, parachain_id, fraction);
+
+            if Operators::<T>::get(offender).map_or(false, |info| info.targets.contains(&parachain_id)) {
+                if let Ok(slashed) = Self::do_slash_operator(offender, fraction, Some(parachain_id)) {
+                    amount = amount.saturating_add(slashed);
+                }
+            }
+
+            if let Some(config) = Restakers::<T>::get(offender) {
+                let now = frame_system::Pallet::<T>::block_number();
+                let allocated = Self::allocated_to(config.total_staked, &config.allocations, parachain_id);
+                let mut exposure = fraction * allocated;
+
+                ParachainExits::<T>::mutate(offender, |exits| {
+                    exits.retain(|exit| exit.until > now);
+                    for exit in exits.iter_mut().filter(|exit| exit.parachain_id == parachain_id) {
+                        let part = fraction * exit.amount;
+                        exit.amount = exit.amount.saturating_sub(part);
+                        exposure = exposure.saturating_add(part);
+                    }
+                });
+
+                let slashed = Self::slash_restaker(offender, exposure, Some((parachain_id, fraction)));
+                amount = amount.saturating_add(slashed);
+            }
+
+            Self::deposit_event(Event::SlashReportProcessed {
+                parachain_id,
+                offender: offender.clone(),
+                amount,
+            });
+        }
+
+        /// Slash the part of unmatured withdrawals that is leaving a parachain
+        fn slash_withdrawals(account: &T::AccountId, parachain_id: u32, fraction: Perbill) -> BalanceOf<T> {
+            let now = frame_system::Pallet::<T>::block_number();
+            let mut total = BalanceOf::<T>::zero();
+
+            Withdrawals::<T>::mutate(account, |queue| {
+                for entry in queue.iter_mut().filter(|entry| entry.matures_at > now) {
+                    let part = fraction * Self::allocated_to(entry.amount, &entry.allocations, parachain_id);
+                    entry.amount = entry.amount.saturating_sub(part);
+                    total = total.saturating_add(part);
+                }
+            });
+            if total.is_zero() {
+                return total;
+            }
+
+            let (imbalance, _) = T::Currency::slash_reserved(account, total);
+            T::Slash::on_unbalanced(imbalance);
+
+            Self::deposit_event(Event::WithdrawalSlashed { account: account.clone(), parachain_id, amount: total });
+            total
+        }
+
+        /// Part of `amount` that `allocations` assign to a parachain
+        fn allocated_to(amount: BalanceOf<T>, allocations: &[RestakeAllocation], parachain_id: u32) -> BalanceOf<T> {
+            allocation::split_amount(amount, allocations)
+                .into_iter()
+                .find(|(id, _)| *id == parachain_id)
+                .map(|(_, part)| part)
+                .unwrap_or_else(Zero::zero)
+        }
+
+        /// Tell consumer parachains how much an account now allocates to them
+        ///
+        /// Parachains dropped from `previous` are told the allocation is zero.
+        fn notify_allocations(account: &T::AccountId, previous: &[RestakeAllocation]) {
+            let current = Restakers::<T>::get(account)
+                .map(|config| allocation::split_amount(config.total_staked, &config.allocations))
+                .unwrap_or_default();
+
+            let dropped = previous
+                .iter()
+                .filter(|a| !current.iter().any(|(id, _)| *id == a.parachain_id))
+                .map(|a| (a.parachain_id, Zero::zero()));
+            for (parachain_id, amount) in current.clone().into_iter().chain(dropped) {
+                Self::send_to_parachain(
+                    parachain_id,
+                    RestakingMessage::AllocationUpdate { account: account.clone(), amount },
+                );
+            }
+        }
+
+        /// Send a message to a registered consumer parachain as an XCM `Transact`
+        ///
+        /// Delivery failures are reported through an event rather than
+        /// failing the operation that triggered the message.
+        pub fn send_to_parachain(parachain_id: u32, message: RestakingMessage<T::AccountId, BalanceOf<T>>) {
+            if !Self::is_registered(parachain_id) {
+                return;
+            }
+
+            let [pallet_index, call_index] = T::ConsumerCallIndex::get();
+            let call = (pallet_index, call_index, VersionedMessageOf::<T>::from(message)).encode();
+            let xcm = Xcm(vec![
+                UnpaidExecution { weight_limit: Unlimited, check_origin: None },
+                Transact {
+                    origin_kind: OriginKind::Native,
+                    require_weight_at_most: T::ConsumerCallWeight::get(),
+                    call: call.into(),
+                },
+            ]);
+
+            let destination = MultiLocation::new(1, X1(Parachain(parachain_id)));
+            if send_xcm::<T::XcmSender>(destination, xcm).is_err() {
+                Self::deposit_event(Event::XcmSendFailed { parachain_id });
+            }
+        }
+
+        /// Burn the same fraction of every asset a restaker has restaked
+        ///
+        /// With a `parachain_id`, only the part of each asset allocated to
+        /// that parachain is exposed.
+        fn slash_assets(config: &RestakingConfigOf<T>, fraction: Perbill, parachain_id: Option<u32>) {
+            let account = &config.account;
+            let vault = Self::asset_vault();
+            let staked: Vec<(T::AssetId, BalanceOf<T>)> = AssetStakes::<T>::iter_prefix(account).collect();
+
+            for (asset, amount) in staked {
+                let exposed = match parachain_id {
+                    Some(parachain_id) => Self::allocated_to(amount, &config.allocations, parachain_id),
+                    None => amount,
+                };
+                let part = fraction * exposed;
+                if part.is_zero() {
+                    continue;
+                }
+                let burned = T::Assets::burn_from(asset, &vault, part, Precision::BestEffort, Fortitude::Force)
+                    .unwrap_or_else(|_| Zero::zero());
+
+                Self::remove_asset_stake(config, asset);
+                AssetStakes::<T>::insert(account, asset, amount.saturating_sub(burned));
+                Self::add_asset_stake(config, asset);
+
+                Self::deposit_event(Event::AssetSlashed { account: account.clone(), asset, amount: burned });
+            }
+        }
+
+        /// Security a parachain receives, valued in the native currency
+        ///
+        /// Restaked assets count at their oracle price minus the asset's
+        /// haircut. Unpriced, stale or delisted assets count for nothing.
+        pub fn parachain_security(parachain_id: u32) -> BalanceOf<T> {
+            ParachainAssetStake::<T>::iter_prefix(parachain_id).fold(
+                ParachainStake::<T>::get(parachain_id),
+                |total, (asset, amount)| total.saturating_add(Self::asset_value(&asset, amount)),
+            )
+        }
+
+        /// Value of an asset amount in the native currency, after haircut
+        pub fn asset_value(asset: &T::AssetId, amount: BalanceOf<T>) -> BalanceOf<T> {
+            let now = frame_system::Pallet::<T>::block_number();
+            match (AssetWhitelist::<T>::get(asset), T::PriceOracle::price(asset)) {
+                (Some(haircut), Some((price, updated_at)))
+                    if now.saturating_sub(updated_at) <= T::MaxPriceAge::get() =>
+                {
+                    let value = price.saturating_mul_int(amount);
+                    value.saturating_sub(haircut * value)
+                }
+                _ => Zero::zero(),
+            }
+        }
+
+        /// Account holding restaked assets
+        pub fn asset_vault() -> T::AccountId {
+            T::PalletId::get().into_sub_account_truncating(b"assets")
+        }
+
+        fn add_asset_stake(config: &RestakingConfigOf<T>, asset: T::AssetId) {
+            let amount = AssetStakes::<T>::get(&config.account, asset);
+            for (parachain_id, part) in allocation::split_amount(amount, &config.allocations) {
+                ParachainAssetStake::<T>::mutate(parachain_id, asset, |stake| *stake = stake.saturating_add(part));
+            }
+        }
+
+        fn remove_asset_stake(config: &RestakingConfigOf<T>, asset: T::AssetId) {
+            let amount = AssetStakes::<T>::get(&config.account, asset);
+            for (parachain_id, part) in allocation::split_amount(amount, &config.allocations) {
+                ParachainAssetStake::<T>::mutate(parachain_id, asset, |stake| *stake = stake.saturating_sub(part));
+            }
+        }
+
+        /// Stake of a restaker delegated to or unbonding from an operator
+        fn exposure_to(operator: &T::AccountId, account: &T::AccountId) -> BalanceOf<T> {
+            let delegated = Delegations::<T>::get(account)
+                .filter(|delegation| &delegation.operator == operator)
+                .map(|delegation| delegation.amount)
+                .unwrap_or_else(Zero::zero);
+            Undelegations::<T>::get(account)
+                .iter()
+                .filter(|entry| &entry.operator == operator)
+                .fold(delegated, |acc, entry| acc.saturating_add(entry.amount))
+        }
+
+        /// Drop a restaker from an operator once nothing is exposed to it
+        fn release_exposure(operator: &T::AccountId, account: &T::AccountId) {
+            let still_exposed = Delegations::<T>::get(account)
+                .map_or(false, |delegation| &delegation.operator == operator)
+                || Undelegations::<T>::get(account).iter().any(|entry| &entry.operator == operator);
+            if still_exposed || !OperatorDelegators::<T>::contains_key(operator, account) {
+                return;
+            }
+
+            OperatorDelegators::<T>::remove(operator, account);
+            Operators::<T>::mutate(operator, |maybe_operator| {
+                if let Some(info) = maybe_operator {
+                    info.delegator_count = info.delegator_count.saturating_sub(1);
+                }
+            });
+        }
+
+        /// Allocate a restaker's stake, reward shares and restaked assets
+        fn add_parachain_stake(config: &RestakingConfigOf<T>) {
+            let account = &config.account;
+            for (parachain_id, part) in allocation::split_amount(config.total_staked, &config.allocations) {
+                ParachainStake::<T>::mutate(parachain_id, |stake| *stake = stake.saturating_add(part));
+            }
+
+            let multiplier = Self::lock_multiplier(config);
+            let boosted = rewards::boosted_stake(config.total_staked, multiplier);
+            for (parachain_id, shares) in allocation::split_amount(boosted, &config.allocations) {
+                Self::settle_rewards(parachain_id, account);
+                Shares::<T>::mutate(parachain_id, account, |s| *s = s.saturating_add(shares));
+                TotalShares::<T>::mutate(parachain_id, |total| *total = total.saturating_add(shares));
+                Self::reset_reward_debt(parachain_id, account);
+            }
+
+            for asset in AssetStakes::<T>::iter_key_prefix(account) {
+                Self::add_asset_stake(config, asset);
+            }
+        }
+
+        /// Release a restaker's stake, reward shares and restaked assets, settling rewards first
+        fn remove_parachain_stake(config: &RestakingConfigOf<T>) {
+            let account = &config.account;
+            for (parachain_id, part) in allocation::split_amount(config.total_staked, &config.allocations) {
+                ParachainStake::<T>::mutate(parachain_id, |stake| *stake = stake.saturating_sub(part));
+            }
+
+            for allocation in &config.allocations {
+                Self::release_shares(allocation.parachain_id, account);
+            }
+
+            for asset in AssetStakes::<T>::iter_key_prefix(account) {
+                Self::remove_asset_stake(config, asset);
+            }
+        }
+
+        /// Settle a restaker's rewards on a parachain and remove its shares there
+        fn release_shares(parachain_id: u32, account: &T::AccountId) {
+            Self::settle_rewards(parachain_id, account);
+            let shares = Shares::<T>::take(parachain_id, account);
+            TotalShares::<T>::mutate(parachain_id, |total| *total = total.saturating_sub(shares));
+            Self::reset_reward_debt(parachain_id, account);
+        }
+
+        /// Rewards accrued on a parachain since the restaker last settled
+        fn unsettled_rewards(parachain_id: u32, account: &T::AccountId) -> BalanceOf<T> {
+            rewards::accrued_rewards(
+                RewardPerShare::<T>::get(parachain_id),
+                Shares::<T>::get(parachain_id, account),
+                RewardDebt::<T>::get(parachain_id, account),
+            )
+        }
+
+        /// Move accrued rewards into the restaker's pending rewards
+        fn settle_rewards(parachain_id: u32, account: &T::AccountId) {
+            let unsettled = Self::unsettled_rewards(parachain_id, account);
+            if !unsettled.is_zero() {
+                PendingRewards::<T>::mutate(account, |pending| *pending = pending.saturating_add(unsettled));
+            }
+            Self::reset_reward_debt(parachain_id, account);
+        }
+
+        /// Mark the accumulator as fully accounted for the restaker's current shares
+        fn reset_reward_debt(parachain_id: u32, account: &T::AccountId) {
+            let debt = RewardPerShare::<T>::get(parachain_id)
+                .saturating_mul_int(Shares::<T>::get(parachain_id, account));
+            if debt.is_zero() {
+                RewardDebt::<T>::remove(parachain_id, account);
+            } else {
+                RewardDebt::<T>::insert(parachain_id, account, debt);
+            }
+        }
+    }
+
+    impl<T> From<AllocationError> for Error<T> {
+        fn from(error: AllocationError) -> Self {
+            match error {
+                AllocationError::Empty => Error::<T>::NoAllocations,
+                AllocationError::Duplicate => Error::<T>::DuplicateAllocation,
+                AllocationError::InvalidTotal => Error::<T>::InvalidAllocationTotal,
+                AllocationError::NotSingle => Error::<T>::NotSingleAllocation,
+            }
+        }
+    }
 }
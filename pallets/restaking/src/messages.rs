@@ -0,0 +1,47 @@
+//! Restaking messages exchanged with consumer parachains
+//!
+//! Messages travel as the single argument of an XCM `Transact` call. They are
+//! versioned so that consumer parachains can upgrade independently of the
+//! security layer.
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{Perbill, RuntimeDebug};
+
+/// Restaking instruction, version 1
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum RestakingMessage<AccountId, Balance> {
+    /// Stake an account allocates to the receiving parachain changed
+    AllocationUpdate {
+        account: AccountId,
+        amount: Balance,
+    },
+    /// The sending parachain reports misbehaviour by a restaker or operator
+    SlashReport {
+        offender: AccountId,
+        fraction: Perbill,
+    },
+    /// An account securing the receiving parachain was slashed
+    SlashNotification {
+        account: AccountId,
+        amount: Balance,
+    },
+    /// Pay out the rewards accrued by an account, sent from the account's own location
+    RewardClaim {
+        account: AccountId,
+    },
+}
+
+/// Versioned restaking message
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum VersionedRestakingMessage<AccountId, Balance> {
+    /// Version 1
+    #[codec(index = 1)]
+    V1(RestakingMessage<AccountId, Balance>),
+}
+
+impl<AccountId, Balance> From<RestakingMessage<AccountId, Balance>> for VersionedRestakingMessage<AccountId, Balance> {
+    fn from(message: RestakingMessage<AccountId, Balance>) -> Self {
+        VersionedRestakingMessage::V1(message)
+    }
+}
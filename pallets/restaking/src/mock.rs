@@ -0,0 +1,201 @@
+//! Mock runtime for restaking tests
+
+use crate as pallet_restaking;
+use crate::{oracle::MockPriceOracle, rewards::LinearBoost};
+use frame_support::{
+    construct_runtime, parameter_types,
+    traits::{AsEnsureOriginWithArg, ConstU128, ConstU32, ConstU64, Everything, GenesisBuild},
+    weights::Weight,
+    PalletId,
+};
+use frame_system::{EnsureNever, EnsureRoot, EnsureSigned};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup},
+    FixedU128,
+};
+use xcm::latest::prelude::*;
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type BlockNumber = u64;
+pub type AssetId = u32;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CHARLIE: AccountId = 3;
+pub const DAVE: AccountId = 4;
+
+pub const PARA_A: u32 = 2000;
+pub const PARA_B: u32 = 2001;
+pub const PARA_C: u32 = 2002;
+
+pub const LP_TOKEN: AssetId = 7;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Assets: pallet_assets,
+        Restaking: pallet_restaking,
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = BlockNumber;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type WeightInfo = ();
+    type Balance = Balance;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type ReserveIdentifier = [u8; 8];
+    type HoldIdentifier = ();
+    type FreezeIdentifier = ();
+    type MaxLocks = ConstU32<10>;
+    type MaxReserves = ConstU32<10>;
+    type MaxHolds = ConstU32<0>;
+    type MaxFreezes = ConstU32<0>;
+}
+
+impl pallet_assets::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Balance = Balance;
+    type RemoveItemsLimit = ConstU32<100>;
+    type AssetId = AssetId;
+    type AssetIdParameter = AssetId;
+    type Currency = Balances;
+    type CreateOrigin = AsEnsureOriginWithArg<EnsureSigned<AccountId>>;
+    type ForceOrigin = EnsureRoot<AccountId>;
+    type AssetDeposit = ConstU128<0>;
+    type AssetAccountDeposit = ConstU128<0>;
+    type MetadataDepositBase = ConstU128<0>;
+    type MetadataDepositPerByte = ConstU128<0>;
+    type ApprovalDeposit = ConstU128<0>;
+    type StringLimit = ConstU32<32>;
+    type Freezer = ();
+    type Extra = ();
+    type CallbackHandle = ();
+    type WeightInfo = ();
+    #[cfg(feature = "runtime-benchmarks")]
+    type BenchmarkHelper = ();
+}
+
+parameter_types! {
+    pub const RestakingPalletId: PalletId = PalletId(*b"py/rstkg");
+    pub const ConsumerCallIndex: [u8; 2] = [60, 0];
+    pub ConsumerCallWeight: Weight = Weight::from_parts(1_000_000_000, 64 * 1024);
+    pub MaxBoost: FixedU128 = FixedU128::from_u32(2);
+}
+
+impl pallet_restaking::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Currency = Balances;
+    type Slash = ();
+    type SlashOrigin = EnsureRoot<AccountId>;
+    type RewardOrigin = EnsureRoot<AccountId>;
+    type PalletId = RestakingPalletId;
+    type MaxRewardedParachains = ConstU32<8>;
+    type ParachainOrigin = EnsureRoot<AccountId>;
+    type MaxRebalanceJobs = ConstU32<8>;
+    type MaxParachainExits = ConstU32<1>;
+    type MaxWithdrawals = ConstU32<2>;
+    type AssetId = AssetId;
+    type Assets = Assets;
+    type PriceOracle = MockPriceOracle;
+    type MaxPriceAge = ConstU64<10>;
+    type AssetOrigin = EnsureRoot<AccountId>;
+    type MaxRestakedAssets = ConstU32<4>;
+    type XcmSender = ();
+    type XcmOrigin = EnsureNever<MultiLocation>;
+    type AccountLocation = ();
+    type ConsumerCallIndex = ConsumerCallIndex;
+    type ConsumerCallWeight = ConsumerCallWeight;
+    type MinRestake = ConstU128<100>;
+    type MaxAllocations = ConstU32<4>;
+    type OperatorBond = ConstU128<500>;
+    type MaxDelegators = ConstU32<2>;
+    type MaxUndelegations = ConstU32<4>;
+    type UnbondingPeriod = ConstU64<20>;
+    type MinStakeDuration = ConstU64<10>;
+    type MaxStakeDuration = ConstU64<110>;
+    type RewardFrequency = ConstU64<10>;
+    type LockBoost = LinearBoost<MaxBoost>;
+}
+
+/// Accounts funded with native balance and `LP_TOKEN`, three registered parachains and a funded reward pot
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![
+            (ALICE, 10_000),
+            (BOB, 10_000),
+            (CHARLIE, 10_000),
+            (DAVE, 10_000),
+            (Restaking::reward_pot(), 100_000),
+        ],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+    pallet_assets::GenesisConfig::<Test> {
+        assets: vec![(LP_TOKEN, ALICE, true, 1)],
+        metadata: vec![],
+        accounts: vec![(LP_TOKEN, ALICE, 5_000), (LP_TOKEN, BOB, 5_000)],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        for parachain_id in [PARA_A, PARA_B, PARA_C] {
+            Restaking::register_parachain(RuntimeOrigin::root(), parachain_id, 1).unwrap();
+        }
+    });
+    ext
+}
+
+/// Run `on_initialize` and `on_idle` for every block up to `n`
+pub fn run_to_block(n: BlockNumber) {
+    use frame_support::traits::Hooks;
+    while System::block_number() < n {
+        System::set_block_number(System::block_number() + 1);
+        let now = System::block_number();
+        Restaking::on_initialize(now);
+        Restaking::on_idle(now, Weight::MAX);
+    }
+}
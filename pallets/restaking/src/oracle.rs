@@ -0,0 +1,64 @@
+//! Price oracle used to value restaked assets
+//!
+//! Restaked assets only contribute to parachain security through their price
+//! in the native currency, so the pallet never needs to know where prices
+//! come from. Prices carry the block they were last updated at, and the
+//! pallet ignores prices older than its configured maximum age.
+
+use sp_runtime::FixedU128;
+
+/// Source of asset prices
+pub trait PriceOracle<AssetId, BlockNumber> {
+    /// Price of one unit of `asset` in units of the native currency, and the block it was updated at
+    ///
+    /// Returns `None` when no price is available, in which case the asset
+    /// contributes nothing to security.
+    fn price(asset: &AssetId) -> Option<(FixedU128, BlockNumber)>;
+}
+
+/// No oracle, every asset is unpriced
+impl<AssetId, BlockNumber> PriceOracle<AssetId, BlockNumber> for () {
+    fn price(_asset: &AssetId) -> Option<(FixedU128, BlockNumber)> {
+        None
+    }
+}
+
+/// Oracle with prices set directly, for tests and benchmarks
+///
+/// Prices are kept in unhashed storage, so they live as long as the
+/// externalities they were set in.
+#[cfg(any(test, feature = "runtime-benchmarks"))]
+pub struct MockPriceOracle;
+
+#[cfg(any(test, feature = "runtime-benchmarks"))]
+mod mock {
+    use super::*;
+    use codec::{Decode, Encode};
+    use frame_support::storage::unhashed;
+    use sp_std::prelude::*;
+
+    const PREFIX: &[u8] = b":restaking:mock_price:";
+
+    fn key<AssetId: Encode>(asset: &AssetId) -> Vec<u8> {
+        [PREFIX, &asset.encode()[..]].concat()
+    }
+
+    impl MockPriceOracle {
+        /// Set the price of an asset and the block it was updated at, or clear it with `None`
+        pub fn set_price<AssetId: Encode, BlockNumber: Encode>(
+            asset: &AssetId,
+            price: Option<(FixedU128, BlockNumber)>,
+        ) {
+            match price {
+                Some(price) => unhashed::put(&key(asset), &price),
+                None => unhashed::kill(&key(asset)),
+            }
+        }
+    }
+
+    impl<AssetId: Encode, BlockNumber: Decode> PriceOracle<AssetId, BlockNumber> for MockPriceOracle {
+        fn price(asset: &AssetId) -> Option<(FixedU128, BlockNumber)> {
+            unhashed::get(&key(asset))
+        }
+    }
+}
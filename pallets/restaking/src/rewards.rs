@@ -0,0 +1,69 @@
+//! Reward math for the restaking pallet
+//!
+//! Like the allocation math, these functions are free of pallet state so
+//! that off-chain tooling can reproduce what the runtime computes.
+
+use sp_runtime::{
+    traits::{AtLeast32BitUnsigned, Get, One},
+    FixedPointNumber, FixedPointOperand, FixedU128,
+};
+use sp_std::marker::PhantomData;
+
+/// Reward multiplier curve for the lock duration chosen by a restaker
+pub trait LockBoostCurve<BlockNumber> {
+    /// Multiplier for a lock of `lock` blocks, given the allowed `[min, max]` range
+    fn multiplier(lock: BlockNumber, min: BlockNumber, max: BlockNumber) -> FixedU128;
+}
+
+/// No boost, every lock earns 1x
+impl<BlockNumber> LockBoostCurve<BlockNumber> for () {
+    fn multiplier(_lock: BlockNumber, _min: BlockNumber, _max: BlockNumber) -> FixedU128 {
+        FixedU128::one()
+    }
+}
+
+/// Linear curve from 1x at the minimum lock up to `MaxBoost` at the maximum lock
+pub struct LinearBoost<MaxBoost>(PhantomData<MaxBoost>);
+
+impl<BlockNumber, MaxBoost> LockBoostCurve<BlockNumber> for LinearBoost<MaxBoost>
+where
+    BlockNumber: AtLeast32BitUnsigned + Copy,
+    MaxBoost: Get<FixedU128>,
+{
+    fn multiplier(lock: BlockNumber, min: BlockNumber, max: BlockNumber) -> FixedU128 {
+        linear_multiplier(lock, min, max, MaxBoost::get())
+    }
+}
+
+/// Multiplier rising linearly from 1x at `min` to `max_boost` at `max`
+pub fn linear_multiplier<BlockNumber: AtLeast32BitUnsigned + Copy>(
+    lock: BlockNumber,
+    min: BlockNumber,
+    max: BlockNumber,
+    max_boost: FixedU128,
+) -> FixedU128 {
+    let one = FixedU128::one();
+    if max <= min {
+        return one;
+    }
+
+    let max_boost = max_boost.max(one);
+    let elapsed: u128 = (lock.clamp(min, max) - min).unique_saturated_into();
+    let span: u128 = (max - min).unique_saturated_into();
+    one + (max_boost - one) * FixedU128::saturating_from_rational(elapsed, span)
+}
+
+/// Stake weighted by a reward multiplier
+pub fn boosted_stake<Balance: FixedPointOperand>(amount: Balance, multiplier: FixedU128) -> Balance {
+    multiplier.saturating_mul_int(amount)
+}
+
+/// Increase of a parachain's reward-per-share accumulator when `amount` is spread over `total_shares`
+pub fn reward_per_share<Balance: FixedPointOperand>(amount: Balance, total_shares: Balance) -> FixedU128 {
+    FixedU128::saturating_from_rational(amount, total_shares)
+}
+
+/// Rewards earned by `shares` at the current accumulator, net of the `debt` already settled
+pub fn accrued_rewards<Balance: FixedPointOperand>(accumulator: FixedU128, shares: Balance, debt: Balance) -> Balance {
+    accumulator.saturating_mul_int(shares).saturating_sub(debt)
+}
@@ -0,0 +1,19 @@
+//! Runtime API for querying restaking state
+
+use codec::Codec;
+use sp_runtime::FixedU128;
+
+sp_api::decl_runtime_apis! {
+    /// Restaking queries for wallets and dashboards
+    pub trait RestakingApi<AccountId, BlockNumber>
+    where
+        AccountId: Codec,
+        BlockNumber: Codec,
+    {
+        /// Blocks left until the account's restake can be unstaked
+        fn remaining_lock(account: AccountId) -> BlockNumber;
+
+        /// Reward multiplier the account's lock currently earns, 1x once the lock has ended
+        fn reward_multiplier(account: AccountId) -> FixedU128;
+    }
+}
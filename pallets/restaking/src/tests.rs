@@ -0,0 +1,726 @@
+//! Restaking tests
+
+use crate::{
+    mock::*, oracle::MockPriceOracle, AssetStakes, Delegations, Error, Event, OperatorDelegators, Operators,
+    ParachainExits, ParachainStake, RebalanceQueue, RestakeAllocation, RestakeStrategy, Restakers, RewardBudgets,
+    Shares, TotalShares, UnclaimedRewards, Undelegations, Withdrawals,
+};
+use frame_support::{assert_noop, assert_ok, bounded_vec, BoundedVec};
+use sp_runtime::{FixedU128, Perbill};
+
+fn single(parachain_id: u32) -> BoundedVec<RestakeAllocation, <Test as crate::Config>::MaxAllocations> {
+    bounded_vec![RestakeAllocation { parachain_id, percentage: 10_000 }]
+}
+
+/// Bob registers as an operator securing `PARA_A` and accepting up to `cap`
+fn register_bob(cap: Balance) {
+    assert_ok!(Restaking::register_operator(RuntimeOrigin::signed(BOB), Perbill::from_percent(10), cap));
+    assert_ok!(Restaking::set_operator_targets(RuntimeOrigin::signed(BOB), bounded_vec![PARA_A]));
+}
+
+/// Restake `amount` on `PARA_A` for the shortest lock, 10 blocks
+fn restake(account: AccountId, amount: Balance) {
+    restake_locked(account, amount, 10);
+}
+
+fn allocation(parachain_id: u32, percentage: u16) -> RestakeAllocation {
+    RestakeAllocation { parachain_id, percentage }
+}
+
+fn price(units: u32, updated_at: BlockNumber) -> Option<(FixedU128, BlockNumber)> {
+    Some((FixedU128::from_u32(units), updated_at))
+}
+
+/// Alice restakes 1_000 natively and 500 `LP_TOKEN` on `PARA_A`, whitelisted with a 10% haircut
+fn restake_with_lp_tokens() {
+    assert_ok!(Restaking::whitelist_asset(RuntimeOrigin::root(), LP_TOKEN, Perbill::from_percent(10)));
+    restake(ALICE, 1_000);
+    assert_ok!(Restaking::restake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 500));
+}
+
+fn restake_locked(account: AccountId, amount: Balance, lock_duration: BlockNumber) {
+    assert_ok!(Restaking::restake(
+        RuntimeOrigin::signed(account),
+        amount,
+        RestakeStrategy::SingleParachain,
+        single(PARA_A),
+        lock_duration,
+    ));
+}
+
+#[test]
+fn delegations_are_capped_by_the_operator() {
+    new_test_ext().execute_with(|| {
+        register_bob(1_500);
+        for account in [ALICE, CHARLIE, DAVE] {
+            restake(account, 1_000);
+        }
+
+        assert_noop!(
+            Restaking::delegate(RuntimeOrigin::signed(ALICE), BOB, 1_001),
+            Error::<Test>::InsufficientStake
+        );
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(ALICE), BOB, 1_000));
+        assert_noop!(
+            Restaking::delegate(RuntimeOrigin::signed(CHARLIE), BOB, 501),
+            Error::<Test>::DelegationCapExceeded
+        );
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(CHARLIE), BOB, 500));
+
+        // `MaxDelegators` is 2
+        assert_ok!(Restaking::update_operator(RuntimeOrigin::signed(BOB), Perbill::from_percent(10), 10_000));
+        assert_noop!(
+            Restaking::delegate(RuntimeOrigin::signed(DAVE), BOB, 100),
+            Error::<Test>::TooManyDelegators
+        );
+
+        // Topping up an existing delegation takes no new slot
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(CHARLIE), BOB, 100));
+        let info = Operators::<Test>::get(BOB).unwrap();
+        assert_eq!(info.total_delegated, 1_600);
+        assert_eq!(info.delegator_count, 2);
+
+        assert_ok!(Restaking::register_operator(RuntimeOrigin::signed(DAVE), Perbill::zero(), 10_000));
+        assert_noop!(
+            Restaking::delegate(RuntimeOrigin::signed(CHARLIE), DAVE, 100),
+            Error::<Test>::AlreadyDelegated
+        );
+    });
+}
+
+#[test]
+fn undelegated_stake_stays_exposed_until_the_unbonding_period_ends() {
+    new_test_ext().execute_with(|| {
+        register_bob(10_000);
+        restake(ALICE, 1_000);
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(ALICE), BOB, 1_000));
+
+        assert_ok!(Restaking::undelegate(RuntimeOrigin::signed(ALICE), 400));
+        System::assert_last_event(
+            Event::Undelegated { delegator: ALICE, operator: BOB, amount: 400, unlock_at: 21 }.into(),
+        );
+        assert_eq!(Delegations::<Test>::get(ALICE).unwrap().amount, 600);
+        assert_eq!(Operators::<Test>::get(BOB).unwrap().total_delegated, 600);
+
+        // `UnbondingPeriod` is 20 blocks
+        run_to_block(20);
+        assert_noop!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 1), Error::<Test>::InsufficientStake);
+        assert_noop!(
+            Restaking::withdraw_undelegated(RuntimeOrigin::signed(ALICE)),
+            Error::<Test>::NothingToWithdraw
+        );
+        run_to_block(21);
+        assert_ok!(Restaking::withdraw_undelegated(RuntimeOrigin::signed(ALICE)));
+        assert_ok!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 400));
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 600);
+
+        // Bob keeps Alice as a delegator until nothing is exposed to him
+        assert_ok!(Restaking::undelegate(RuntimeOrigin::signed(ALICE), 600));
+        assert!(Delegations::<Test>::get(ALICE).is_none());
+        assert!(OperatorDelegators::<Test>::contains_key(BOB, ALICE));
+        assert_noop!(
+            Restaking::deregister_operator(RuntimeOrigin::signed(BOB)),
+            Error::<Test>::OperatorHasDelegators
+        );
+
+        run_to_block(41);
+        assert_ok!(Restaking::withdraw_undelegated(RuntimeOrigin::signed(ALICE)));
+        assert!(!OperatorDelegators::<Test>::contains_key(BOB, ALICE));
+        assert_eq!(Operators::<Test>::get(BOB).unwrap().delegator_count, 0);
+        assert_ok!(Restaking::deregister_operator(RuntimeOrigin::signed(BOB)));
+        assert_eq!(Balances::reserved_balance(BOB), 0);
+    });
+}
+
+#[test]
+fn operator_slashes_pass_through_to_delegated_and_unbonding_stake() {
+    new_test_ext().execute_with(|| {
+        register_bob(10_000);
+        restake(ALICE, 1_000);
+        restake(CHARLIE, 1_000);
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(ALICE), BOB, 1_000));
+        assert_ok!(Restaking::undelegate(RuntimeOrigin::signed(ALICE), 200));
+
+        assert_noop!(
+            Restaking::slash_operator(RuntimeOrigin::signed(CHARLIE), BOB, Perbill::from_percent(10)),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Restaking::slash_operator(RuntimeOrigin::root(), BOB, Perbill::from_percent(10)));
+
+        let info = Operators::<Test>::get(BOB).unwrap();
+        assert_eq!(info.bond, 450);
+        assert_eq!(info.total_delegated, 720);
+        assert_eq!(Delegations::<Test>::get(ALICE).unwrap().amount, 720);
+        assert_eq!(Undelegations::<Test>::get(ALICE)[0].amount, 180);
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 900);
+        assert_eq!(Balances::reserved_balance(ALICE), 900);
+        assert_eq!(Balances::reserved_balance(BOB), 450);
+
+        // Charlie never delegated to Bob
+        assert_eq!(Restakers::<Test>::get(CHARLIE).unwrap().total_staked, 1_000);
+        assert_eq!(ParachainStake::<Test>::get(PARA_A), 1_900);
+
+        System::assert_has_event(Event::DelegatorSlashed { delegator: ALICE, operator: BOB, amount: 100 }.into());
+        System::assert_last_event(
+            Event::OperatorSlashed { operator: BOB, fraction: Perbill::from_percent(10), amount: 150 }.into(),
+        );
+    });
+}
+
+#[test]
+fn slashing_a_restaker_reduces_its_delegation() {
+    new_test_ext().execute_with(|| {
+        register_bob(10_000);
+        restake(ALICE, 1_000);
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(ALICE), BOB, 600));
+        assert_ok!(Restaking::undelegate(RuntimeOrigin::signed(ALICE), 300));
+
+        assert_eq!(Restaking::slash_restaker(&ALICE, 100, None), 100);
+        assert_eq!(Delegations::<Test>::get(ALICE).unwrap().amount, 200);
+        assert_eq!(Operators::<Test>::get(BOB).unwrap().total_delegated, 200);
+        assert_eq!(Undelegations::<Test>::get(ALICE)[0].amount, 300);
+
+        // Whatever is still exposed beyond the remaining stake comes off the undelegation
+        assert_eq!(Restaking::slash_restaker(&ALICE, 700, None), 700);
+        assert!(Delegations::<Test>::get(ALICE).is_none());
+        assert_eq!(Operators::<Test>::get(BOB).unwrap().total_delegated, 0);
+        assert_eq!(Undelegations::<Test>::get(ALICE)[0].amount, 200);
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 200);
+        assert_eq!(Balances::reserved_balance(ALICE), 200);
+        assert!(OperatorDelegators::<Test>::contains_key(BOB, ALICE));
+    });
+}
+
+#[test]
+fn lock_durations_must_be_within_the_configured_range() {
+    new_test_ext().execute_with(|| {
+        // `MinStakeDuration` is 10 and `MaxStakeDuration` 110 blocks
+        for lock_duration in [9, 111] {
+            assert_noop!(
+                Restaking::restake(
+                    RuntimeOrigin::signed(ALICE),
+                    1_000,
+                    RestakeStrategy::SingleParachain,
+                    single(PARA_A),
+                    lock_duration,
+                ),
+                Error::<Test>::InvalidLockDuration
+            );
+        }
+        restake_locked(ALICE, 1_000, 110);
+        System::assert_last_event(
+            Event::Restaked {
+                account: ALICE,
+                amount: 1_000,
+                strategy: RestakeStrategy::SingleParachain,
+                locked_until: 111,
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn locked_stake_cannot_be_unstaked_until_the_lock_ends() {
+    new_test_ext().execute_with(|| {
+        restake_locked(ALICE, 1_000, 50);
+        assert_eq!(Restaking::remaining_lock(&ALICE), 50);
+        assert_noop!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 100), Error::<Test>::StakeLocked);
+
+        // A shorter top-up keeps the existing lock
+        run_to_block(11);
+        restake(ALICE, 100);
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().locked_until, 51);
+        assert_eq!(Restaking::remaining_lock(&ALICE), 40);
+
+        run_to_block(51);
+        assert_eq!(Restaking::remaining_lock(&ALICE), 0);
+        assert_ok!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 1_100));
+        assert_eq!(Restaking::remaining_lock(&ALICE), 0);
+    });
+}
+
+#[test]
+fn longer_locks_earn_a_boost_until_they_end() {
+    new_test_ext().execute_with(|| {
+        restake_locked(ALICE, 1_000, 110);
+        restake_locked(BOB, 1_000, 60);
+        restake(CHARLIE, 1_000);
+
+        // `LinearBoost` goes from 1x at 10 blocks to 2x at 110 blocks
+        assert_eq!(Restaking::reward_multiplier(&ALICE), FixedU128::from_u32(2));
+        assert_eq!(Restaking::reward_multiplier(&BOB), FixedU128::from_rational(3, 2));
+        assert_eq!(Restaking::reward_multiplier(&CHARLIE), FixedU128::from_u32(1));
+        assert_eq!(Restaking::reward_multiplier(&DAVE), FixedU128::from_u32(0));
+        assert_eq!(Shares::<Test>::get(PARA_A, ALICE), 2_000);
+        assert_eq!(Shares::<Test>::get(PARA_A, BOB), 1_500);
+
+        run_to_block(61);
+        assert_eq!(Restaking::reward_multiplier(&ALICE), FixedU128::from_u32(2));
+        assert_eq!(Restaking::reward_multiplier(&BOB), FixedU128::from_u32(1));
+        assert_eq!(Shares::<Test>::get(PARA_A, BOB), 1_000);
+        run_to_block(111);
+        assert_eq!(Restaking::reward_multiplier(&ALICE), FixedU128::from_u32(1));
+        assert_eq!(Shares::<Test>::get(PARA_A, ALICE), 1_000);
+        assert_eq!(TotalShares::<Test>::get(PARA_A), 3_000);
+    });
+}
+
+#[test]
+fn rewards_are_split_by_lock_boosted_shares() {
+    new_test_ext().execute_with(|| {
+        restake_locked(ALICE, 1_000, 110);
+        restake(BOB, 1_000);
+        assert_noop!(
+            Restaking::set_reward_budget(RuntimeOrigin::signed(ALICE), PARA_A, 300),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 300));
+
+        // `RewardFrequency` is 10 blocks
+        run_to_block(9);
+        assert_eq!(Restaking::calculate_rewards(&ALICE), 0);
+        run_to_block(10);
+        System::assert_has_event(Event::RewardsDistributed { parachain_id: PARA_A, amount: 300 }.into());
+        assert_eq!(Restaking::calculate_rewards(&ALICE), 200);
+        assert_eq!(Restaking::calculate_rewards(&BOB), 100);
+
+        // Bob's lock ends at block 11, after which both earn on their plain stake
+        run_to_block(20);
+        assert_eq!(Restaking::calculate_rewards(&ALICE), 400);
+        assert_eq!(Restaking::calculate_rewards(&BOB), 200);
+        assert_eq!(UnclaimedRewards::<Test>::get(), 600);
+    });
+}
+
+#[test]
+fn distribution_is_capped_by_the_reward_pot() {
+    new_test_ext().execute_with(|| {
+        restake(ALICE, 1_000);
+        restake(BOB, 1_000);
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 60_000));
+
+        // The pot holds 100_000 and keeps the existential deposit
+        run_to_block(20);
+        assert_eq!(UnclaimedRewards::<Test>::get(), 99_999);
+        assert_eq!(Restaking::calculate_rewards(&ALICE), 49_999);
+
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 0));
+        assert!(RewardBudgets::<Test>::get().is_empty());
+    });
+}
+
+#[test]
+fn claims_pay_the_operator_fee_on_the_delegated_share() {
+    new_test_ext().execute_with(|| {
+        register_bob(10_000);
+        restake(ALICE, 1_000);
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(ALICE), BOB, 500));
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 1_000));
+        run_to_block(10);
+
+        // Bob takes 10% of the half of the rewards earned by delegated stake
+        assert_ok!(Restaking::claim_rewards(RuntimeOrigin::signed(ALICE), false));
+        System::assert_last_event(
+            Event::RewardsClaimed { account: ALICE, amount: 950, operator_fee: 50, compounded: false }.into(),
+        );
+        assert_eq!(Balances::free_balance(ALICE), 10_000 - 1_000 + 950);
+        assert_eq!(Balances::free_balance(BOB), 10_000 - 500 + 50);
+        assert_eq!(UnclaimedRewards::<Test>::get(), 0);
+        assert_noop!(Restaking::claim_rewards(RuntimeOrigin::signed(ALICE), false), Error::<Test>::NoRewards);
+    });
+}
+
+#[test]
+fn compounded_rewards_are_restaked_under_the_current_allocations() {
+    new_test_ext().execute_with(|| {
+        restake(ALICE, 1_000);
+        restake(BOB, 1_000);
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 200));
+        run_to_block(10);
+
+        assert_ok!(Restaking::claim_rewards(RuntimeOrigin::signed(ALICE), true));
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 1_100);
+        assert_eq!(Balances::reserved_balance(ALICE), 1_100);
+        assert_eq!(ParachainStake::<Test>::get(PARA_A), 2_100);
+        assert_eq!(Shares::<Test>::get(PARA_A, ALICE), 1_100);
+
+        // Compounded stake earns from the next period on
+        run_to_block(20);
+        assert_eq!(Restaking::calculate_rewards(&ALICE), 104);
+        assert_eq!(Restaking::calculate_rewards(&BOB), 195);
+        assert_noop!(Restaking::claim_rewards(RuntimeOrigin::signed(DAVE), true), Error::<Test>::NotRestaker);
+    });
+}
+
+#[test]
+fn proportional_restakers_follow_the_parachain_registry() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            1_000,
+            RestakeStrategy::Proportional,
+            bounded_vec![],
+            10,
+        ));
+        let spread = vec![allocation(PARA_A, 3_334), allocation(PARA_B, 3_333), allocation(PARA_C, 3_333)];
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().allocations, spread);
+
+        assert_noop!(
+            Restaking::register_parachain(RuntimeOrigin::signed(ALICE), 2003, 3),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_noop!(
+            Restaking::register_parachain(RuntimeOrigin::root(), PARA_A, 3),
+            Error::<Test>::ParachainAlreadyRegistered
+        );
+        assert_ok!(Restaking::register_parachain(RuntimeOrigin::root(), 2003, 3));
+
+        // Restakers are moved in `on_idle`, not on registration
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().allocations, spread);
+        run_to_block(2);
+        let allocations = vec![
+            allocation(PARA_A, 1_668),
+            allocation(PARA_B, 1_666),
+            allocation(PARA_C, 1_666),
+            allocation(2003, 5_000),
+        ];
+        System::assert_has_event(Event::Rebalanced { account: ALICE, allocations: allocations.clone() }.into());
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().allocations, allocations);
+        assert_eq!(ParachainStake::<Test>::get(2003), 500);
+        assert_eq!(Shares::<Test>::get(2003, ALICE), 500);
+        assert!(RebalanceQueue::<Test>::get().is_empty());
+    });
+}
+
+#[test]
+fn deregistered_parachains_stay_slashable_for_the_unbonding_period() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            1_000,
+            RestakeStrategy::Custom,
+            bounded_vec![allocation(PARA_A, 5_000), allocation(PARA_B, 5_000)],
+            10,
+        ));
+        assert_noop!(
+            Restaking::deregister_parachain(RuntimeOrigin::root(), 2003),
+            Error::<Test>::UnknownParachain
+        );
+        assert_ok!(Restaking::deregister_parachain(RuntimeOrigin::root(), PARA_A));
+        assert_noop!(
+            Restaking::restake(RuntimeOrigin::signed(BOB), 1_000, RestakeStrategy::SingleParachain, single(PARA_A), 10),
+            Error::<Test>::UnknownParachain
+        );
+
+        // Custom restakers keep the relative weights of their remaining parachains
+        run_to_block(2);
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().allocations, vec![allocation(PARA_B, 10_000)]);
+        assert_eq!(ParachainStake::<Test>::get(PARA_A), 0);
+        assert_eq!(ParachainStake::<Test>::get(PARA_B), 1_000);
+        assert_eq!(Shares::<Test>::get(PARA_A, ALICE), 0);
+        assert_eq!(TotalShares::<Test>::get(PARA_A), 0);
+
+        let exits = ParachainExits::<Test>::get(ALICE);
+        assert_eq!(exits.len(), 1);
+        assert_eq!((exits[0].parachain_id, exits[0].amount, exits[0].until), (PARA_A, 500, 22));
+    });
+}
+
+#[test]
+fn failed_rebalances_are_recorded_without_stalling_the_queue() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            900,
+            RestakeStrategy::Custom,
+            bounded_vec![allocation(PARA_A, 3_334), allocation(PARA_B, 3_333), allocation(PARA_C, 3_333)],
+            10,
+        ));
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(CHARLIE),
+            1_000,
+            RestakeStrategy::Custom,
+            bounded_vec![allocation(PARA_B, 5_000), allocation(PARA_C, 5_000)],
+            10,
+        ));
+        assert_ok!(Restaking::deregister_parachain(RuntimeOrigin::root(), PARA_A));
+        run_to_block(2);
+        assert_eq!(ParachainExits::<Test>::get(ALICE).len(), 1);
+
+        // Alice has no room for a second exit while the first is still slashable
+        assert_ok!(Restaking::deregister_parachain(RuntimeOrigin::root(), PARA_B));
+        run_to_block(3);
+        System::assert_has_event(
+            Event::RebalanceFailed {
+                account: ALICE,
+                parachain_id: PARA_B,
+                error: Error::<Test>::TooManyParachainExits.into(),
+            }
+            .into(),
+        );
+        assert_eq!(Shares::<Test>::get(PARA_B, ALICE), 0);
+        assert_eq!(
+            Restakers::<Test>::get(ALICE).unwrap().allocations,
+            vec![allocation(PARA_B, 5_000), allocation(PARA_C, 5_000)]
+        );
+
+        // Charlie is rebalanced whichever order the accounts were visited in
+        assert_eq!(Restakers::<Test>::get(CHARLIE).unwrap().allocations, vec![allocation(PARA_C, 10_000)]);
+        assert_eq!(ParachainExits::<Test>::get(CHARLIE)[0].amount, 500);
+        assert!(RebalanceQueue::<Test>::get().is_empty());
+    });
+}
+
+#[test]
+fn only_whitelisted_assets_can_be_restaked() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Restaking::restake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 500),
+            Error::<Test>::AssetNotWhitelisted
+        );
+        assert_noop!(
+            Restaking::whitelist_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, Perbill::from_percent(10)),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Restaking::whitelist_asset(RuntimeOrigin::root(), LP_TOKEN, Perbill::from_percent(10)));
+        assert_noop!(
+            Restaking::restake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 500),
+            Error::<Test>::NotRestaker
+        );
+
+        restake(ALICE, 1_000);
+        assert_ok!(Restaking::restake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 500));
+        System::assert_last_event(Event::AssetRestaked { account: ALICE, asset: LP_TOKEN, amount: 500 }.into());
+        assert_eq!(Assets::balance(LP_TOKEN, ALICE), 4_500);
+        assert_eq!(Assets::balance(LP_TOKEN, Restaking::asset_vault()), 500);
+    });
+}
+
+#[test]
+fn restaked_assets_are_unstaked_after_the_lock_and_before_the_native_stake() {
+    new_test_ext().execute_with(|| {
+        restake_with_lp_tokens();
+        assert_noop!(
+            Restaking::unstake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 500),
+            Error::<Test>::StakeLocked
+        );
+
+        run_to_block(11);
+        assert_noop!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 1_000), Error::<Test>::AssetsStillRestaked);
+        assert_noop!(
+            Restaking::unstake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 501),
+            Error::<Test>::InsufficientStake
+        );
+        assert_ok!(Restaking::unstake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 500));
+        assert!(!AssetStakes::<Test>::contains_key(ALICE, LP_TOKEN));
+        assert_eq!(Assets::balance(LP_TOKEN, ALICE), 5_000);
+        assert_ok!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 1_000));
+    });
+}
+
+#[test]
+fn restaked_assets_add_security_at_price_minus_haircut() {
+    new_test_ext().execute_with(|| {
+        restake_with_lp_tokens();
+        MockPriceOracle::set_price(&LP_TOKEN, price(2, 1));
+
+        assert_eq!(Restaking::asset_value(&LP_TOKEN, 500), 900);
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_900);
+        assert_eq!(Restaking::parachain_security(PARA_B), 0);
+
+        run_to_block(5);
+        MockPriceOracle::set_price(&LP_TOKEN, price(3, 5));
+        assert_eq!(Restaking::parachain_security(PARA_A), 2_350);
+    });
+}
+
+#[test]
+fn unpriced_stale_and_delisted_assets_add_no_security() {
+    new_test_ext().execute_with(|| {
+        restake_with_lp_tokens();
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_000);
+
+        MockPriceOracle::set_price(&LP_TOKEN, price(2, 1));
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_900);
+
+        // `MaxPriceAge` is 10 blocks
+        run_to_block(11);
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_900);
+        run_to_block(12);
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_000);
+
+        MockPriceOracle::set_price(&LP_TOKEN, price(2, 12));
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_900);
+        MockPriceOracle::set_price::<_, BlockNumber>(&LP_TOKEN, None);
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_000);
+
+        MockPriceOracle::set_price(&LP_TOKEN, price(2, 12));
+        assert_ok!(Restaking::delist_asset(RuntimeOrigin::root(), LP_TOKEN));
+        System::assert_last_event(Event::AssetDelisted { asset: LP_TOKEN }.into());
+        assert_eq!(Restaking::parachain_security(PARA_A), 1_000);
+    });
+}
+
+#[test]
+fn slashes_burn_restaked_assets_in_kind() {
+    new_test_ext().execute_with(|| {
+        restake_with_lp_tokens();
+        MockPriceOracle::set_price(&LP_TOKEN, price(2, 1));
+
+        assert_eq!(Restaking::slash_restaker(&ALICE, 100, None), 100);
+        System::assert_has_event(Event::AssetSlashed { account: ALICE, asset: LP_TOKEN, amount: 50 }.into());
+        assert_eq!(AssetStakes::<Test>::get(ALICE, LP_TOKEN), 450);
+        assert_eq!(Assets::balance(LP_TOKEN, Restaking::asset_vault()), 450);
+        assert_eq!(Restaking::parachain_security(PARA_A), 900 + 810);
+    });
+}
+
+#[test]
+fn slash_reports_only_reach_delegated_stake_allocated_to_the_reporter() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Restaking::register_operator(RuntimeOrigin::signed(BOB), Perbill::zero(), 10_000));
+        assert_ok!(Restaking::set_operator_targets(RuntimeOrigin::signed(BOB), bounded_vec![PARA_A, PARA_B]));
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            1_000,
+            RestakeStrategy::Custom,
+            bounded_vec![allocation(PARA_A, 5_000), allocation(PARA_B, 5_000)],
+            10,
+        ));
+        assert_ok!(Restaking::delegate(RuntimeOrigin::signed(ALICE), BOB, 1_000));
+
+        Restaking::process_slash_report(PARA_A, &BOB, Perbill::from_percent(10));
+
+        // The whole bond is exposed, but only half of Alice's delegation secures `PARA_A`
+        System::assert_last_event(
+            Event::SlashReportProcessed { parachain_id: PARA_A, offender: BOB, amount: 100 }.into(),
+        );
+        assert_eq!(Operators::<Test>::get(BOB).unwrap().bond, 450);
+        assert_eq!(Delegations::<Test>::get(ALICE).unwrap().amount, 950);
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 950);
+
+        // Operators are only slashed by parachains they secure
+        Restaking::process_slash_report(PARA_C, &BOB, Perbill::from_percent(10));
+        assert_eq!(Operators::<Test>::get(BOB).unwrap().bond, 450);
+    });
+}
+
+#[test]
+fn slash_reports_only_burn_assets_allocated_to_the_reporter() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Restaking::whitelist_asset(RuntimeOrigin::root(), LP_TOKEN, Perbill::zero()));
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            1_000,
+            RestakeStrategy::Custom,
+            bounded_vec![allocation(PARA_A, 5_000), allocation(PARA_B, 5_000)],
+            10,
+        ));
+        assert_ok!(Restaking::restake_asset(RuntimeOrigin::signed(ALICE), LP_TOKEN, 500));
+
+        Restaking::process_slash_report(PARA_A, &ALICE, Perbill::from_percent(10));
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 950);
+        System::assert_has_event(Event::AssetSlashed { account: ALICE, asset: LP_TOKEN, amount: 25 }.into());
+        assert_eq!(AssetStakes::<Test>::get(ALICE, LP_TOKEN), 475);
+
+        // Parachains the restaker does not secure cannot slash it
+        Restaking::process_slash_report(PARA_C, &ALICE, Perbill::from_percent(10));
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 950);
+        assert_eq!(AssetStakes::<Test>::get(ALICE, LP_TOKEN), 475);
+    });
+}
+
+#[test]
+fn stake_that_left_a_parachain_stays_slashable_by_it() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            1_000,
+            RestakeStrategy::Custom,
+            bounded_vec![allocation(PARA_A, 5_000), allocation(PARA_B, 5_000)],
+            10,
+        ));
+        assert_ok!(Restaking::deregister_parachain(RuntimeOrigin::root(), PARA_A));
+        run_to_block(2);
+
+        Restaking::process_slash_report(PARA_A, &ALICE, Perbill::from_percent(10));
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 950);
+        assert_eq!(ParachainExits::<Test>::get(ALICE)[0].amount, 450);
+
+        // Once the unbonding period has passed the exit is gone
+        run_to_block(22);
+        Restaking::process_slash_report(PARA_A, &ALICE, Perbill::from_percent(10));
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 950);
+        assert!(ParachainExits::<Test>::get(ALICE).is_empty());
+    });
+}
+
+#[test]
+fn unstaked_stake_is_withdrawn_in_batches_once_it_matures() {
+    new_test_ext().execute_with(|| {
+        restake(ALICE, 1_000);
+        run_to_block(11);
+
+        assert_ok!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 300));
+        System::assert_last_event(Event::Unstaked { account: ALICE, amount: 300, matures_at: 31 }.into());
+        run_to_block(12);
+        assert_ok!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 200));
+        assert_noop!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 100), Error::<Test>::TooManyWithdrawals);
+
+        // Unstaked stake stays reserved until it is withdrawn
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 500);
+        assert_eq!(ParachainStake::<Test>::get(PARA_A), 500);
+        assert_eq!(Balances::reserved_balance(ALICE), 1_000);
+        assert_noop!(
+            Restaking::withdraw_unbonded(RuntimeOrigin::signed(ALICE), 2),
+            Error::<Test>::NothingToWithdraw
+        );
+
+        run_to_block(32);
+        assert_ok!(Restaking::withdraw_unbonded(RuntimeOrigin::signed(ALICE), 1));
+        System::assert_last_event(Event::Withdrawn { account: ALICE, amount: 300, entries: 1 }.into());
+        assert_eq!(Balances::reserved_balance(ALICE), 700);
+        assert_eq!(Withdrawals::<Test>::get(ALICE).len(), 1);
+
+        assert_ok!(Restaking::withdraw_unbonded(RuntimeOrigin::signed(ALICE), 2));
+        assert_eq!(Balances::free_balance(ALICE), 9_500);
+        assert!(!Withdrawals::<Test>::contains_key(ALICE));
+    });
+}
+
+#[test]
+fn queued_withdrawals_stay_slashable_until_they_mature() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            1_000,
+            RestakeStrategy::Custom,
+            bounded_vec![allocation(PARA_A, 5_000), allocation(PARA_B, 5_000)],
+            10,
+        ));
+        run_to_block(11);
+        assert_ok!(Restaking::unstake(RuntimeOrigin::signed(ALICE), 400));
+
+        // 200 of the withdrawal and 300 of the remaining stake secure `PARA_A`
+        Restaking::process_slash_report(PARA_A, &ALICE, Perbill::from_percent(10));
+        System::assert_has_event(Event::WithdrawalSlashed { account: ALICE, parachain_id: PARA_A, amount: 20 }.into());
+        System::assert_last_event(
+            Event::SlashReportProcessed { parachain_id: PARA_A, offender: ALICE, amount: 50 }.into(),
+        );
+        assert_eq!(Withdrawals::<Test>::get(ALICE)[0].amount, 380);
+        assert_eq!(Restakers::<Test>::get(ALICE).unwrap().total_staked, 570);
+        assert_eq!(Balances::reserved_balance(ALICE), 950);
+
+        // Parachains the withdrawal was not leaving cannot slash it
+        Restaking::process_slash_report(PARA_C, &ALICE, Perbill::from_percent(10));
+        assert_eq!(Withdrawals::<Test>::get(ALICE)[0].amount, 380);
+
+        // A matured withdrawal is out of reach
+        run_to_block(31);
+        Restaking::process_slash_report(PARA_A, &ALICE, Perbill::from_percent(10));
+        assert_eq!(Withdrawals::<Test>::get(ALICE)[0].amount, 380);
+        assert_ok!(Restaking::withdraw_unbonded(RuntimeOrigin::signed(ALICE), 1));
+        System::assert_last_event(Event::Withdrawn { account: ALICE, amount: 380, entries: 1 }.into());
+    });
+}
@@ -0,0 +1,169 @@
+//! XCM simulator network for restaking messages
+//!
+//! The security chain runs the restaking pallet, two consumer parachains are
+//! registered with it and a third parachain is not.
+
+pub mod parachain;
+pub mod relay_chain;
+
+use crate::messages::RestakingMessage;
+use codec::Encode as _;
+use frame_support::{assert_ok, traits::GenesisBuild, weights::Weight};
+use xcm::latest::prelude::*;
+use xcm_simulator::{decl_test_network, decl_test_parachain, decl_test_relay_chain, TestExt};
+
+pub use parachain::{AccountId, Balance};
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+
+pub const SECURITY_CHAIN: u32 = 1000;
+pub const PARA_A: u32 = 2000;
+pub const PARA_B: u32 = 2001;
+pub const UNREGISTERED: u32 = 2002;
+
+decl_test_parachain! {
+    pub struct SecurityChain {
+        Runtime = parachain::Runtime,
+        XcmpMessageHandler = parachain::MsgQueue,
+        DmpMessageHandler = parachain::MsgQueue,
+        new_ext = para_ext(SECURITY_CHAIN),
+    }
+}
+
+decl_test_parachain! {
+    pub struct ConsumerA {
+        Runtime = parachain::Runtime,
+        XcmpMessageHandler = parachain::MsgQueue,
+        DmpMessageHandler = parachain::MsgQueue,
+        new_ext = para_ext(PARA_A),
+    }
+}
+
+decl_test_parachain! {
+    pub struct ConsumerB {
+        Runtime = parachain::Runtime,
+        XcmpMessageHandler = parachain::MsgQueue,
+        DmpMessageHandler = parachain::MsgQueue,
+        new_ext = para_ext(PARA_B),
+    }
+}
+
+decl_test_parachain! {
+    pub struct Unregistered {
+        Runtime = parachain::Runtime,
+        XcmpMessageHandler = parachain::MsgQueue,
+        DmpMessageHandler = parachain::MsgQueue,
+        new_ext = para_ext(UNREGISTERED),
+    }
+}
+
+decl_test_relay_chain! {
+    pub struct Relay {
+        Runtime = relay_chain::Runtime,
+        RuntimeCall = relay_chain::RuntimeCall,
+        RuntimeEvent = relay_chain::RuntimeEvent,
+        XcmConfig = relay_chain::XcmConfig,
+        MessageQueue = relay_chain::MessageQueue,
+        System = relay_chain::System,
+        new_ext = relay_ext(),
+    }
+}
+
+decl_test_network! {
+    pub struct MockNet {
+        relay_chain = Relay,
+        parachains = vec![
+            (1000, SecurityChain),
+            (2000, ConsumerA),
+            (2001, ConsumerB),
+            (2002, Unregistered),
+        ],
+    }
+}
+
+/// Funded restakers, with `PARA_A` and `PARA_B` registered on the security chain
+pub fn para_ext(para_id: u32) -> sp_io::TestExternalities {
+    use parachain::{MsgQueue, Restaking, Runtime, RuntimeOrigin, System};
+
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+    pallet_balances::GenesisConfig::<Runtime> {
+        balances: vec![(ALICE, 10_000), (Restaking::reward_pot(), 100_000)],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        MsgQueue::set_para_id(para_id.into());
+        if para_id == SECURITY_CHAIN {
+            Restaking::register_parachain(RuntimeOrigin::root(), PARA_A, 1).unwrap();
+            Restaking::register_parachain(RuntimeOrigin::root(), PARA_B, 1).unwrap();
+        }
+    });
+    ext
+}
+
+pub fn relay_ext() -> sp_io::TestExternalities {
+    let storage = frame_system::GenesisConfig::default().build_storage::<relay_chain::Runtime>().unwrap();
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| relay_chain::System::set_block_number(1));
+    ext
+}
+
+/// Send a restaking message from the current parachain to the security chain
+///
+/// Consumer parachains dispatch `handle_message` with their XCM origin.
+pub fn send_to_security_chain(message: RestakingMessage<AccountId, Balance>) {
+    send_with_origin(vec![], message);
+}
+
+/// Send a restaking message on behalf of an account on the current parachain
+///
+/// The account's location, rather than the parachain's, dispatches
+/// `handle_message`.
+pub fn send_as_account(account: AccountId, message: RestakingMessage<AccountId, Balance>) {
+    send_with_origin(vec![DescendOrigin(X1(AccountIndex64 { network: None, index: account }))], message);
+}
+
+fn send_with_origin(mut instructions: Vec<Instruction<()>>, message: RestakingMessage<AccountId, Balance>) {
+    let call = parachain::RuntimeCall::Restaking(crate::Call::handle_message { message: message.into() });
+    instructions.extend([
+        UnpaidExecution { weight_limit: Unlimited, check_origin: None },
+        Transact {
+            origin_kind: OriginKind::Xcm,
+            require_weight_at_most: Weight::from_parts(1_000_000_000, 64 * 1024),
+            call: call.encode().into(),
+        },
+    ]);
+    assert_ok!(send_xcm::<parachain::XcmRouter>((Parent, Parachain(SECURITY_CHAIN)).into(), Xcm(instructions)));
+}
+
+/// Messages a consumer parachain received from the security chain
+pub fn received() -> Vec<RestakingMessage<AccountId, Balance>> {
+    let security_chain = MultiLocation::new(1, X1(Parachain(SECURITY_CHAIN)));
+    parachain::mock_consumer::Received::<parachain::Runtime>::get()
+        .into_iter()
+        .map(|(origin, message)| {
+            assert_eq!(origin, security_chain);
+            let crate::messages::VersionedRestakingMessage::V1(message) = message;
+            message
+        })
+        .collect()
+}
+
+/// Run `on_initialize` and `on_idle` on the security chain for every block up to `n`
+pub fn run_to_block(n: u64) {
+    use frame_support::traits::Hooks;
+    use parachain::{Restaking, System};
+
+    SecurityChain::execute_with(|| {
+        while System::block_number() < n {
+            System::set_block_number(System::block_number() + 1);
+            let now = System::block_number();
+            Restaking::on_initialize(now);
+            Restaking::on_idle(now, Weight::MAX);
+        }
+    });
+}
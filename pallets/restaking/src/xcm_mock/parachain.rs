@@ -0,0 +1,374 @@
+//! Parachain runtime mock
+//!
+//! Every parachain in the network runs the same runtime. The security chain
+//! uses `Restaking`, consumer parachains record the messages they receive
+//! in `Consumer`, at the call index the security chain sends to.
+
+use crate as pallet_restaking;
+use crate::rewards::LinearBoost;
+use codec::{Decode, Encode};
+use frame_support::{
+    construct_runtime, parameter_types,
+    traits::{AsEnsureOriginWithArg, ConstU128, ConstU32, ConstU64, Everything, Nothing},
+    weights::Weight,
+    PalletId,
+};
+use frame_system::{EnsureRoot, EnsureSigned};
+use pallet_xcm::XcmPassthrough;
+use polkadot_core_primitives::BlockNumber as RelayBlockNumber;
+use polkadot_parachain::primitives::{DmpMessageHandler, Id as ParaId, Sibling, XcmpMessageFormat, XcmpMessageHandler};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Convert, IdentityLookup},
+    FixedU128,
+};
+use xcm::{latest::prelude::*, VersionedXcm};
+use xcm_builder::{AllowUnpaidExecutionFrom, EnsureXcmOrigin, FixedWeightBounds, SiblingParachainConvertsVia};
+use xcm_executor::{traits::ConvertOrigin, XcmExecutor};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type AssetId = u32;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
+type Block = frame_system::mocking::MockBlock<Runtime>;
+
+construct_runtime!(
+    pub enum Runtime where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system = 0,
+        Balances: pallet_balances = 1,
+        Assets: pallet_assets = 2,
+        MsgQueue: mock_msg_queue = 3,
+        PolkadotXcm: pallet_xcm = 4,
+        Restaking: pallet_restaking = 5,
+        Consumer: mock_consumer = 60,
+    }
+);
+
+impl frame_system::Config for Runtime {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type WeightInfo = ();
+    type Balance = Balance;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type ReserveIdentifier = [u8; 8];
+    type HoldIdentifier = ();
+    type FreezeIdentifier = ();
+    type MaxLocks = ConstU32<10>;
+    type MaxReserves = ConstU32<10>;
+    type MaxHolds = ConstU32<0>;
+    type MaxFreezes = ConstU32<0>;
+}
+
+impl pallet_assets::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type Balance = Balance;
+    type RemoveItemsLimit = ConstU32<100>;
+    type AssetId = AssetId;
+    type AssetIdParameter = AssetId;
+    type Currency = Balances;
+    type CreateOrigin = AsEnsureOriginWithArg<EnsureSigned<AccountId>>;
+    type ForceOrigin = EnsureRoot<AccountId>;
+    type AssetDeposit = ConstU128<0>;
+    type AssetAccountDeposit = ConstU128<0>;
+    type MetadataDepositBase = ConstU128<0>;
+    type MetadataDepositPerByte = ConstU128<0>;
+    type ApprovalDeposit = ConstU128<0>;
+    type StringLimit = ConstU32<32>;
+    type Freezer = ();
+    type Extra = ();
+    type CallbackHandle = ();
+    type WeightInfo = ();
+    #[cfg(feature = "runtime-benchmarks")]
+    type BenchmarkHelper = ();
+}
+
+parameter_types! {
+    pub UniversalLocation: InteriorMultiLocation = Parachain(MsgQueue::parachain_id().into()).into();
+    pub const UnitWeightCost: Weight = Weight::from_parts(1, 1);
+}
+
+pub type LocationToAccountId = SiblingParachainConvertsVia<Sibling, AccountId>;
+
+/// Native origin of a sibling parachain, as an XCM origin
+///
+/// Stands in for cumulus' `SiblingParachainAsNative`, which consumer
+/// parachains use to accept the security chain's messages.
+pub struct SiblingParachainAsXcm;
+impl ConvertOrigin<RuntimeOrigin> for SiblingParachainAsXcm {
+    fn convert_origin(origin: impl Into<MultiLocation>, kind: OriginKind) -> Result<RuntimeOrigin, MultiLocation> {
+        match (kind, origin.into()) {
+            (OriginKind::Native, location @ MultiLocation { parents: 1, interior: X1(Parachain(_)) }) => {
+                Ok(pallet_xcm::Origin::Xcm(location).into())
+            }
+            (_, location) => Err(location),
+        }
+    }
+}
+
+/// Account of an `AccountIndex64` location on a sibling parachain
+///
+/// Every parachain in the network uses the same account IDs, so the index is
+/// the account itself.
+pub struct SiblingAccountIndex;
+impl Convert<MultiLocation, Option<AccountId>> for SiblingAccountIndex {
+    fn convert(location: MultiLocation) -> Option<AccountId> {
+        match location {
+            MultiLocation { parents: 1, interior: X2(Parachain(_), AccountIndex64 { index, .. }) } => Some(index),
+            _ => None,
+        }
+    }
+}
+
+pub type XcmOriginToCallOrigin = (SiblingParachainAsXcm, XcmPassthrough<RuntimeOrigin>);
+
+pub type XcmRouter = super::ParachainXcmRouter<MsgQueue>;
+
+pub struct XcmConfig;
+impl xcm_executor::Config for XcmConfig {
+    type RuntimeCall = RuntimeCall;
+    type XcmSender = XcmRouter;
+    type AssetTransactor = ();
+    type OriginConverter = XcmOriginToCallOrigin;
+    type IsReserve = ();
+    type IsTeleporter = ();
+    type UniversalLocation = UniversalLocation;
+    type Barrier = AllowUnpaidExecutionFrom<Everything>;
+    type Weigher = FixedWeightBounds<UnitWeightCost, RuntimeCall, ConstU32<100>>;
+    type Trader = ();
+    type ResponseHandler = ();
+    type AssetTrap = ();
+    type AssetLocker = ();
+    type AssetExchanger = ();
+    type AssetClaims = ();
+    type SubscriptionService = ();
+    type PalletInstancesInfo = ();
+    type FeeManager = ();
+    type MaxAssetsIntoHolding = ConstU32<64>;
+    type MessageExporter = ();
+    type UniversalAliases = Nothing;
+    type CallDispatcher = RuntimeCall;
+    type SafeCallFilter = Everything;
+}
+
+impl pallet_xcm::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type SendXcmOrigin = EnsureXcmOrigin<RuntimeOrigin, ()>;
+    type XcmRouter = XcmRouter;
+    type ExecuteXcmOrigin = EnsureXcmOrigin<RuntimeOrigin, ()>;
+    type XcmExecuteFilter = Nothing;
+    type XcmExecutor = XcmExecutor<XcmConfig>;
+    type XcmTeleportFilter = Nothing;
+    type XcmReserveTransferFilter = Nothing;
+    type Weigher = FixedWeightBounds<UnitWeightCost, RuntimeCall, ConstU32<100>>;
+    type UniversalLocation = UniversalLocation;
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    const VERSION_DISCOVERY_QUEUE_SIZE: u32 = 100;
+    type AdvertisedXcmVersion = pallet_xcm::CurrentXcmVersion;
+    type Currency = Balances;
+    type CurrencyMatcher = ();
+    type TrustedLockers = ();
+    type SovereignAccountOf = LocationToAccountId;
+    type MaxLockers = ConstU32<8>;
+    type WeightInfo = pallet_xcm::TestWeightInfo;
+    #[cfg(feature = "runtime-benchmarks")]
+    type ReachableDest = ();
+    type AdminOrigin = EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+    pub const RestakingPalletId: PalletId = PalletId(*b"py/rstkg");
+    pub const ConsumerCallIndex: [u8; 2] = [60, 0];
+    pub ConsumerCallWeight: Weight = Weight::from_parts(1_000_000_000, 64 * 1024);
+    pub MaxBoost: FixedU128 = FixedU128::from_u32(2);
+}
+
+impl pallet_restaking::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type Currency = Balances;
+    type Slash = ();
+    type SlashOrigin = EnsureRoot<AccountId>;
+    type RewardOrigin = EnsureRoot<AccountId>;
+    type PalletId = RestakingPalletId;
+    type MaxRewardedParachains = ConstU32<8>;
+    type ParachainOrigin = EnsureRoot<AccountId>;
+    type MaxRebalanceJobs = ConstU32<8>;
+    type MaxParachainExits = ConstU32<4>;
+    type MaxWithdrawals = ConstU32<8>;
+    type AssetId = AssetId;
+    type Assets = Assets;
+    type PriceOracle = ();
+    type MaxPriceAge = ConstU64<10>;
+    type AssetOrigin = EnsureRoot<AccountId>;
+    type MaxRestakedAssets = ConstU32<4>;
+    type XcmSender = XcmRouter;
+    type XcmOrigin = pallet_xcm::EnsureXcm<Everything>;
+    type AccountLocation = SiblingAccountIndex;
+    type ConsumerCallIndex = ConsumerCallIndex;
+    type ConsumerCallWeight = ConsumerCallWeight;
+    type MinRestake = ConstU128<100>;
+    type MaxAllocations = ConstU32<4>;
+    type OperatorBond = ConstU128<500>;
+    type MaxDelegators = ConstU32<8>;
+    type MaxUndelegations = ConstU32<4>;
+    type UnbondingPeriod = ConstU64<20>;
+    type MinStakeDuration = ConstU64<10>;
+    type MaxStakeDuration = ConstU64<110>;
+    type RewardFrequency = ConstU64<10>;
+    type LockBoost = LinearBoost<MaxBoost>;
+}
+
+/// Restaking message handler of a consumer parachain
+#[frame_support::pallet]
+pub mod mock_consumer {
+    use super::{AccountId, Balance};
+    use crate::messages::VersionedRestakingMessage;
+    use frame_support::pallet_prelude::*;
+    use frame_system::pallet_prelude::*;
+    use sp_std::prelude::*;
+    use xcm::latest::prelude::*;
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// Origin of messages from the security chain
+        type XcmOrigin: EnsureOrigin<Self::RuntimeOrigin, Success = MultiLocation>;
+    }
+
+    #[pallet::pallet]
+    #[pallet::without_storage_info]
+    pub struct Pallet<T>(_);
+
+    /// Messages received, with the location they came from
+    #[pallet::storage]
+    pub type Received<T: Config> =
+        StorageValue<_, Vec<(MultiLocation, VersionedRestakingMessage<AccountId, Balance>)>, ValueQuery>;
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Record a message from the security chain
+        #[pallet::call_index(0)]
+        #[pallet::weight(Weight::zero())]
+        pub fn handle_message(
+            origin: OriginFor<T>,
+            message: VersionedRestakingMessage<AccountId, Balance>,
+        ) -> DispatchResult {
+            let location = T::XcmOrigin::ensure_origin(origin)?;
+            Received::<T>::append((location, message));
+            Ok(())
+        }
+    }
+}
+
+impl mock_consumer::Config for Runtime {
+    type XcmOrigin = pallet_xcm::EnsureXcm<Everything>;
+}
+
+/// XCMP and DMP queue executing messages as they arrive
+#[frame_support::pallet]
+pub mod mock_msg_queue {
+    use super::*;
+    use frame_support::pallet_prelude::*;
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// Executor for incoming messages
+        type XcmExecutor: ExecuteXcm<Self::RuntimeCall>;
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {}
+
+    #[pallet::pallet]
+    #[pallet::without_storage_info]
+    pub struct Pallet<T>(_);
+
+    #[pallet::storage]
+    #[pallet::getter(fn parachain_id)]
+    pub(super) type ParachainId<T: Config> = StorageValue<_, ParaId, ValueQuery>;
+
+    impl<T: Config> Get<ParaId> for Pallet<T> {
+        fn get() -> ParaId {
+            Self::parachain_id()
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        pub fn set_para_id(para_id: ParaId) {
+            ParachainId::<T>::put(para_id);
+        }
+
+        fn execute(origin: impl Into<MultiLocation>, message: VersionedXcm<T::RuntimeCall>, max_weight: Weight) {
+            let id = message.using_encoded(sp_io::hashing::blake2_256);
+            if let Ok(xcm) = Xcm::try_from(message) {
+                let _ = T::XcmExecutor::execute_xcm(origin, xcm, id, max_weight);
+            }
+        }
+    }
+
+    impl<T: Config> XcmpMessageHandler for Pallet<T> {
+        fn handle_xcmp_messages<'a, I: Iterator<Item = (ParaId, RelayBlockNumber, &'a [u8])>>(
+            iter: I,
+            max_weight: Weight,
+        ) -> Weight {
+            for (sender, _sent_at, mut data) in iter {
+                let _ = XcmpMessageFormat::decode(&mut data).expect("Simulator encodes versioned XCM; qed");
+                let sender = (Parent, Parachain(sender.into()));
+                while !data.is_empty() {
+                    let message = VersionedXcm::<T::RuntimeCall>::decode(&mut data)
+                        .expect("Simulator encodes versioned XCM; qed");
+                    Self::execute(sender, message, max_weight);
+                }
+            }
+            max_weight
+        }
+    }
+
+    impl<T: Config> DmpMessageHandler for Pallet<T> {
+        fn handle_dmp_messages(iter: impl Iterator<Item = (RelayBlockNumber, Vec<u8>)>, limit: Weight) -> Weight {
+            for (_sent_at, data) in iter {
+                if let Ok(message) = VersionedXcm::<T::RuntimeCall>::decode(&mut &data[..]) {
+                    Self::execute(Parent, message, limit);
+                }
+            }
+            limit
+        }
+    }
+}
+
+impl mock_msg_queue::Config for Runtime {
+    type XcmExecutor = XcmExecutor<XcmConfig>;
+}
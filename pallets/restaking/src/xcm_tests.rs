@@ -0,0 +1,146 @@
+//! Restaking message round trips between the security chain and consumer parachains
+
+use crate::{
+    messages::RestakingMessage, xcm_mock::*, PendingRewards, RestakeAllocation, RestakeStrategy, Restakers,
+};
+use frame_support::{assert_ok, bounded_vec};
+use sp_runtime::Perbill;
+use xcm_simulator::TestExt;
+
+use parachain::{Balances, Restaking, Runtime, RuntimeOrigin};
+
+/// Alice restakes 1_000 on `PARA_A`
+fn restake_on_para_a() {
+    SecurityChain::execute_with(|| {
+        assert_ok!(Restaking::restake(
+            RuntimeOrigin::signed(ALICE),
+            1_000,
+            RestakeStrategy::SingleParachain,
+            bounded_vec![RestakeAllocation { parachain_id: PARA_A, percentage: 10_000 }],
+            10,
+        ));
+    });
+}
+
+#[test]
+fn registered_consumers_receive_allocation_updates() {
+    MockNet::reset();
+    restake_on_para_a();
+
+    ConsumerA::execute_with(|| {
+        assert_eq!(received(), vec![RestakingMessage::AllocationUpdate { account: ALICE, amount: 1_000 }]);
+    });
+    ConsumerB::execute_with(|| assert_eq!(received(), vec![]));
+}
+
+#[test]
+fn accounts_claim_rewards_from_consumers_without_compounding() {
+    MockNet::reset();
+    restake_on_para_a();
+    SecurityChain::execute_with(|| {
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 100));
+    });
+    run_to_block(10);
+
+    ConsumerA::execute_with(|| send_as_account(ALICE, RestakingMessage::RewardClaim { account: ALICE }));
+
+    SecurityChain::execute_with(|| {
+        assert_eq!(PendingRewards::<Runtime>::get(ALICE), 0);
+        assert_eq!(Balances::free_balance(ALICE), 10_000 - 1_000 + 100);
+        assert_eq!(Restakers::<Runtime>::get(ALICE).unwrap().total_staked, 1_000);
+    });
+}
+
+#[test]
+fn reward_claims_for_other_accounts_are_rejected() {
+    MockNet::reset();
+    restake_on_para_a();
+    SecurityChain::execute_with(|| {
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 100));
+    });
+    run_to_block(10);
+
+    // Neither the parachain itself nor another account may pay out Alice's rewards
+    ConsumerA::execute_with(|| {
+        send_to_security_chain(RestakingMessage::RewardClaim { account: ALICE });
+        send_as_account(BOB, RestakingMessage::RewardClaim { account: ALICE });
+    });
+
+    SecurityChain::execute_with(|| {
+        assert_eq!(Balances::free_balance(ALICE), 10_000 - 1_000);
+        assert_eq!(Restaking::calculate_rewards(&ALICE), 100);
+    });
+}
+
+#[test]
+fn slash_reports_from_accounts_are_rejected() {
+    MockNet::reset();
+    restake_on_para_a();
+
+    ConsumerA::execute_with(|| {
+        send_as_account(BOB, RestakingMessage::SlashReport { offender: ALICE, fraction: Perbill::from_percent(10) });
+    });
+
+    SecurityChain::execute_with(|| {
+        assert_eq!(Restakers::<Runtime>::get(ALICE).unwrap().total_staked, 1_000);
+    });
+}
+
+#[test]
+fn slash_reports_slash_stake_and_notify_the_reporter() {
+    MockNet::reset();
+    restake_on_para_a();
+
+    ConsumerA::execute_with(|| {
+        send_to_security_chain(RestakingMessage::SlashReport {
+            offender: ALICE,
+            fraction: Perbill::from_percent(10),
+        });
+    });
+
+    SecurityChain::execute_with(|| {
+        assert_eq!(Restakers::<Runtime>::get(ALICE).unwrap().total_staked, 900);
+    });
+    ConsumerA::execute_with(|| {
+        assert_eq!(
+            received(),
+            vec![
+                RestakingMessage::AllocationUpdate { account: ALICE, amount: 1_000 },
+                RestakingMessage::SlashNotification { account: ALICE, amount: 100 },
+            ]
+        );
+    });
+}
+
+#[test]
+fn slash_reports_only_reach_stake_securing_the_reporter() {
+    MockNet::reset();
+    restake_on_para_a();
+
+    ConsumerB::execute_with(|| {
+        send_to_security_chain(RestakingMessage::SlashReport {
+            offender: ALICE,
+            fraction: Perbill::from_percent(10),
+        });
+    });
+
+    SecurityChain::execute_with(|| {
+        assert_eq!(Restakers::<Runtime>::get(ALICE).unwrap().total_staked, 1_000);
+    });
+}
+
+#[test]
+fn messages_from_unregistered_parachains_are_rejected() {
+    MockNet::reset();
+    restake_on_para_a();
+    SecurityChain::execute_with(|| {
+        assert_ok!(Restaking::set_reward_budget(RuntimeOrigin::root(), PARA_A, 100));
+    });
+    run_to_block(10);
+
+    Unregistered::execute_with(|| send_as_account(ALICE, RestakingMessage::RewardClaim { account: ALICE }));
+
+    SecurityChain::execute_with(|| {
+        assert_eq!(Balances::free_balance(ALICE), 10_000 - 1_000);
+    });
+}
@@ -0,0 +1,168 @@
+//! Diversity constraints on validator sets
+//!
+//! Validators declare the operator running them and, optionally, the region
+//! and autonomous system (ASN) they run in. A parachain can cap how many of
+//! its validators share an operator, region or ASN so that no single entity
+//! controls its set.
+//!
+//! Selection is greedy-then-repair. Candidates are taken in rank order as
+//! long as they fit under every cap. If that leaves the set short of its
+//! target, a repair pass looks for swaps that trade one selected validator
+//! for a rejected one in a way that lets a second rejected validator in,
+//! growing the set by one each time.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+
+/// Diversity tags of a validator
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct DiversityTags<Operator> {
+    /// Entity operating the validator
+    pub operator: Operator,
+    /// ISO 3166-1 alpha-2 code of the country the validator runs in
+    pub region: Option<[u8; 2]>,
+    /// Autonomous system number of the validator's network
+    pub asn: Option<u32>,
+}
+
+/// Max validators sharing a tag in one parachain's set, `None` for no cap
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct DiversityCaps {
+    /// Max validators per operator
+    pub max_per_operator: Option<u32>,
+    /// Max validators per region
+    pub max_per_region: Option<u32>,
+    /// Max validators per ASN
+    pub max_per_asn: Option<u32>,
+}
+
+/// Validators selected so far per tag
+struct Usage<'a, Operator> {
+    operators: BTreeMap<&'a Operator, u32>,
+    regions: BTreeMap<[u8; 2], u32>,
+    asns: BTreeMap<u32, u32>,
+}
+
+fn below<K: Ord>(counts: &BTreeMap<K, u32>, key: &K, cap: Option<u32>) -> bool {
+    cap.map_or(true, |cap| counts.get(key).copied().unwrap_or(0) < cap)
+}
+
+fn adjust<K: Ord>(counts: &mut BTreeMap<K, u32>, key: K, add: bool) {
+    let count = counts.entry(key).or_insert(0);
+    *count = if add { count.saturating_add(1) } else { count.saturating_sub(1) };
+}
+
+impl<'a, Operator: Ord> Usage<'a, Operator> {
+    fn new() -> Self {
+        Self { operators: BTreeMap::new(), regions: BTreeMap::new(), asns: BTreeMap::new() }
+    }
+
+    /// Whether adding a validator with `tags` keeps every count within its cap
+    fn admits(&self, tags: &DiversityTags<Operator>, caps: &DiversityCaps) -> bool {
+        below(&self.operators, &&tags.operator, caps.max_per_operator)
+            && tags.region.map_or(true, |region| below(&self.regions, &region, caps.max_per_region))
+            && tags.asn.map_or(true, |asn| below(&self.asns, &asn, caps.max_per_asn))
+    }
+
+    fn update(&mut self, tags: &'a DiversityTags<Operator>, add: bool) {
+        adjust(&mut self.operators, &tags.operator, add);
+        if let Some(region) = tags.region {
+            adjust(&mut self.regions, region, add);
+        }
+        if let Some(asn) = tags.asn {
+            adjust(&mut self.asns, asn, add);
+        }
+    }
+}
+
+/// Whether a validator with `tags` can join `members` without exceeding `caps`
+pub fn fits<Operator: Ord>(
+    members: &[DiversityTags<Operator>],
+    tags: &DiversityTags<Operator>,
+    caps: &DiversityCaps,
+) -> bool {
+    let mut usage = Usage::new();
+    for member in members {
+        usage.update(member, true);
+    }
+    usage.admits(tags, caps)
+}
+
+/// Indices into `ranked` of up to `target` validators that respect `caps`
+///
+/// `ranked` is ordered best first; the result keeps that order. Only the
+/// first `2 * target` rejected candidates are considered for repair, which
+/// bounds the work done on chain.
+pub fn select_diverse<Operator: Ord>(
+    ranked: &[DiversityTags<Operator>],
+    target: u32,
+    caps: &DiversityCaps,
+) -> Vec<usize> {
+    let target = target as usize;
+    let mut usage = Usage::new();
+    let mut selected = Vec::with_capacity(target);
+    let mut rejected = Vec::new();
+
+    for (index, tags) in ranked.iter().enumerate() {
+        if selected.len() == target {
+            break;
+        }
+        if usage.admits(tags, caps) {
+            usage.update(tags, true);
+            selected.push(index);
+        } else {
+            rejected.push(index);
+        }
+    }
+
+    rejected.truncate(target.saturating_mul(2));
+    while selected.len() < target {
+        let Some((slot, swapped_in, added)) = find_repair(ranked, caps, &mut usage, &selected, &rejected) else {
+            break;
+        };
+        let swapped_out = selected[slot];
+        usage.update(&ranked[swapped_out], false);
+        usage.update(&ranked[swapped_in], true);
+        usage.update(&ranked[added], true);
+        selected[slot] = swapped_in;
+        selected.push(added);
+        rejected.retain(|index| *index != swapped_in && *index != added);
+        rejected.push(swapped_out);
+    }
+
+    selected.sort();
+    selected
+}
+
+/// Find a selected slot and two rejected candidates such that replacing the
+/// slot with the first candidate lets the second one in as well
+fn find_repair<'a, Operator: Ord>(
+    ranked: &'a [DiversityTags<Operator>],
+    caps: &DiversityCaps,
+    usage: &mut Usage<'a, Operator>,
+    selected: &[usize],
+    rejected: &[usize],
+) -> Option<(usize, usize, usize)> {
+    for &candidate in rejected {
+        for (slot, &member) in selected.iter().enumerate() {
+            usage.update(&ranked[member], false);
+            let mut found = None;
+            if usage.admits(&ranked[candidate], caps) {
+                usage.update(&ranked[candidate], true);
+                found = rejected
+                    .iter()
+                    .copied()
+                    .find(|other| *other != candidate && usage.admits(&ranked[*other], caps))
+                    .map(|other| (slot, candidate, other));
+                usage.update(&ranked[candidate], false);
+            }
+            usage.update(&ranked[member], true);
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,114 @@
+//! Heartbeats proving that validators are online
+//!
+//! Once per session every validator's offchain worker submits a heartbeat
+//! signed with the validator's heartbeat key. At session end the pallet
+//! records which validators were heard from, and reports the others as an
+//! `UnresponsivenessOffence`.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::{traits::Saturating, KeyTypeId, Perbill, RuntimeDebug};
+use sp_staking::{offence::{Kind, Offence}, SessionIndex};
+use sp_std::prelude::*;
+
+use crate::selection::FULL_WEIGHT;
+
+/// Key type of heartbeat keys
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"vhbt");
+
+/// Heartbeat keys signed with sr25519
+pub mod sr25519 {
+    mod app_sr25519 {
+        use sp_application_crypto::{app_crypto, sr25519};
+        app_crypto!(sr25519, super::super::KEY_TYPE);
+    }
+
+    /// Heartbeat public key
+    pub type AuthorityId = app_sr25519::Public;
+
+    /// Heartbeat signature
+    pub type AuthoritySignature = app_sr25519::Signature;
+}
+
+/// Heartbeat submitted by a validator's offchain worker
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct Heartbeat<BlockNumber, ValidatorId> {
+    /// Block at which the heartbeat was sent
+    pub block_number: BlockNumber,
+    /// Session the heartbeat belongs to
+    pub session_index: SessionIndex,
+    /// Validator sending the heartbeat
+    pub validator: ValidatorId,
+}
+
+/// Number of past sessions kept in a liveness record
+pub const LIVENESS_WINDOW: u8 = 32;
+
+/// Liveness of a validator over its last `LIVENESS_WINDOW` sessions
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct LivenessRecord {
+    /// One bit per session, most recent session in the lowest bit
+    pub history: u32,
+    /// Sessions recorded so far, up to `LIVENESS_WINDOW`
+    pub tracked: u8,
+}
+
+impl LivenessRecord {
+    /// Record whether the validator was online in the session that just ended
+    pub fn record(&mut self, online: bool) {
+        self.history = (self.history << 1) | online as u32;
+        self.tracked = self.tracked.saturating_add(1).min(LIVENESS_WINDOW);
+    }
+
+    /// Share of recorded sessions the validator was online in (0-10000)
+    pub fn uptime(&self) -> u16 {
+        if self.tracked == 0 {
+            return FULL_WEIGHT as u16;
+        }
+        let online = self.history.count_ones();
+        (online * FULL_WEIGHT / self.tracked as u32) as u16
+    }
+}
+
+/// Validators that sent no heartbeat during a session
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct UnresponsivenessOffence<Offender> {
+    /// Session of the offence
+    pub session_index: SessionIndex,
+    /// Number of validators expected to send a heartbeat
+    pub validator_set_count: u32,
+    /// Validators that did not
+    pub offenders: Vec<Offender>,
+}
+
+impl<Offender: Clone> Offence<Offender> for UnresponsivenessOffence<Offender> {
+    const ID: Kind = *b"eigen:offline___";
+    type TimeSlot = SessionIndex;
+
+    fn offenders(&self) -> Vec<Offender> {
+        self.offenders.clone()
+    }
+
+    fn session_index(&self) -> SessionIndex {
+        self.session_index
+    }
+
+    fn validator_set_count(&self) -> u32 {
+        self.validator_set_count
+    }
+
+    fn time_slot(&self) -> Self::TimeSlot {
+        self.session_index
+    }
+
+    /// Same curve as `im-online`: nothing while up to 10% + 1 of the
+    /// validators are offline, then growing linearly to 7% when a third is
+    fn slash_fraction(&self, offenders_count: u32) -> Perbill {
+        if let Some(threshold) = offenders_count.checked_sub(self.validator_set_count / 10 + 1) {
+            let x = Perbill::from_rational(3 * threshold, self.validator_set_count);
+            x.saturating_mul(Perbill::from_percent(7))
+        } else {
+            Perbill::default()
+        }
+    }
+}
@@ -2,19 +2,72 @@
 //!
 //! This module coordinates validators across different parachains
 //! in the Matrix-Magiq ecosystem.
+//!
+//! Each parachain has a `CoordinationConfig` set by governance. Updates are
+//! validated before they are stored: selection weights must add up to 100%
+//! and the validator set sizes must satisfy `min <= target <= max`.
+//!
+//! Every `rotation_frequency` blocks a parachain's validator set is
+//! reselected and pushed into pallet_eigen's `ValidatorSets`. Outgoing
+//! validators stay in the set for `HandoverPeriod` blocks alongside the
+//! incoming ones so that they can hand over work, after which the rotation
+//! completes and only the newly selected set remains.
+//!
+//! At most `MaxRotationsPerBlock` rotations are started or completed in a
+//! block. Rotations that are due wait in `RotationQueue` and handovers that
+//! ended are completed in the following blocks. Selection considers the
+//! first `MaxCandidates` validator profiles in storage order.
+//!
+//! Performance metrics come from the chain itself. Each validator registers
+//! a heartbeat key, and its offchain worker submits one signed heartbeat per
+//! session as an unsigned transaction. Blocks authored are counted through
+//! `pallet_authorship`. When pallet_eigen ends a session, heartbeats and
+//! block counts are folded into each selected validator's
+//! `ValidatorPerformance`, and validators that sent no heartbeat are
+//! reported as unresponsive. Each session also yields a score, see
+//! `scoring`, whose moving average is the track record `Hybrid` selection
+//! weighs with `history_weight`.
+//!
+//! Jailed validators leave every validator set immediately and cannot be
+//! selected. Each jailing of the same validator doubles its duration, up to
+//! `MaxJailDuration`. Jails expire in `on_initialize`; a validator whose
+//! stake has fallen below the minimum stays jailed until it re-bonds and
+//! calls `unjail`.
+//!
+//! Slashes are typed by `pallet_eigen::Offence`. The fraction of stake
+//! slashed follows a governance-set `SlashCurve` per offence and grows with
+//! the share of validators committing the same offence in the same session;
+//! earlier offenders of that session are topped up to the higher fraction.
+//! `ConfigOrigin` reports offences with `report_offence`; other pallets call
+//! `slash_validator` and `jail_validator` directly.
+//!
+//! Validators declare their operator and optionally their region and ASN.
+//! Each parachain can cap how many of its validators share any of these, see
+//! `diversity`; a validator that declared nothing counts as operated by its
+//! own account.
+//!
+//! The eligible validators that just missed selection form a ranked standby
+//! pool for the parachain. A validator that is jailed or exits is replaced
+//! right away by the best standby that fits under the parachain's caps.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{
-    dispatch::DispatchResult,
-    pallet_prelude::*,
-    traits::{Get, Currency},
-};
-use frame_system::pallet_prelude::*;
-use sp_runtime::{traits::Hash, RuntimeDebug};
-use sp_std::prelude::*;
+pub use pallet::*;
+
+pub mod diversity;
+pub mod heartbeat;
+pub mod scoring;
+pub mod selection;
+pub mod slashing;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
 
 /// Validator rotation strategy
 #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
@@ -44,8 +97,22 @@ pub struct SelectionCriteria {
     pub history_weight: u16,
 }
 
+impl SelectionCriteria {
+    /// Check that the uptime is a valid percentage and the weights sum to 100%
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.min_uptime as u32 > selection::FULL_WEIGHT {
+            return Err(ConfigError::InvalidUptime);
+        }
+        let total = self.performance_weight as u32 + self.stake_weight as u32 + self.history_weight as u32;
+        if total != selection::FULL_WEIGHT {
+            return Err(ConfigError::InvalidWeights);
+        }
+        Ok(())
+    }
+}
+
 /// Validator coordination configuration
-#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 pub struct CoordinationConfig<BlockNumber> {
     /// Rotation frequency in blocks
     pub rotation_frequency: BlockNumber,
@@ -61,66 +128,1492 @@ pub struct CoordinationConfig<BlockNumber> {
     pub min_validators: u32,
 }
 
-/// Select validators for a parachain
-pub fn select_validators<T: frame_system::Config>(
-    parachain_id: u32,
-    count: u32,
-    strategy: RotationStrategy,
-    criteria: &SelectionCriteria,
-) -> Vec<T::ValidatorId> {
-    // Implementation would select validators based on strategy and criteria
-    // This is a placeholder for the actual implementation
-    Vec::new()
+impl<BlockNumber: sp_runtime::traits::Zero> CoordinationConfig<BlockNumber> {
+    /// Check the selection criteria, rotation frequency and set sizes
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.selection_criteria.validate()?;
+        if self.rotation_frequency.is_zero() {
+            return Err(ConfigError::ZeroRotationFrequency);
+        }
+        if self.min_validators > self.target_validators || self.target_validators > self.max_validators {
+            return Err(ConfigError::InvalidSetSize);
+        }
+        Ok(())
+    }
 }
 
-/// Rotate validators across parachains
-pub fn rotate_validators<T: frame_system::Config>(
-    config: &CoordinationConfig<T::BlockNumber>,
-) -> DispatchResult {
-    // Implementation would rotate validators according to the configuration
-    // This is a placeholder for the actual implementation
-    Ok(())
+/// Reasons a coordination configuration is rejected
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ConfigError {
+    /// Performance, stake and history weights do not sum to 10000
+    InvalidWeights,
+    /// Minimum uptime is above 10000
+    InvalidUptime,
+    /// Set sizes do not satisfy `min_validators <= target_validators <= max_validators`
+    InvalidSetSize,
+    /// Rotation frequency is zero
+    ZeroRotationFrequency,
 }
 
-/// Update validator performance metrics
-pub fn update_performance<T: frame_system::Config>(
-    validator: &T::ValidatorId,
-    uptime: u16,
-    blocks_proposed: u64,
-    blocks_finalized: u64,
-) -> DispatchResult {
-    // Implementation would update validator performance metrics
-    // This is a placeholder for the actual implementation
-    Ok(())
+/// Change of a single configuration field
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct FieldChange<T> {
+    /// Previous value, `None` if the parachain had no configuration
+    pub old: Option<T>,
+    /// New value
+    pub new: T,
 }
 
-/// Slash a validator for misbehavior
-pub fn slash_validator<T: frame_system::Config>(
-    validator: &T::ValidatorId,
-    amount: T::Balance,
-    reason: Vec<u8>,
-) -> DispatchResult {
-    // Implementation would slash the validator and update metrics
-    // This is a placeholder for the actual implementation
-    Ok(())
+/// Fields changed by a configuration update, unchanged fields are `None`
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct ConfigDiff<BlockNumber> {
+    /// Rotation frequency in blocks
+    pub rotation_frequency: Option<FieldChange<BlockNumber>>,
+    /// Rotation strategy
+    pub rotation_strategy: Option<FieldChange<RotationStrategy>>,
+    /// Selection criteria
+    pub selection_criteria: Option<FieldChange<SelectionCriteria>>,
+    /// Maximum validator set size
+    pub max_validators: Option<FieldChange<u32>>,
+    /// Target validator set size
+    pub target_validators: Option<FieldChange<u32>>,
+    /// Minimum validator set size
+    pub min_validators: Option<FieldChange<u32>>,
+}
+
+impl<BlockNumber: Clone + PartialEq> ConfigDiff<BlockNumber> {
+    /// Diff between the stored configuration, if any, and its replacement
+    pub fn between(old: Option<&CoordinationConfig<BlockNumber>>, new: &CoordinationConfig<BlockNumber>) -> Self {
+        Self {
+            rotation_frequency: change(old.map(|c| &c.rotation_frequency), &new.rotation_frequency),
+            rotation_strategy: change(old.map(|c| &c.rotation_strategy), &new.rotation_strategy),
+            selection_criteria: change(old.map(|c| &c.selection_criteria), &new.selection_criteria),
+            max_validators: change(old.map(|c| &c.max_validators), &new.max_validators),
+            target_validators: change(old.map(|c| &c.target_validators), &new.target_validators),
+            min_validators: change(old.map(|c| &c.min_validators), &new.min_validators),
+        }
+    }
+
+    /// Whether no field changed
+    pub fn is_empty(&self) -> bool {
+        self.rotation_frequency.is_none()
+            && self.rotation_strategy.is_none()
+            && self.selection_criteria.is_none()
+            && self.max_validators.is_none()
+            && self.target_validators.is_none()
+            && self.min_validators.is_none()
+    }
 }
 
-/// Jail a validator temporarily
-pub fn jail_validator<T: frame_system::Config>(
-    validator: &T::ValidatorId,
-    duration: T::BlockNumber,
-    reason: Vec<u8>,
-) -> DispatchResult {
-    // Implementation would jail the validator temporarily
-    // This is a placeholder for the actual implementation
-    Ok(())
+fn change<T: Clone + PartialEq>(old: Option<&T>, new: &T) -> Option<FieldChange<T>> {
+    match old {
+        Some(old) if old == new => None,
+        _ => Some(FieldChange { old: old.cloned(), new: new.clone() }),
+    }
 }
 
-/// Release a validator from jail
-pub fn release_validator<T: frame_system::Config>(
-    validator: &T::ValidatorId,
-) -> DispatchResult {
-    // Implementation would release the validator from jail
-    // This is a placeholder for the actual implementation
-    Ok(())
+#[frame_support::pallet]
+pub mod pallet {
+    use super::*;
+    use crate::{
+        diversity::{self, DiversityCaps, DiversityTags},
+        heartbeat::{Heartbeat, LivenessRecord, UnresponsivenessOffence},
+        scoring::{ScoreRecord, SessionMetrics, SessionScore},
+        slashing::{self, SlashCurve},
+    };
+    use pallet_eigen::{BalanceOf, Offence};
+    use frame_support::{pallet_prelude::*, traits::Randomness};
+    use frame_system::{
+        offchain::{SendTransactionTypes, SubmitTransaction},
+        pallet_prelude::*,
+    };
+    use sp_runtime::{
+        offchain::storage::StorageValueRef,
+        traits::{One, Saturating, UniqueSaturatedInto, Zero},
+        Perbill, RuntimeAppPublic,
+    };
+    use sp_staking::offence::ReportOffence;
+    use sp_std::prelude::*;
+
+    /// Blocks an offchain worker waits before resending a heartbeat that was not included
+    const HEARTBEAT_RETRY_BLOCKS: u32 = 5;
+
+    /// Alias for the coordination configuration stored per parachain
+    pub type CoordinationConfigOf<T> = CoordinationConfig<<T as frame_system::Config>::BlockNumber>;
+
+    /// Alias for the heartbeat submitted by offchain workers
+    pub type HeartbeatOf<T> =
+        Heartbeat<<T as frame_system::Config>::BlockNumber, <T as pallet_eigen::Config>::ValidatorId>;
+
+    /// Alias for the score record kept per validator
+    pub type ScoreRecordOf<T> = ScoreRecord<<T as Config>::MaxScoreHistory>;
+
+    /// Alias for the jail reasons stored on chain
+    pub type JailReasonOf<T> = BoundedVec<u8, <T as Config>::MaxReasonLength>;
+
+    /// Alias for the diversity tags declared by a validator
+    pub type DiversityTagsOf<T> = DiversityTags<<T as frame_system::Config>::AccountId>;
+
+    /// Alias for a list of validators bounded by the validator set size
+    pub type ValidatorsOf<T> = BoundedVec<
+        <T as pallet_eigen::Config>::ValidatorId,
+        <T as pallet_eigen::Config>::MaxValidatorsPerSet,
+    >;
+
+    /// Rotation whose outgoing validators are still handing over
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct PendingRotation<T: Config> {
+        /// Newly selected set, which remains once the handover is over
+        pub incoming: ValidatorsOf<T>,
+        /// Validators that joined the set
+        pub added: ValidatorsOf<T>,
+        /// Validators that leave the set when the handover is over
+        pub removed: ValidatorsOf<T>,
+        /// Block at which the handover ends
+        pub completes_at: T::BlockNumber,
+    }
+
+    /// Jailed validator
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    #[scale_info(skip_type_params(T))]
+    pub struct JailInfo<T: Config> {
+        /// Why the validator was jailed
+        pub reason: JailReasonOf<T>,
+        /// Block at which the jail expires
+        pub until: T::BlockNumber,
+        /// Status to restore on release
+        pub previous_status: pallet_eigen::ValidatorStatus,
+    }
+
+    #[pallet::pallet]
+    pub struct Pallet<T>(_);
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config + pallet_eigen::Config + SendTransactionTypes<Call<Self>> {
+        /// The overarching event type
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+        /// Origin allowed to update coordination configurations
+        type ConfigOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Source of randomness for validator selection
+        type Randomness: Randomness<Self::Hash, Self::BlockNumber>;
+
+        /// Max parachains with a coordination configuration
+        #[pallet::constant]
+        type MaxParachains: Get<u32>;
+
+        /// Blocks outgoing validators stay in the set after a rotation
+        #[pallet::constant]
+        type HandoverPeriod: Get<Self::BlockNumber>;
+
+        /// Max validator profiles read when selecting a validator set
+        #[pallet::constant]
+        type MaxCandidates: Get<u32>;
+
+        /// Max rotations started or completed in `on_initialize` per block
+        #[pallet::constant]
+        type MaxRotationsPerBlock: Get<u32>;
+
+        /// Key validators sign heartbeats with
+        type AuthorityId: Member + Parameter + RuntimeAppPublic + Ord + MaxEncodedLen;
+
+        /// Priority of heartbeat transactions
+        #[pallet::constant]
+        type UnsignedPriority: Get<TransactionPriority>;
+
+        /// Weight of the previous average when a session score is folded in
+        #[pallet::constant]
+        type ScoreDecay: Get<Perbill>;
+
+        /// Session scores kept per validator
+        #[pallet::constant]
+        type MaxScoreHistory: Get<u32>;
+
+        /// Longest jail, however often a validator was jailed before
+        #[pallet::constant]
+        type MaxJailDuration: Get<Self::BlockNumber>;
+
+        /// Max length of a stored jail reason
+        #[pallet::constant]
+        type MaxReasonLength: Get<u32>;
+
+        /// Max jails expiring in the same block
+        #[pallet::constant]
+        type MaxJailExpiriesPerBlock: Get<u32>;
+
+        /// Max validators slashed for the same offence in a session
+        #[pallet::constant]
+        type MaxOffendersPerSession: Get<u32>;
+
+        /// Max standby validators kept per parachain
+        #[pallet::constant]
+        type MaxStandby: Get<u32>;
+
+        /// Handler for validators that sent no heartbeat during a session
+        type ReportUnresponsiveness: ReportOffence<
+            Self::AccountId,
+            Self::ValidatorId,
+            UnresponsivenessOffence<Self::ValidatorId>,
+        >;
+    }
+
+    /// Coordination configuration of each parachain
+    #[pallet::storage]
+    pub type CoordinationConfigs<T: Config> = CountedStorageMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        CoordinationConfigOf<T>,
+    >;
+
+    /// Rotations in their handover window, by parachain
+    #[pallet::storage]
+    pub type PendingRotations<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        PendingRotation<T>,
+    >;
+
+    /// Parachains whose rotation is due but has not started yet, oldest first
+    #[pallet::storage]
+    pub type RotationQueue<T: Config> = StorageValue<_, BoundedVec<u32, T::MaxParachains>, ValueQuery>;
+
+    /// Heartbeat key of each validator
+    #[pallet::storage]
+    pub type HeartbeatKeys<T: Config> = StorageMap<_, Blake2_128Concat, T::ValidatorId, T::AuthorityId>;
+
+    /// Validator owning each heartbeat key
+    #[pallet::storage]
+    pub type HeartbeatAuthorities<T: Config> = StorageMap<_, Blake2_128Concat, T::AuthorityId, T::ValidatorId>;
+
+    /// Heartbeats received in each session
+    #[pallet::storage]
+    pub type ReceivedHeartbeats<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        u32, // Session index
+        Blake2_128Concat,
+        T::ValidatorId,
+        (),
+    >;
+
+    /// Blocks authored by each validator in each session
+    #[pallet::storage]
+    pub type AuthoredBlocks<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        u32, // Session index
+        Blake2_128Concat,
+        T::ValidatorId,
+        u32,
+        ValueQuery,
+    >;
+
+    /// Sessions each validator was online in
+    #[pallet::storage]
+    pub type Liveness<T: Config> = StorageMap<_, Blake2_128Concat, T::ValidatorId, LivenessRecord, ValueQuery>;
+
+    /// Score average and latest session scores of each validator
+    #[pallet::storage]
+    pub type ScoreHistory<T: Config> = StorageMap<_, Blake2_128Concat, T::ValidatorId, ScoreRecordOf<T>, ValueQuery>;
+
+    /// Currently jailed validators
+    #[pallet::storage]
+    pub type Jails<T: Config> = StorageMap<_, Blake2_128Concat, T::ValidatorId, JailInfo<T>>;
+
+    /// Number of times each validator was jailed
+    #[pallet::storage]
+    pub type JailCount<T: Config> = StorageMap<_, Blake2_128Concat, T::ValidatorId, u32, ValueQuery>;
+
+    /// Jails expiring at each block
+    #[pallet::storage]
+    pub type JailExpiries<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        BoundedVec<T::ValidatorId, T::MaxJailExpiriesPerBlock>,
+        ValueQuery,
+    >;
+
+    /// Slash fraction curve of each offence, offences without one are not slashed
+    #[pallet::storage]
+    pub type SlashCurves<T: Config> = StorageMap<_, Twox64Concat, Offence, SlashCurve, ValueQuery>;
+
+    /// Fraction of stake each offender was slashed for an offence in a session
+    #[pallet::storage]
+    pub type SessionOffences<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        (u32, Offence), // Session index, offence
+        Blake2_128Concat,
+        T::ValidatorId,
+        Perbill,
+    >;
+
+    /// Number of validators that committed an offence in a session
+    #[pallet::storage]
+    pub type SessionOffenderCount<T: Config> = StorageMap<_, Twox64Concat, (u32, Offence), u32, ValueQuery>;
+
+    /// Diversity tags declared by each validator
+    #[pallet::storage]
+    pub type ValidatorTags<T: Config> = StorageMap<_, Blake2_128Concat, T::ValidatorId, DiversityTagsOf<T>>;
+
+    /// Diversity caps of each parachain
+    #[pallet::storage]
+    pub type ParachainDiversityCaps<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        DiversityCaps,
+        ValueQuery,
+    >;
+
+    /// Ranked standby validators of each parachain, best first
+    #[pallet::storage]
+    pub type StandbyPools<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u32, // Parachain ID
+        BoundedVec<T::ValidatorId, T::MaxStandby>,
+        ValueQuery,
+    >;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// A parachain's coordination configuration was set or changed
+        ConfigUpdated {
+            parachain_id: u32,
+            diff: ConfigDiff<T::BlockNumber>,
+        },
+
+        /// A parachain's validator set was reselected
+        RotationStarted {
+            parachain_id: u32,
+            added: Vec<T::ValidatorId>,
+            removed: Vec<T::ValidatorId>,
+            handover_ends: T::BlockNumber,
+        },
+
+        /// Outgoing validators left the set after their handover
+        RotationCompleted {
+            parachain_id: u32,
+            added: Vec<T::ValidatorId>,
+            removed: Vec<T::ValidatorId>,
+        },
+
+        /// Too few eligible validators to rotate, the current set was kept
+        RotationSkipped {
+            parachain_id: u32,
+            available: u32,
+        },
+
+        /// A validator set its heartbeat key
+        HeartbeatKeySet {
+            validator: T::ValidatorId,
+            key: T::AuthorityId,
+        },
+
+        /// A heartbeat was received
+        HeartbeatReceived {
+            validator: T::ValidatorId,
+            session_index: u32,
+        },
+
+        /// Selected validators sent no heartbeat during a session
+        ValidatorsOffline {
+            session_index: u32,
+            validators: Vec<T::ValidatorId>,
+        },
+
+        /// A validator was jailed and removed from the sets of `parachains`
+        ValidatorJailed {
+            validator: T::ValidatorId,
+            until: T::BlockNumber,
+            reason: Vec<u8>,
+            parachains: Vec<u32>,
+        },
+
+        /// A jail expired but the validator's stake is below the minimum
+        JailExpired {
+            validator: T::ValidatorId,
+        },
+
+        /// A validator was released from jail
+        ValidatorReleased {
+            validator: T::ValidatorId,
+        },
+
+        /// The slash fraction curve of an offence was set
+        SlashCurveSet {
+            offence: Offence,
+            curve: SlashCurve,
+        },
+
+        /// A validator declared its diversity tags
+        DiversityTagsSet {
+            validator: T::ValidatorId,
+            tags: DiversityTagsOf<T>,
+        },
+
+        /// A parachain's diversity caps were set
+        DiversityCapsSet {
+            parachain_id: u32,
+            caps: DiversityCaps,
+        },
+
+        /// Diversity caps kept a parachain's set below its target size
+        DiversityShortfall {
+            parachain_id: u32,
+            selected: u32,
+            target: u32,
+        },
+
+        /// A standby validator replaced one that left a parachain's set
+        StandbyPromoted {
+            parachain_id: u32,
+            validator: T::ValidatorId,
+            replaced: T::ValidatorId,
+        },
+
+        /// No standby could keep a parachain's set at its minimum size
+        BelowMinimum {
+            parachain_id: u32,
+            validators: u32,
+        },
+
+        /// A validator exited and left the sets of `parachains`
+        ValidatorExited {
+            validator: T::ValidatorId,
+            parachains: Vec<u32>,
+        },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// Performance, stake and history weights do not sum to 10000
+        InvalidWeights,
+
+        /// Minimum uptime is above 10000
+        InvalidUptime,
+
+        /// Set sizes do not satisfy `min_validators <= target_validators <= max_validators`
+        InvalidSetSize,
+
+        /// Rotation frequency is zero
+        ZeroRotationFrequency,
+
+        /// Maximum set size exceeds the validator set bound
+        TooManyValidators,
+
+        /// Too many parachains have a coordination configuration
+        TooManyParachains,
+
+        /// Validator not found
+        ValidatorNotFound,
+
+        /// Caller is not the validator's account
+        NotValidatorAccount,
+
+        /// Heartbeat key belongs to another validator
+        KeyInUse,
+
+        /// Heartbeat is not for the current session or its signature is invalid
+        InvalidHeartbeat,
+
+        /// Heartbeat already received this session
+        DuplicateHeartbeat,
+
+        /// Validator is not jailed
+        NotJailed,
+
+        /// Jail has not expired yet
+        JailNotExpired,
+
+        /// Validator's stake is below the minimum
+        InsufficientStake,
+
+        /// Too many jails expire around the same block
+        TooManyJailExpiries,
+
+        /// Slash curve base is above its max
+        InvalidSlashCurve,
+
+        /// Too many validators were slashed for the offence this session
+        TooManyOffenders,
+
+        /// Validator is jailed
+        ValidatorJailed,
+    }
+
+    impl<T> From<ConfigError> for Error<T> {
+        fn from(error: ConfigError) -> Self {
+            match error {
+                ConfigError::InvalidWeights => Error::InvalidWeights,
+                ConfigError::InvalidUptime => Error::InvalidUptime,
+                ConfigError::InvalidSetSize => Error::InvalidSetSize,
+                ConfigError::ZeroRotationFrequency => Error::ZeroRotationFrequency,
+            }
+        }
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            let mut budget = T::MaxRotationsPerBlock::get();
+            let mut queue = RotationQueue::<T>::get();
+            let queued = queue.len();
+            let mut weight = Self::expire_jails(now);
+            weight = weight.saturating_add(T::DbWeight::get().reads(2));
+
+            for (parachain_id, config) in CoordinationConfigs::<T>::iter() {
+                weight = weight.saturating_add(T::DbWeight::get().reads(2));
+
+                let handover_over =
+                    PendingRotations::<T>::get(parachain_id).map_or(false, |pending| pending.completes_at <= now);
+                if handover_over && budget > 0 {
+                    let _ = Self::complete_rotation(parachain_id);
+                    weight = weight.saturating_add(Self::set_update_weight());
+                    budget -= 1;
+                }
+
+                if (now % config.rotation_frequency).is_zero() && !queue.contains(&parachain_id) {
+                    // Holds every configured parachain at most once, so there is room
+                    let _ = queue.try_push(parachain_id);
+                }
+            }
+
+            let mut candidates = None;
+            let mut rotated = 0;
+            while budget > 0 && rotated < queue.len() {
+                let parachain_id = queue[rotated];
+                rotated += 1;
+                let Some(config) = CoordinationConfigs::<T>::get(parachain_id) else { continue };
+                weight = weight.saturating_add(T::DbWeight::get().reads(1));
+
+                // Rotations don't change who can be selected, so one scan serves the whole block
+                let candidates = candidates.get_or_insert_with(|| {
+                    let (candidates, scanned) = Self::scan_candidates();
+                    weight = weight.saturating_add(Self::candidates_weight(scanned));
+                    candidates
+                });
+                let used = match Self::rotate_with(parachain_id, &config, candidates) {
+                    Ok(info) => info.actual_weight,
+                    Err(e) => e.post_info.actual_weight,
+                };
+                weight = weight.saturating_add(used.unwrap_or_else(Self::set_update_weight));
+                budget -= 1;
+            }
+
+            if rotated > 0 || queue.len() != queued {
+                queue = BoundedVec::truncate_from(queue.into_inner().split_off(rotated));
+                RotationQueue::<T>::put(queue);
+                weight = weight.saturating_add(T::DbWeight::get().writes(1));
+            }
+            weight
+        }
+
+        fn offchain_worker(now: BlockNumberFor<T>) {
+            if !sp_io::offchain::is_validator() {
+                return;
+            }
+            let Some(session) = pallet_eigen::CurrentSession::<T>::get() else { return };
+
+            // Heartbeats are sent from the middle of the session on, so that
+            // a validator that went offline early in the session is not counted
+            let half = (session.end.saturating_sub(session.start)) / 2u32.into();
+            if now < session.start.saturating_add(half) {
+                return;
+            }
+
+            Self::send_heartbeats(now, session.index);
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Set the coordination configuration of a parachain
+        ///
+        /// The first rotation happens at the next multiple of the rotation
+        /// frequency. Emits `ConfigUpdated` with the fields that changed,
+        /// even if none did.
+        #[pallet::call_index(0)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 2))]
+        pub fn set_coordination_config(
+            origin: OriginFor<T>,
+            parachain_id: u32,
+            config: CoordinationConfigOf<T>,
+        ) -> DispatchResult {
+            T::ConfigOrigin::ensure_origin(origin)?;
+
+            config.validate().map_err(Error::<T>::from)?;
+            ensure!(
+                config.max_validators <= <T as pallet_eigen::Config>::MaxValidatorsPerSet::get(),
+                Error::<T>::TooManyValidators
+            );
+
+            let old = CoordinationConfigs::<T>::get(parachain_id);
+            ensure!(
+                old.is_some() || CoordinationConfigs::<T>::count() < T::MaxParachains::get(),
+                Error::<T>::TooManyParachains
+            );
+            let diff = ConfigDiff::between(old.as_ref(), &config);
+            CoordinationConfigs::<T>::insert(parachain_id, config);
+
+            Self::deposit_event(Event::ConfigUpdated { parachain_id, diff });
+            Ok(())
+        }
+
+        /// Set the key a validator signs heartbeats with
+        ///
+        /// Must be called from the validator's account.
+        #[pallet::call_index(1)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3))]
+        pub fn set_heartbeat_key(
+            origin: OriginFor<T>,
+            validator: T::ValidatorId,
+            key: T::AuthorityId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let profile = pallet_eigen::Validators::<T>::get(validator).ok_or(Error::<T>::ValidatorNotFound)?;
+            ensure!(profile.account == who, Error::<T>::NotValidatorAccount);
+            ensure!(
+                HeartbeatAuthorities::<T>::get(&key).map_or(true, |owner| owner == validator),
+                Error::<T>::KeyInUse
+            );
+
+            if let Some(old) = HeartbeatKeys::<T>::get(validator) {
+                HeartbeatAuthorities::<T>::remove(old);
+            }
+            HeartbeatKeys::<T>::insert(validator, key.clone());
+            HeartbeatAuthorities::<T>::insert(key.clone(), validator);
+
+            Self::deposit_event(Event::HeartbeatKeySet { validator, key });
+            Ok(())
+        }
+
+        /// Record a heartbeat
+        ///
+        /// Submitted as an unsigned transaction by the validator's offchain
+        /// worker; the signature is checked in `validate_unsigned`.
+        #[pallet::call_index(2)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 1))]
+        pub fn heartbeat(
+            origin: OriginFor<T>,
+            heartbeat: HeartbeatOf<T>,
+            // Checked in `validate_unsigned`
+            _signature: <T::AuthorityId as RuntimeAppPublic>::Signature,
+        ) -> DispatchResult {
+            ensure_none(origin)?;
+
+            ensure!(
+                heartbeat.session_index == pallet_eigen::Pallet::<T>::current_session_index()
+                    && HeartbeatKeys::<T>::contains_key(heartbeat.validator),
+                Error::<T>::InvalidHeartbeat
+            );
+            ensure!(
+                !ReceivedHeartbeats::<T>::contains_key(heartbeat.session_index, heartbeat.validator),
+                Error::<T>::DuplicateHeartbeat
+            );
+
+            ReceivedHeartbeats::<T>::insert(heartbeat.session_index, heartbeat.validator, ());
+            Self::deposit_event(Event::HeartbeatReceived {
+                validator: heartbeat.validator,
+                session_index: heartbeat.session_index,
+            });
+            Ok(())
+        }
+
+        /// Release a validator whose jail expired after it re-bonded
+        ///
+        /// Must be called from the validator's account, once the jail has
+        /// expired and the validator's stake is back at the minimum.
+        #[pallet::call_index(3)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 2))]
+        pub fn unjail(origin: OriginFor<T>, validator: T::ValidatorId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let profile = pallet_eigen::Validators::<T>::get(validator).ok_or(Error::<T>::ValidatorNotFound)?;
+            ensure!(profile.account == who, Error::<T>::NotValidatorAccount);
+            let jail = Jails::<T>::get(validator).ok_or(Error::<T>::NotJailed)?;
+            ensure!(jail.until <= frame_system::Pallet::<T>::block_number(), Error::<T>::JailNotExpired);
+            ensure!(Self::has_minimum_stake(&validator), Error::<T>::InsufficientStake);
+
+            Self::release_validator(&validator)
+        }
+
+        /// Set the slash fraction curve of an offence
+        #[pallet::call_index(4)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_slash_curve(origin: OriginFor<T>, offence: Offence, curve: SlashCurve) -> DispatchResult {
+            T::ConfigOrigin::ensure_origin(origin)?;
+            ensure!(curve.is_valid(), Error::<T>::InvalidSlashCurve);
+
+            SlashCurves::<T>::insert(offence, curve);
+            Self::deposit_event(Event::SlashCurveSet { offence, curve });
+            Ok(())
+        }
+
+        /// Slash a validator for an offence, and jail it if `jail_duration` is set
+        ///
+        /// The slashed fraction follows the offence's curve, see
+        /// `slash_validator`. The jail duration escalates like any other.
+        /// Only the offenders actually slashed are charged for.
+        #[pallet::call_index(5)]
+        #[pallet::weight(Pallet::<T>::offence_weight(T::MaxOffendersPerSession::get(), true))]
+        pub fn report_offence(
+            origin: OriginFor<T>,
+            validator: T::ValidatorId,
+            offence: Offence,
+            jail_duration: Option<T::BlockNumber>,
+        ) -> DispatchResultWithPostInfo {
+            T::ConfigOrigin::ensure_origin(origin)?;
+
+            let (_, slashed) = Self::slash_validator(&validator, offence)?;
+            if let Some(duration) = jail_duration {
+                Self::jail_validator(&validator, duration, offence.name().to_vec())?;
+            }
+            Ok(Some(Self::offence_weight(slashed, jail_duration.is_some())).into())
+        }
+
+        /// Declare the operator, region and ASN of a validator
+        ///
+        /// Must be called from the validator's account. Takes effect at the
+        /// next rotation.
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_diversity_tags(
+            origin: OriginFor<T>,
+            validator: T::ValidatorId,
+            tags: DiversityTagsOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let profile = pallet_eigen::Validators::<T>::get(validator).ok_or(Error::<T>::ValidatorNotFound)?;
+            ensure!(profile.account == who, Error::<T>::NotValidatorAccount);
+
+            ValidatorTags::<T>::insert(validator, tags.clone());
+            Self::deposit_event(Event::DiversityTagsSet { validator, tags });
+            Ok(())
+        }
+
+        /// Set the diversity caps of a parachain
+        ///
+        /// Takes effect at the next rotation.
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_diversity_caps(origin: OriginFor<T>, parachain_id: u32, caps: DiversityCaps) -> DispatchResult {
+            T::ConfigOrigin::ensure_origin(origin)?;
+
+            ParachainDiversityCaps::<T>::insert(parachain_id, caps);
+            Self::deposit_event(Event::DiversityCapsSet { parachain_id, caps });
+            Ok(())
+        }
+
+        /// Stop validating for good
+        ///
+        /// Must be called from the validator's account. The validator leaves
+        /// every set at once and is replaced from the standby pools.
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 2)
+            .saturating_add(Pallet::<T>::replacement_weight().saturating_mul(T::MaxParachains::get().into())))]
+        pub fn exit(origin: OriginFor<T>, validator: T::ValidatorId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let profile = pallet_eigen::Validators::<T>::get(validator).ok_or(Error::<T>::ValidatorNotFound)?;
+            ensure!(profile.account == who, Error::<T>::NotValidatorAccount);
+            ensure!(!Jails::<T>::contains_key(validator), Error::<T>::ValidatorJailed);
+
+            let parachains = pallet_eigen::Pallet::<T>::remove_from_sets(&validator);
+            pallet_eigen::Pallet::<T>::set_status(&validator, pallet_eigen::ValidatorStatus::Exited)?;
+            Self::deposit_event(Event::ValidatorExited { validator, parachains: parachains.clone() });
+
+            for parachain_id in parachains {
+                Self::replace_from_standby(parachain_id, &validator);
+            }
+            Ok(())
+        }
+    }
+
+    #[pallet::validate_unsigned]
+    impl<T: Config> ValidateUnsigned for Pallet<T> {
+        type Call = Call<T>;
+
+        fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+            let Call::heartbeat { heartbeat, signature } = call else {
+                return InvalidTransaction::Call.into();
+            };
+
+            let Some(session) = pallet_eigen::CurrentSession::<T>::get() else {
+                return InvalidTransaction::Stale.into();
+            };
+            if heartbeat.session_index < session.index {
+                return InvalidTransaction::Stale.into();
+            }
+            if heartbeat.session_index > session.index {
+                return InvalidTransaction::Future.into();
+            }
+            if ReceivedHeartbeats::<T>::contains_key(heartbeat.session_index, heartbeat.validator) {
+                return InvalidTransaction::Stale.into();
+            }
+
+            let Some(key) = HeartbeatKeys::<T>::get(heartbeat.validator) else {
+                return InvalidTransaction::BadSigner.into();
+            };
+            if !key.verify(&heartbeat.encode(), signature) {
+                return InvalidTransaction::BadProof.into();
+            }
+
+            let now = frame_system::Pallet::<T>::block_number();
+            ValidTransaction::with_tag_prefix("ValidatorHeartbeat")
+                .priority(T::UnsignedPriority::get())
+                .and_provides((heartbeat.session_index, heartbeat.validator))
+                .longevity(session.end.saturating_sub(now).unique_saturated_into())
+                .propagate(true)
+                .build()
+        }
+    }
+
+    impl<T: Config> pallet_authorship::EventHandler<T::ValidatorId, T::BlockNumber> for Pallet<T> {
+        fn note_author(author: T::ValidatorId) {
+            let session_index = pallet_eigen::Pallet::<T>::current_session_index();
+            AuthoredBlocks::<T>::mutate(session_index, author, |count| *count = count.saturating_add(1));
+        }
+    }
+
+    impl<T: Config> pallet_eigen::OnSessionEnd for Pallet<T> {
+        /// Fold the session's heartbeats and authored blocks into the
+        /// performance of every validator in a parachain set
+        ///
+        /// Blocks authored in a session count as settled once the following
+        /// session ends, by which point finality has long caught up.
+        fn on_session_end(session_index: u32) -> Weight {
+            let expected = Self::selected_validators();
+            let mut weight = T::DbWeight::get().reads(T::MaxParachains::get().into());
+
+            let session_length: u32 = pallet_eigen::CurrentSession::<T>::get()
+                .map_or(Zero::zero(), |session| session.end.saturating_sub(session.start))
+                .unique_saturated_into();
+            let expected_blocks = session_length / (expected.len() as u32).max(1);
+
+            let previous = session_index.checked_sub(1);
+            let mut offline = Vec::new();
+            for validator in expected.iter() {
+                let online = ReceivedHeartbeats::<T>::contains_key(session_index, validator);
+                let uptime = Liveness::<T>::mutate(validator, |record| {
+                    record.record(online);
+                    record.uptime()
+                });
+                let proposed = AuthoredBlocks::<T>::get(session_index, validator);
+                let settled = previous.map_or(0, |previous| AuthoredBlocks::<T>::get(previous, validator));
+                let _ = Self::update_performance(validator, uptime, proposed.into(), settled.into());
+                Self::update_score(validator, session_index, uptime, proposed, settled, expected_blocks);
+                if !online {
+                    offline.push(*validator);
+                }
+            }
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(7, 4).saturating_mul(expected.len() as u64));
+
+            if let Some(previous) = previous {
+                let removed = AuthoredBlocks::<T>::clear_prefix(previous, u32::MAX, None);
+                weight = weight.saturating_add(T::DbWeight::get().writes(removed.unique.into()));
+            }
+            let removed = ReceivedHeartbeats::<T>::clear_prefix(session_index, u32::MAX, None);
+            weight = weight.saturating_add(T::DbWeight::get().writes(removed.unique.into()));
+            for offence in Offence::ALL {
+                let removed = SessionOffences::<T>::clear_prefix((session_index, offence), u32::MAX, None);
+                SessionOffenderCount::<T>::remove((session_index, offence));
+                weight = weight.saturating_add(T::DbWeight::get().writes(1 + removed.unique as u64));
+            }
+
+            if !offline.is_empty() {
+                Self::deposit_event(Event::ValidatorsOffline { session_index, validators: offline.clone() });
+                let offence = UnresponsivenessOffence {
+                    session_index,
+                    validator_set_count: expected.len() as u32,
+                    offenders: offline,
+                };
+                // An offence already reported for this session is not reported again
+                let _ = T::ReportUnresponsiveness::report_offence(Vec::new(), offence);
+            }
+            weight
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Select validators for a parachain
+        ///
+        /// Candidates are all registered validators that are neither jailed nor
+        /// exited. The result only depends on chain state and `seed`; see
+        /// `selection::rank_candidates` for how each strategy orders candidates
+        /// and `diversity::select_diverse` for how the parachain's diversity
+        /// caps are applied.
+        pub fn select_validators(
+            parachain_id: u32,
+            count: u32,
+            strategy: RotationStrategy,
+            criteria: &SelectionCriteria,
+            seed: [u8; 32],
+        ) -> Vec<T::ValidatorId> {
+            Self::select_from(&Self::candidates(), parachain_id, count, strategy, criteria, seed).0
+        }
+
+        /// Selected validators and the remaining eligible ones, both best first
+        fn select_from(
+            candidates: &[selection::Candidate<T::ValidatorId>],
+            parachain_id: u32,
+            count: u32,
+            strategy: RotationStrategy,
+            criteria: &SelectionCriteria,
+            seed: [u8; 32],
+        ) -> (Vec<T::ValidatorId>, Vec<T::ValidatorId>) {
+            let seed = sp_io::hashing::blake2_256(&(seed, parachain_id).encode());
+            let (ranked, tags): (Vec<T::ValidatorId>, Vec<DiversityTagsOf<T>>) =
+                selection::rank_candidates(candidates, strategy, criteria, seed)
+                    .into_iter()
+                    .filter_map(|id| Self::tags_of(&id).map(|tags| (id, tags)))
+                    .unzip();
+            let chosen = diversity::select_diverse(&tags, count, &ParachainDiversityCaps::<T>::get(parachain_id));
+
+            let mut selected = Vec::with_capacity(chosen.len());
+            let mut runners_up = Vec::new();
+            let mut chosen = chosen.into_iter().peekable();
+            for (index, id) in ranked.into_iter().enumerate() {
+                if chosen.next_if_eq(&index).is_some() {
+                    selected.push(id);
+                } else {
+                    runners_up.push(id);
+                }
+            }
+            (selected, runners_up)
+        }
+
+        /// Declared diversity tags of a validator, or its own account as operator
+        fn tags_of(validator: &T::ValidatorId) -> Option<DiversityTagsOf<T>> {
+            ValidatorTags::<T>::get(validator).or_else(|| {
+                pallet_eigen::Validators::<T>::get(validator)
+                    .map(|profile| DiversityTags { operator: profile.account, region: None, asn: None })
+            })
+        }
+
+        /// Score a validator's session and fold it into its track record
+        ///
+        /// The session's slashes are those received since the previous score.
+        fn update_score(
+            validator: &T::ValidatorId,
+            session_index: u32,
+            uptime: u16,
+            blocks_proposed: u32,
+            blocks_settled: u32,
+            expected_blocks: u32,
+        ) {
+            let Some(mut profile) = pallet_eigen::Validators::<T>::get(validator) else { return };
+            ScoreHistory::<T>::mutate(validator, |record| {
+                let slashes = profile.performance.slashes.saturating_sub(record.slashes_seen);
+                let score = scoring::session_score(&SessionMetrics {
+                    uptime,
+                    blocks_proposed,
+                    blocks_settled,
+                    expected_blocks,
+                    slashes,
+                });
+                record.push(SessionScore { session_index, score }, T::ScoreDecay::get());
+                record.slashes_seen = profile.performance.slashes;
+                profile.performance.score = score;
+            });
+            pallet_eigen::Validators::<T>::insert(validator, profile);
+        }
+
+        /// Draw a selection seed from the configured randomness source
+        pub fn selection_seed(parachain_id: u32) -> [u8; 32] {
+            let (random, _) = T::Randomness::random(&(b"validator_selection", parachain_id).encode());
+            sp_io::hashing::blake2_256(&random.encode())
+        }
+
+        /// Registered validators that can be selected, in storage order
+        ///
+        /// Only the first `MaxCandidates` profiles are read. A validator's
+        /// track record is the moving average of its session scores.
+        /// Validators that have not been scored yet are judged on their
+        /// current score alone.
+        pub fn candidates() -> Vec<selection::Candidate<T::ValidatorId>> {
+            Self::scan_candidates().0
+        }
+
+        /// Candidates and the number of profiles read to find them
+        fn scan_candidates() -> (Vec<selection::Candidate<T::ValidatorId>>, u32) {
+            let mut scanned = 0;
+            let candidates = pallet_eigen::Validators::<T>::iter_values()
+                .take(T::MaxCandidates::get() as usize)
+                .inspect(|_| scanned += 1)
+                .filter(|profile| {
+                    matches!(
+                        profile.status,
+                        pallet_eigen::ValidatorStatus::Active | pallet_eigen::ValidatorStatus::Pending
+                    )
+                })
+                .map(|profile| {
+                    let record = ScoreHistory::<T>::get(profile.id);
+                    selection::Candidate {
+                        id: profile.id,
+                        stake: pallet_eigen::TotalStake::<T>::get(profile.id).unique_saturated_into(),
+                        uptime: profile.performance.uptime,
+                        score: profile.performance.score,
+                        history: if record.history.is_empty() { profile.performance.score } else { record.average },
+                    }
+                })
+                .collect();
+            (candidates, scanned)
+        }
+
+        /// Weight of reading `scanned` profiles with the stake and score history of each
+        fn candidates_weight(scanned: u32) -> Weight {
+            T::DbWeight::get().reads(1 + 3 * scanned as u64)
+        }
+
+        /// Reselect the validator set of a parachain
+        ///
+        /// A rotation still in its handover window is completed first. If
+        /// fewer than `min_validators` candidates are eligible, the current
+        /// set is kept. Otherwise the selected set is pushed to pallet_eigen
+        /// together with the outgoing validators, which stay until the
+        /// handover ends.
+        ///
+        /// Returns the weight consumed.
+        pub fn rotate_validators(
+            parachain_id: u32,
+            config: &CoordinationConfigOf<T>,
+        ) -> DispatchResultWithPostInfo {
+            let (candidates, scanned) = Self::scan_candidates();
+            let mut info = Self::rotate_with(parachain_id, config, &candidates)?;
+            let scan = Self::candidates_weight(scanned);
+            info.actual_weight = info.actual_weight.map(|weight| weight.saturating_add(scan));
+            Ok(info)
+        }
+
+        /// Reselect the validator set of a parachain from `candidates`
+        fn rotate_with(
+            parachain_id: u32,
+            config: &CoordinationConfigOf<T>,
+            candidates: &[selection::Candidate<T::ValidatorId>],
+        ) -> DispatchResultWithPostInfo {
+            let mut weight = T::DbWeight::get().reads(2);
+            if PendingRotations::<T>::contains_key(parachain_id) {
+                Self::complete_rotation(parachain_id)?;
+                weight = weight.saturating_add(Self::set_update_weight());
+            }
+
+            // Diversity caps and the tags of every candidate
+            weight = weight.saturating_add(T::DbWeight::get().reads(1 + 2 * candidates.len() as u64));
+            let (incoming, runners_up) = Self::select_from(
+                candidates,
+                parachain_id,
+                config.target_validators,
+                config.rotation_strategy,
+                &config.selection_criteria,
+                Self::selection_seed(parachain_id),
+            );
+            if (incoming.len() as u32) < config.target_validators && !runners_up.is_empty() {
+                Self::deposit_event(Event::DiversityShortfall {
+                    parachain_id,
+                    selected: incoming.len() as u32,
+                    target: config.target_validators,
+                });
+            }
+            if (incoming.len() as u32) < config.min_validators {
+                Self::deposit_event(Event::RotationSkipped { parachain_id, available: incoming.len() as u32 });
+                return Ok(Some(weight).into());
+            }
+
+            StandbyPools::<T>::insert(parachain_id, BoundedVec::truncate_from(runners_up));
+            weight = weight.saturating_add(T::DbWeight::get().writes(1));
+
+            let current: Vec<T::ValidatorId> =
+                pallet_eigen::ValidatorSets::<T>::get(parachain_id).iter().map(|v| v.id).collect();
+            let added: Vec<T::ValidatorId> = incoming.iter().filter(|id| !current.contains(id)).copied().collect();
+            let removed: Vec<T::ValidatorId> = current.iter().filter(|id| !incoming.contains(id)).copied().collect();
+
+            let now = frame_system::Pallet::<T>::block_number();
+            weight = weight.saturating_add(Self::set_update_weight());
+            if removed.is_empty() || T::HandoverPeriod::get().is_zero() {
+                pallet_eigen::Pallet::<T>::set_validator_set(parachain_id, incoming)?;
+                Self::deposit_event(Event::RotationStarted {
+                    parachain_id,
+                    added: added.clone(),
+                    removed: removed.clone(),
+                    handover_ends: now,
+                });
+                Self::deposit_event(Event::RotationCompleted { parachain_id, added, removed });
+                return Ok(Some(weight).into());
+            }
+
+            // Outgoing validators that do not fit next to the incoming set leave immediately
+            let mut members = incoming.clone();
+            members.extend(removed.iter().copied());
+            members.truncate(<T as pallet_eigen::Config>::MaxValidatorsPerSet::get() as usize);
+            pallet_eigen::Pallet::<T>::set_validator_set(parachain_id, members)?;
+
+            let handover_ends = now.saturating_add(T::HandoverPeriod::get());
+            Self::deposit_event(Event::RotationStarted {
+                parachain_id,
+                added: added.clone(),
+                removed: removed.clone(),
+                handover_ends,
+            });
+            PendingRotations::<T>::insert(
+                parachain_id,
+                PendingRotation {
+                    incoming: BoundedVec::truncate_from(incoming),
+                    added: BoundedVec::truncate_from(added),
+                    removed: BoundedVec::truncate_from(removed),
+                    completes_at: handover_ends,
+                },
+            );
+            Ok(Some(weight.saturating_add(T::DbWeight::get().writes(1))).into())
+        }
+
+        /// End the handover of a parachain's pending rotation
+        pub fn complete_rotation(parachain_id: u32) -> DispatchResult {
+            let Some(pending) = PendingRotations::<T>::take(parachain_id) else { return Ok(()) };
+            pallet_eigen::Pallet::<T>::set_validator_set(parachain_id, pending.incoming.into_inner())?;
+            Self::deposit_event(Event::RotationCompleted {
+                parachain_id,
+                added: pending.added.into_inner(),
+                removed: pending.removed.into_inner(),
+            });
+            Ok(())
+        }
+
+        /// Weight of replacing one validator set in pallet_eigen
+        fn set_update_weight() -> Weight {
+            let bound = <T as pallet_eigen::Config>::MaxValidatorsPerSet::get() as u64;
+            T::DbWeight::get().reads_writes(1 + 4 * bound, 2 + 2 * bound)
+        }
+
+        /// Submit a heartbeat for every local key of a selected validator
+        fn send_heartbeats(now: T::BlockNumber, session_index: u32) {
+            for key in T::AuthorityId::all() {
+                let Some(validator) = HeartbeatAuthorities::<T>::get(&key) else { continue };
+                if ReceivedHeartbeats::<T>::contains_key(session_index, validator) {
+                    continue;
+                }
+
+                // Remember when the last heartbeat was sent, and resend it if it
+                // has not been included after a few blocks
+                let storage_key = (b"validator-coordination::heartbeat", validator).encode();
+                let storage = StorageValueRef::persistent(&storage_key);
+                let due = storage.mutate(|sent: Result<Option<(u32, T::BlockNumber)>, _>| match sent {
+                    Ok(Some((index, at)))
+                        if index == session_index && now < at.saturating_add(HEARTBEAT_RETRY_BLOCKS.into()) =>
+                    {
+                        Err(())
+                    }
+                    _ => Ok((session_index, now)),
+                });
+                if due.is_err() {
+                    continue;
+                }
+
+                let heartbeat = Heartbeat { block_number: now, session_index, validator };
+                let Some(signature) = key.sign(&heartbeat.encode()) else { continue };
+                let call = Call::heartbeat { heartbeat, signature };
+                let _ = SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into());
+            }
+        }
+
+        /// Record a session's metrics for a validator
+        ///
+        /// `uptime` replaces the stored uptime, the block counts are added to
+        /// the running totals.
+        pub fn update_performance(
+            validator: &T::ValidatorId,
+            uptime: u16,
+            blocks_proposed: u64,
+            blocks_settled: u64,
+        ) -> DispatchResult {
+            let now = frame_system::Pallet::<T>::block_number();
+            pallet_eigen::Validators::<T>::try_mutate(validator, |profile| {
+                let profile = profile.as_mut().ok_or(Error::<T>::ValidatorNotFound)?;
+                let performance = &mut profile.performance;
+                performance.uptime = uptime.min(selection::FULL_WEIGHT as u16);
+                performance.blocks_proposed = performance.blocks_proposed.saturating_add(blocks_proposed);
+                performance.blocks_settled = performance.blocks_settled.saturating_add(blocks_settled);
+                profile.last_updated = now;
+                Ok(())
+            })
+        }
+
+        /// Validators in at least one parachain's set
+        pub fn selected_validators() -> Vec<T::ValidatorId> {
+            let mut selected = Vec::new();
+            for set in pallet_eigen::ValidatorSets::<T>::iter_values() {
+                for member in set.iter() {
+                    if !selected.contains(&member.id) {
+                        selected.push(member.id);
+                    }
+                }
+            }
+            selected
+        }
+
+        /// Slash a validator for an offence in the current session
+        ///
+        /// The fraction follows the offence's curve for the number of
+        /// validators that committed it this session, out of all selected
+        /// validators. Earlier offenders of the session are slashed again up
+        /// to the new fraction. A repeat report of the same offence within a
+        /// session only tops up. At most `MaxOffendersPerSession` validators
+        /// are slashed for the same offence in a session.
+        ///
+        /// Returns the amount slashed from `validator` and the number of
+        /// offenders slashed.
+        pub fn slash_validator(
+            validator: &T::ValidatorId,
+            offence: Offence,
+        ) -> Result<(BalanceOf<T>, u32), DispatchError> {
+            ensure!(pallet_eigen::Validators::<T>::contains_key(validator), Error::<T>::ValidatorNotFound);
+
+            let key = (pallet_eigen::Pallet::<T>::current_session_index(), offence);
+            if !SessionOffences::<T>::contains_key(key, validator) {
+                ensure!(
+                    SessionOffenderCount::<T>::get(key) < T::MaxOffendersPerSession::get(),
+                    Error::<T>::TooManyOffenders
+                );
+                SessionOffences::<T>::insert(key, validator, Perbill::zero());
+                SessionOffenderCount::<T>::mutate(key, |count| *count = count.saturating_add(1));
+                pallet_eigen::Pallet::<T>::note_slash(validator);
+            }
+
+            let mut validators = Self::selected_validators();
+            if !validators.contains(validator) {
+                validators.push(*validator);
+            }
+            let target = SlashCurves::<T>::get(offence)
+                .fraction(SessionOffenderCount::<T>::get(key), validators.len() as u32);
+
+            let mut slashed = Zero::zero();
+            let mut count = 0;
+            let offenders: Vec<(T::ValidatorId, Perbill)> = SessionOffences::<T>::iter_prefix(key).collect();
+            for (offender, applied) in offenders {
+                let fraction = slashing::top_up(applied, target);
+                if fraction.is_zero() {
+                    continue;
+                }
+                let amount = pallet_eigen::Pallet::<T>::slash_stake(&offender, fraction, offence);
+                SessionOffences::<T>::insert(key, offender, target);
+                count += 1;
+                if offender == *validator {
+                    slashed = amount;
+                }
+            }
+            Ok((slashed, count))
+        }
+
+        /// Weight of reporting an offence that slashed `slashed` offenders, and jailed if `jailed`
+        ///
+        /// Reading the selected validators and the session's offenders is
+        /// bounded by `MaxParachains` and `MaxOffendersPerSession`; removing
+        /// a jailed validator from its sets, and replacing it from each
+        /// standby pool, by `MaxParachains`.
+        pub fn offence_weight(slashed: u32, jailed: bool) -> Weight {
+            let parachains: u64 = T::MaxParachains::get().into();
+            let mut weight = T::DbWeight::get()
+                .reads_writes(6 + parachains + T::MaxOffendersPerSession::get() as u64, 3)
+                .saturating_add(
+                    pallet_eigen::Pallet::<T>::slash_stake_weight()
+                        .saturating_add(T::DbWeight::get().writes(1))
+                        .saturating_mul(slashed.into()),
+                );
+            if jailed {
+                let expiries: u64 = T::MaxJailExpiriesPerBlock::get().into();
+                let jail = T::DbWeight::get().reads_writes(4 + expiries + parachains, 5 + parachains);
+                let replacements = Self::replacement_weight().saturating_mul(parachains);
+                weight = weight.saturating_add(jail).saturating_add(replacements);
+            }
+            weight
+        }
+
+        /// Jail a validator and remove it from every validator set
+        ///
+        /// `duration` doubles with every previous jailing of the validator, up
+        /// to `MaxJailDuration`. Jailing a validator that is already jailed
+        /// extends its jail if the new one lasts longer.
+        pub fn jail_validator(
+            validator: &T::ValidatorId,
+            duration: T::BlockNumber,
+            reason: Vec<u8>,
+        ) -> DispatchResult {
+            let profile = pallet_eigen::Validators::<T>::get(validator).ok_or(Error::<T>::ValidatorNotFound)?;
+
+            let count = JailCount::<T>::get(validator);
+            let escalation = 1u32.checked_shl(count).unwrap_or(u32::MAX);
+            let duration = duration.saturating_mul(escalation.into()).min(T::MaxJailDuration::get());
+            let mut until = frame_system::Pallet::<T>::block_number().saturating_add(duration);
+
+            let previous = Jails::<T>::get(validator);
+            match &previous {
+                Some(previous) if previous.until >= until => until = previous.until,
+                _ => {
+                    until = Self::schedule_expiry(validator, until)?;
+                    if let Some(previous) = &previous {
+                        JailExpiries::<T>::mutate(previous.until, |expiring| expiring.retain(|id| id != validator));
+                    }
+                }
+            }
+
+            let parachains = pallet_eigen::Pallet::<T>::remove_from_sets(validator);
+            pallet_eigen::Pallet::<T>::set_status(validator, pallet_eigen::ValidatorStatus::Jailed)?;
+            Jails::<T>::insert(
+                validator,
+                JailInfo {
+                    reason: BoundedVec::truncate_from(reason.clone()),
+                    until,
+                    previous_status: previous.map_or(profile.status, |previous| previous.previous_status),
+                },
+            );
+            JailCount::<T>::insert(validator, count.saturating_add(1));
+
+            Self::deposit_event(Event::ValidatorJailed {
+                validator: *validator,
+                until,
+                reason,
+                parachains: parachains.clone(),
+            });
+
+            for parachain_id in parachains {
+                Self::replace_from_standby(parachain_id, validator);
+            }
+            Ok(())
+        }
+
+        /// Fill the seat `departed` left in a parachain's set from its standby pool
+        ///
+        /// Standbys that can no longer be selected are dropped from the pool.
+        /// During a handover only the incoming validators count towards the
+        /// target size and the diversity caps, and `departed` leaves the
+        /// pending rotation's incoming set. The best remaining standby that
+        /// fits under the parachain's caps joins the set, and the pending
+        /// rotation if there is one, unless the set is already at its target
+        /// size. An outgoing validator leaves early if the set is full.
+        pub fn replace_from_standby(parachain_id: u32, departed: &T::ValidatorId) {
+            let Some(config) = CoordinationConfigs::<T>::get(parachain_id) else { return };
+            let members: Vec<T::ValidatorId> =
+                pallet_eigen::ValidatorSets::<T>::get(parachain_id).iter().map(|v| v.id).collect();
+
+            let mut pending = PendingRotations::<T>::get(parachain_id);
+            if let Some(pending) = &mut pending {
+                pending.incoming.retain(|id| id != departed);
+            }
+            // Validators that remain once any handover is over
+            let mut staying: Vec<T::ValidatorId> =
+                pending.as_ref().map_or_else(|| members.clone(), |pending| pending.incoming.to_vec());
+
+            let mut pool = StandbyPools::<T>::get(parachain_id);
+            pool.retain(|id| {
+                pallet_eigen::Validators::<T>::get(id).map_or(false, |profile| {
+                    matches!(
+                        profile.status,
+                        pallet_eigen::ValidatorStatus::Active | pallet_eigen::ValidatorStatus::Pending
+                    )
+                })
+            });
+
+            if (staying.len() as u32) < config.target_validators {
+                let caps = ParachainDiversityCaps::<T>::get(parachain_id);
+                let staying_tags: Vec<DiversityTagsOf<T>> = staying.iter().filter_map(Self::tags_of).collect();
+                let position = pool.iter().position(|id| {
+                    !members.contains(id)
+                        && Self::tags_of(id).map_or(false, |tags| diversity::fits(&staying_tags, &tags, &caps))
+                });
+
+                if let Some(position) = position {
+                    let promoted = pool[position];
+                    let mut next = members;
+                    if next.len() >= <T as pallet_eigen::Config>::MaxValidatorsPerSet::get() as usize {
+                        if let Some(outgoing) = next.iter().rposition(|id| !staying.contains(id)) {
+                            next.remove(outgoing);
+                        }
+                    }
+                    next.push(promoted);
+                    if pallet_eigen::Pallet::<T>::set_validator_set(parachain_id, next).is_ok() {
+                        pool.remove(position);
+                        staying.push(promoted);
+                        if let Some(pending) = &mut pending {
+                            // `departed` was removed from `incoming` above, so there is room
+                            let _ = pending.incoming.try_push(promoted);
+                        }
+                        Self::deposit_event(Event::StandbyPromoted {
+                            parachain_id,
+                            validator: promoted,
+                            replaced: *departed,
+                        });
+                    }
+                }
+            }
+            StandbyPools::<T>::insert(parachain_id, pool);
+            if let Some(pending) = pending {
+                PendingRotations::<T>::insert(parachain_id, pending);
+            }
+
+            if (staying.len() as u32) < config.min_validators {
+                Self::deposit_event(Event::BelowMinimum { parachain_id, validators: staying.len() as u32 });
+            }
+        }
+
+        /// Weight of `replace_from_standby` for one parachain
+        pub(crate) fn replacement_weight() -> Weight {
+            let standby = T::MaxStandby::get() as u64;
+            let bound = <T as pallet_eigen::Config>::MaxValidatorsPerSet::get() as u64;
+            T::DbWeight::get()
+                .reads_writes(4 + 3 * standby + 2 * bound, 2)
+                .saturating_add(Self::set_update_weight())
+        }
+
+        /// Release a validator from jail, restoring its status from before the jail
+        ///
+        /// The validator rejoins validator sets at the next rotation.
+        pub fn release_validator(validator: &T::ValidatorId) -> DispatchResult {
+            let jail = Jails::<T>::take(validator).ok_or(Error::<T>::NotJailed)?;
+            JailExpiries::<T>::mutate(jail.until, |expiring| expiring.retain(|id| id != validator));
+            pallet_eigen::Pallet::<T>::set_status(validator, jail.previous_status)?;
+            Self::deposit_event(Event::ValidatorReleased { validator: *validator });
+            Ok(())
+        }
+
+        /// Index a jail expiry at the first block from `until` with room left
+        fn schedule_expiry(validator: &T::ValidatorId, until: T::BlockNumber) -> Result<T::BlockNumber, DispatchError> {
+            let mut at = until;
+            for _ in 0..T::MaxJailExpiriesPerBlock::get().max(1) {
+                if JailExpiries::<T>::mutate(at, |expiring| expiring.try_push(*validator)).is_ok() {
+                    return Ok(at);
+                }
+                at = at.saturating_add(One::one());
+            }
+            Err(Error::<T>::TooManyJailExpiries.into())
+        }
+
+        /// Release validators whose jail expires at `now`, if their stake allows
+        fn expire_jails(now: T::BlockNumber) -> Weight {
+            let expiring = JailExpiries::<T>::take(now);
+            for validator in expiring.iter() {
+                if Self::has_minimum_stake(validator) {
+                    let _ = Self::release_validator(validator);
+                } else {
+                    Self::deposit_event(Event::JailExpired { validator: *validator });
+                }
+            }
+            T::DbWeight::get()
+                .reads_writes(1, 1)
+                .saturating_add(T::DbWeight::get().reads_writes(4, 3).saturating_mul(expiring.len() as u64))
+        }
+
+        /// Whether a validator's total stake is at least pallet_eigen's minimum
+        fn has_minimum_stake(validator: &T::ValidatorId) -> bool {
+            pallet_eigen::TotalStake::<T>::get(validator) >= <T as pallet_eigen::Config>::MinStakeAmount::get()
+        }
+    }
 }
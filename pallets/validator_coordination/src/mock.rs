@@ -0,0 +1,231 @@
+//! Mock runtime for validator coordination tests
+
+use crate as pallet_validator_coordination;
+use crate::heartbeat::UnresponsivenessOffence;
+use frame_support::{
+    construct_runtime, parameter_types,
+    traits::{ConstU128, ConstU32, ConstU64, Currency as _, Everything, Hooks, Randomness},
+    weights::constants::RocksDbWeight,
+};
+use frame_system::{offchain::SendTransactionTypes, EnsureRoot};
+use pallet_eigen::{ExistenceRequirement, ValidatorPerformance, ValidatorProfile, ValidatorStatus};
+use sp_core::H256;
+use sp_runtime::{
+    testing::{Header, UintAuthorityId},
+    traits::{BlakeTwo256, Hash, IdentityLookup},
+    DispatchResult, Perbill,
+};
+use sp_staking::{
+    offence::{Offence, OffenceError, ReportOffence},
+    SessionIndex,
+};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type BlockNumber = u64;
+
+pub const PARA_A: u32 = 2000;
+pub const PARA_B: u32 = 2001;
+pub const PARA_C: u32 = 2002;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Eigen: pallet_eigen,
+        ValidatorCoordination: pallet_validator_coordination,
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = RocksDbWeight;
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = BlockNumber;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type WeightInfo = ();
+    type Balance = Balance;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type ReserveIdentifier = [u8; 8];
+    type HoldIdentifier = ();
+    type FreezeIdentifier = ();
+    type MaxLocks = ConstU32<10>;
+    type MaxReserves = ConstU32<10>;
+    type MaxHolds = ConstU32<0>;
+    type MaxFreezes = ConstU32<0>;
+}
+
+/// pallet_eigen's currency backed by `Balances`
+pub struct EigenCurrency;
+
+impl pallet_eigen::Currency<AccountId> for EigenCurrency {
+    type Balance = Balance;
+
+    fn free_balance(who: &AccountId) -> Balance {
+        Balances::free_balance(who)
+    }
+
+    fn transfer(
+        source: &AccountId,
+        dest: &AccountId,
+        value: Balance,
+        existence_requirement: ExistenceRequirement,
+    ) -> DispatchResult {
+        let existence_requirement = match existence_requirement {
+            ExistenceRequirement::KeepAlive => frame_support::traits::ExistenceRequirement::KeepAlive,
+            ExistenceRequirement::AllowDeath => frame_support::traits::ExistenceRequirement::AllowDeath,
+        };
+        <Balances as frame_support::traits::Currency<AccountId>>::transfer(source, dest, value, existence_requirement)
+    }
+
+    fn slash(who: &AccountId, value: Balance) -> Balance {
+        <Balances as frame_support::traits::Currency<AccountId>>::slash(who, value).1
+    }
+}
+
+impl pallet_eigen::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type ValidatorId = AccountId;
+    type Currency = EigenCurrency;
+    type MaxValidatorsPerSet = ConstU32<8>;
+    type MinStakeAmount = ConstU128<100>;
+    type SessionDuration = ConstU64<10>;
+    type MaxNominatorsPerValidator = ConstU32<4>;
+    type OnSessionEnd = ValidatorCoordination;
+}
+
+impl<C> SendTransactionTypes<C> for Test
+where
+    RuntimeCall: From<C>,
+{
+    type OverarchingCall = RuntimeCall;
+    type Extrinsic = UncheckedExtrinsic;
+}
+
+parameter_types! {
+    /// Unresponsiveness offences reported so far, with the fraction they would slash
+    pub static Offences: Vec<(UnresponsivenessOffence<AccountId>, Perbill)> = vec![];
+    pub const ScoreDecay: Perbill = Perbill::from_percent(80);
+}
+
+/// Records reported offences, once per session
+pub struct OffenceHandler;
+
+impl ReportOffence<AccountId, AccountId, UnresponsivenessOffence<AccountId>> for OffenceHandler {
+    fn report_offence(
+        _reporters: Vec<AccountId>,
+        offence: UnresponsivenessOffence<AccountId>,
+    ) -> Result<(), OffenceError> {
+        if Self::is_known_offence(&offence.offenders, &offence.session_index) {
+            return Err(OffenceError::DuplicateReport);
+        }
+        let fraction = offence.slash_fraction(offence.offenders.len() as u32);
+        Offences::mutate(|offences| offences.push((offence, fraction)));
+        Ok(())
+    }
+
+    fn is_known_offence(_offenders: &[AccountId], time_slot: &SessionIndex) -> bool {
+        Offences::get().iter().any(|(offence, _)| offence.session_index == *time_slot)
+    }
+}
+
+/// Randomness derived from the subject and the current block
+pub struct TestRandomness;
+
+impl Randomness<H256, BlockNumber> for TestRandomness {
+    fn random(subject: &[u8]) -> (H256, BlockNumber) {
+        let now = System::block_number();
+        (BlakeTwo256::hash_of(&(subject, now)), now)
+    }
+}
+
+impl pallet_validator_coordination::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type ConfigOrigin = EnsureRoot<AccountId>;
+    type Randomness = TestRandomness;
+    type MaxParachains = ConstU32<4>;
+    type HandoverPeriod = ConstU64<3>;
+    type MaxCandidates = ConstU32<16>;
+    type MaxRotationsPerBlock = ConstU32<2>;
+    type AuthorityId = UintAuthorityId;
+    type UnsignedPriority = ConstU64<100>;
+    type ScoreDecay = ScoreDecay;
+    type MaxScoreHistory = ConstU32<3>;
+    type MaxJailDuration = ConstU64<40>;
+    type MaxReasonLength = ConstU32<8>;
+    type MaxJailExpiriesPerBlock = ConstU32<2>;
+    type MaxOffendersPerSession = ConstU32<4>;
+    type MaxStandby = ConstU32<3>;
+    type ReportUnresponsiveness = OffenceHandler;
+}
+
+/// Register validator `id`, bonded by its own account, with the given stake, uptime and score
+///
+/// The account is funded with the stake.
+pub fn register_validator(id: AccountId, stake: Balance, uptime: u16, score: u16) {
+    pallet_eigen::Validators::<Test>::insert(
+        id,
+        ValidatorProfile {
+            id,
+            account: id,
+            commission_rate: 0,
+            active_parachains: Default::default(),
+            performance: ValidatorPerformance { uptime, blocks_proposed: 0, blocks_settled: 0, slashes: 0, score },
+            status: ValidatorStatus::Active,
+            joined_at: 0,
+            last_updated: 0,
+        },
+    );
+    pallet_eigen::TotalStake::<Test>::insert(id, stake);
+    Balances::make_free_balance_be(&id, stake);
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
+
+/// Run `on_initialize` for every block up to `n`
+///
+/// Sessions last ten blocks, the first one starts at block 2.
+pub fn run_to_block(n: BlockNumber) {
+    while System::block_number() < n {
+        System::set_block_number(System::block_number() + 1);
+        Eigen::on_initialize(System::block_number());
+        ValidatorCoordination::on_initialize(System::block_number());
+    }
+}
@@ -0,0 +1,136 @@
+//! Validator performance scoring
+//!
+//! At the end of every session each selected validator gets a session score
+//! in basis points (0-10000):
+//!
+//! ```text
+//! base  = 50% * uptime + 30% * proposal_rate + 20% * settlement_rate
+//! score = base / 2^slashes
+//! ```
+//!
+//! where `proposal_rate` and `settlement_rate` are the blocks proposed and
+//! settled in the session relative to the validator's fair share of the
+//! session's blocks, capped at 100%, and `slashes` counts the slashes
+//! received during the session. Settled blocks are those the validator
+//! authored in the previous session, which finality is assumed to have
+//! caught up with. Session scores are folded into an
+//! exponential moving average
+//!
+//! ```text
+//! average = decay * average + (1 - decay) * score
+//! ```
+//!
+//! which selection uses as the validator's track record. The latest session
+//! scores are kept in a ring buffer so that the average can be explained.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+    traits::Get, BoundedVec, CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+};
+use scale_info::TypeInfo;
+use sp_runtime::{PerThing, Perbill, RuntimeDebug};
+
+use crate::selection::FULL_WEIGHT;
+
+/// Weight of uptime in the session score, in basis points
+pub const UPTIME_WEIGHT: u32 = 5_000;
+/// Weight of the proposal rate in the session score, in basis points
+pub const PROPOSAL_WEIGHT: u32 = 3_000;
+/// Weight of the settlement rate in the session score, in basis points
+pub const SETTLEMENT_WEIGHT: u32 = 2_000;
+
+/// What a validator did during a session
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct SessionMetrics {
+    /// Uptime percentage (0-10000, representing 0-100.00%)
+    pub uptime: u16,
+    /// Blocks proposed in the session
+    pub blocks_proposed: u32,
+    /// Blocks settled in the session
+    pub blocks_settled: u32,
+    /// Fair share of the session's blocks
+    pub expected_blocks: u32,
+    /// Slashes received in the session
+    pub slashes: u32,
+}
+
+/// `part / whole` in basis points, capped at 100%
+fn rate(part: u32, whole: u32) -> u32 {
+    if whole == 0 {
+        return FULL_WEIGHT;
+    }
+    ((part as u64 * FULL_WEIGHT as u64 / whole as u64) as u32).min(FULL_WEIGHT)
+}
+
+/// Score of a single session (0-10000)
+pub fn session_score(metrics: &SessionMetrics) -> u16 {
+    let uptime = (metrics.uptime as u32).min(FULL_WEIGHT);
+    let proposal_rate = rate(metrics.blocks_proposed, metrics.expected_blocks);
+    let settlement_rate = rate(metrics.blocks_settled, metrics.expected_blocks);
+    let base = (UPTIME_WEIGHT * uptime + PROPOSAL_WEIGHT * proposal_rate + SETTLEMENT_WEIGHT * settlement_rate)
+        / FULL_WEIGHT;
+    base.checked_shr(metrics.slashes).unwrap_or(0) as u16
+}
+
+/// Fold a session score into the moving average, starting from the first score
+pub fn decayed_average(average: Option<u16>, score: u16, decay: Perbill) -> u16 {
+    match average {
+        Some(average) => (decay * average as u32 + decay.left_from_one() * score as u32) as u16,
+        None => score,
+    }
+}
+
+/// Score of a validator in a session
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct SessionScore {
+    /// Session index
+    pub session_index: u32,
+    /// Session score (0-10000, representing 0-100.00%)
+    pub score: u16,
+}
+
+/// Moving average of a validator's scores and the latest session scores
+#[derive(Encode, Decode, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[codec(mel_bound(Capacity: Get<u32>))]
+#[scale_info(skip_type_params(Capacity))]
+pub struct ScoreRecord<Capacity: Get<u32>> {
+    /// Exponential moving average of the session scores
+    pub average: u16,
+    /// Latest session scores, oldest overwritten first
+    pub history: BoundedVec<SessionScore, Capacity>,
+    /// Position of the next write once `history` is full
+    pub cursor: u32,
+    /// Slashes counted by the last score, to find those of the next session
+    pub slashes_seen: u32,
+}
+
+impl<Capacity: Get<u32>> ScoreRecord<Capacity> {
+    /// Record a session score and update the moving average
+    pub fn push(&mut self, entry: SessionScore, decay: Perbill) {
+        let average = if self.history.is_empty() { None } else { Some(self.average) };
+        self.average = decayed_average(average, entry.score, decay);
+
+        if self.history.try_push(entry).is_err() {
+            let len = self.history.len() as u32;
+            if len == 0 {
+                return;
+            }
+            if let Some(slot) = self.history.get_mut((self.cursor % len) as usize) {
+                *slot = entry;
+            }
+            self.cursor = (self.cursor + 1) % len;
+        }
+    }
+
+    /// Session scores from oldest to newest
+    pub fn in_order(&self) -> impl Iterator<Item = &SessionScore> {
+        let (newer, older) = self.history.split_at(self.cursor as usize % self.history.len().max(1));
+        older.iter().chain(newer.iter())
+    }
+}
+
+impl<Capacity: Get<u32>> Default for ScoreRecord<Capacity> {
+    fn default() -> Self {
+        Self { average: 0, history: BoundedVec::default(), cursor: 0, slashes_seen: 0 }
+    }
+}
@@ -0,0 +1,154 @@
+//! Validator selection for every `RotationStrategy`
+//!
+//! Selection is a pure function of the candidates, the criteria and a seed:
+//! the same inputs always produce the same ranking. Candidates below
+//! `min_stake` or `min_uptime` are never selected.
+
+use crate::{RotationStrategy, SelectionCriteria};
+use codec::Encode;
+use sp_std::prelude::*;
+
+/// Basis points representing 100.00%
+pub const FULL_WEIGHT: u32 = 10_000;
+
+/// Validator considered for selection
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Candidate<ValidatorId> {
+    /// Validator ID
+    pub id: ValidatorId,
+    /// Total stake backing the validator
+    pub stake: u128,
+    /// Uptime percentage (0-10000, representing 0-100.00%)
+    pub uptime: u16,
+    /// Performance score (0-10000, representing 0-100.00%)
+    pub score: u16,
+    /// Track record over past sessions (0-10000, representing 0-100.00%)
+    pub history: u16,
+}
+
+/// Deterministic stream of random numbers derived from a seed
+pub struct SeedStream {
+    seed: [u8; 32],
+    counter: u64,
+}
+
+impl SeedStream {
+    /// Create a stream from a seed
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self { seed, counter: 0 }
+    }
+
+    /// Next random number
+    pub fn next_u128(&mut self) -> u128 {
+        let hash = sp_io::hashing::blake2_256(&(self.seed, self.counter).encode());
+        self.counter += 1;
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hash[..16]);
+        u128::from_le_bytes(bytes)
+    }
+
+    /// Next random number below `bound`, which must be positive
+    pub fn next_below(&mut self, bound: u128) -> u128 {
+        self.next_u128() % bound
+    }
+}
+
+/// Whether a candidate meets the minimum stake and uptime
+pub fn is_eligible<ValidatorId>(candidate: &Candidate<ValidatorId>, criteria: &SelectionCriteria) -> bool {
+    candidate.stake >= criteria.min_stake && candidate.uptime >= criteria.min_uptime
+}
+
+/// Rank every eligible candidate, best first
+///
+/// - `Random` is a seeded shuffle that ignores stake.
+/// - `Performance` orders by score.
+/// - `StakeWeighted` draws candidates one by one with probability
+///   proportional to stake, without replacement.
+/// - `Hybrid` orders by the criteria's weighted sum of score, stake
+///   (relative to the largest eligible stake) and history.
+///
+/// Ties are broken by stake and then by the order of `candidates`.
+pub fn rank_candidates<ValidatorId: Clone>(
+    candidates: &[Candidate<ValidatorId>],
+    strategy: RotationStrategy,
+    criteria: &SelectionCriteria,
+    seed: [u8; 32],
+) -> Vec<ValidatorId> {
+    let mut eligible: Vec<&Candidate<ValidatorId>> =
+        candidates.iter().filter(|c| is_eligible(c, criteria)).collect();
+    let mut stream = SeedStream::new(seed);
+
+    match strategy {
+        RotationStrategy::Random => {
+            for i in (1..eligible.len()).rev() {
+                let j = stream.next_below(i as u128 + 1) as usize;
+                eligible.swap(i, j);
+            }
+        }
+        RotationStrategy::Performance => {
+            eligible.sort_by(|a, b| b.score.cmp(&a.score).then(b.stake.cmp(&a.stake)));
+        }
+        RotationStrategy::StakeWeighted => {
+            let mut remaining = eligible;
+            eligible = Vec::with_capacity(remaining.len());
+            while !remaining.is_empty() {
+                let total: u128 = remaining.iter().map(|c| c.stake).fold(0, u128::saturating_add);
+                let index = if total == 0 {
+                    0
+                } else {
+                    let mut target = stream.next_below(total);
+                    remaining
+                        .iter()
+                        .position(|c| {
+                            if target < c.stake {
+                                true
+                            } else {
+                                target -= c.stake;
+                                false
+                            }
+                        })
+                        .unwrap_or(0)
+                };
+                eligible.push(remaining.remove(index));
+            }
+        }
+        RotationStrategy::Hybrid => {
+            let max_stake = eligible.iter().map(|c| c.stake).max().unwrap_or(0);
+            let mut scored: Vec<(u64, &Candidate<ValidatorId>)> =
+                eligible.iter().map(|c| (hybrid_score(c, criteria, max_stake), *c)).collect();
+            scored.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(b.stake.cmp(&a.stake)));
+            eligible = scored.into_iter().map(|(_, c)| c).collect();
+        }
+    }
+
+    eligible.into_iter().map(|c| c.id.clone()).collect()
+}
+
+/// Select up to `count` candidates
+pub fn select<ValidatorId: Clone>(
+    candidates: &[Candidate<ValidatorId>],
+    count: u32,
+    strategy: RotationStrategy,
+    criteria: &SelectionCriteria,
+    seed: [u8; 32],
+) -> Vec<ValidatorId> {
+    let mut ranked = rank_candidates(candidates, strategy, criteria, seed);
+    ranked.truncate(count as usize);
+    ranked
+}
+
+/// Weighted score used by the `Hybrid` strategy, scaled by 10000
+pub fn hybrid_score<ValidatorId>(
+    candidate: &Candidate<ValidatorId>,
+    criteria: &SelectionCriteria,
+    max_stake: u128,
+) -> u64 {
+    let relative_stake = if max_stake == 0 {
+        0
+    } else {
+        (candidate.stake.saturating_mul(FULL_WEIGHT as u128) / max_stake) as u64
+    };
+    criteria.performance_weight as u64 * candidate.score as u64
+        + criteria.stake_weight as u64 * relative_stake
+        + criteria.history_weight as u64 * candidate.history as u64
+}
@@ -0,0 +1,54 @@
+//! Slash fractions for validator offences
+//!
+//! Each `Offence` has a curve giving the fraction of stake slashed. A
+//! validator offending alone is slashed close to the curve's `base`; the
+//! fraction grows linearly with the share of validators committing the same
+//! offence in the same session and reaches `max` once that share is
+//! `saturation`. Coordinated misbehaviour is therefore punished harder than
+//! an isolated fault.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::{traits::Saturating, PerThing, Perbill, RuntimeDebug};
+
+/// Slash fraction curve of an offence
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct SlashCurve {
+    /// Fraction slashed as the offending share approaches zero
+    pub base: Perbill,
+    /// Fraction slashed once the offending share reaches `saturation`
+    pub max: Perbill,
+    /// Share of offending validators at which `max` is reached
+    pub saturation: Perbill,
+}
+
+impl SlashCurve {
+    /// Whether `base` does not exceed `max`
+    pub fn is_valid(&self) -> bool {
+        self.base <= self.max
+    }
+
+    /// Fraction slashed when `offenders` out of `validators` committed the offence
+    pub fn fraction(&self, offenders: u32, validators: u32) -> Perbill {
+        let share = Perbill::from_rational(offenders, validators.max(offenders).max(1));
+        if self.max <= self.base {
+            return self.base;
+        }
+        if share >= self.saturation {
+            return self.max;
+        }
+        let progress = Perbill::from_rational(share.deconstruct(), self.saturation.deconstruct());
+        self.base.saturating_add(progress * (self.max.saturating_sub(self.base)))
+    }
+}
+
+/// Fraction of the remaining stake to slash to go from `applied` to `target`
+///
+/// Both fractions are of the stake before the first slash.
+pub fn top_up(applied: Perbill, target: Perbill) -> Perbill {
+    if target <= applied {
+        return Perbill::zero();
+    }
+    let remaining = applied.left_from_one();
+    Perbill::from_rational(target.saturating_sub(applied).deconstruct(), remaining.deconstruct())
+}
@@ -0,0 +1,1079 @@
+//! Validator coordination tests
+
+use crate::{
+    diversity::{self, DiversityCaps, DiversityTags},
+    heartbeat::Heartbeat, mock::*, selection, AuthoredBlocks, Call, CoordinationConfig, CoordinationConfigs,
+    ConfigDiff, Error, Event, FieldChange, HeartbeatAuthorities, HeartbeatKeys, JailCount, JailExpiries, Jails,
+    PendingRotations,
+    ReceivedHeartbeats, RotationQueue, RotationStrategy, ScoreHistory, SelectionCriteria,
+    scoring::{self, ScoreRecord, SessionMetrics, SessionScore},
+    slashing::{self, SlashCurve},
+    ParachainDiversityCaps, SessionOffences, SlashCurves, StandbyPools, ValidatorTags,
+};
+use codec::Encode;
+use frame_support::{
+    assert_noop, assert_ok,
+    traits::{Currency as _, Hooks},
+};
+use pallet_eigen::{Offence, ValidatorSets, ValidatorStatus};
+use sp_runtime::{
+    testing::{TestSignature, UintAuthorityId},
+    traits::ValidateUnsigned,
+    transaction_validity::{InvalidTransaction, TransactionSource, TransactionValidityError},
+    DispatchError, Perbill, RuntimeAppPublic,
+};
+
+fn criteria(min_stake: u128, min_uptime: u16) -> SelectionCriteria {
+    SelectionCriteria { min_stake, min_uptime, performance_weight: 5_000, stake_weight: 3_000, history_weight: 2_000 }
+}
+
+/// Hybrid selection of four to six validators, rotated every ten blocks
+fn config() -> CoordinationConfig<BlockNumber> {
+    CoordinationConfig {
+        rotation_frequency: 10,
+        rotation_strategy: RotationStrategy::Hybrid,
+        selection_criteria: criteria(100, 5_000),
+        max_validators: 6,
+        target_validators: 5,
+        min_validators: 4,
+    }
+}
+
+/// Performance selection of two to four validators, three by default
+fn performance_config() -> CoordinationConfig<BlockNumber> {
+    CoordinationConfig {
+        rotation_strategy: RotationStrategy::Performance,
+        selection_criteria: criteria(0, 0),
+        max_validators: 4,
+        target_validators: 3,
+        min_validators: 2,
+        ..config()
+    }
+}
+
+fn configure(parachain_id: u32, config: CoordinationConfig<BlockNumber>) {
+    assert_ok!(ValidatorCoordination::set_coordination_config(RuntimeOrigin::root(), parachain_id, config));
+}
+
+/// Validators `ids` with their score set to `score`
+fn set_scores(ids: impl IntoIterator<Item = AccountId>, score: u16) {
+    for id in ids {
+        pallet_eigen::Validators::<Test>::mutate(id, |p| p.as_mut().unwrap().performance.score = score);
+    }
+}
+
+fn members(parachain_id: u32) -> Vec<AccountId> {
+    let mut ids: Vec<AccountId> = ValidatorSets::<Test>::get(parachain_id).iter().map(|v| v.id).collect();
+    ids.sort();
+    ids
+}
+
+/// Validator `id` signing heartbeats with key `id`
+fn set_key(id: AccountId) {
+    assert_ok!(ValidatorCoordination::set_heartbeat_key(RuntimeOrigin::signed(id), id, UintAuthorityId(id)));
+}
+
+/// Heartbeat of validator `id` in the current session, signed with its key
+fn heartbeat(id: AccountId) -> Call<Test> {
+    let heartbeat = Heartbeat {
+        block_number: System::block_number(),
+        session_index: Eigen::current_session_index(),
+        validator: id,
+    };
+    let signature = UintAuthorityId(id).sign(&heartbeat.encode()).unwrap();
+    Call::heartbeat { heartbeat, signature }
+}
+
+fn validate(call: &Call<Test>) -> Result<(), TransactionValidityError> {
+    ValidatorCoordination::validate_unsigned(TransactionSource::External, call).map(|_| ())
+}
+
+fn submit(call: Call<Test>) {
+    assert_ok!(validate(&call));
+    let Call::heartbeat { heartbeat, signature } = call else { unreachable!() };
+    assert_ok!(ValidatorCoordination::heartbeat(RuntimeOrigin::none(), heartbeat, signature));
+}
+
+fn select(count: u32, strategy: RotationStrategy, seed: u8) -> Vec<AccountId> {
+    ValidatorCoordination::select_validators(PARA_A, count, strategy, &criteria(0, 0), [seed; 32])
+}
+
+#[test]
+fn jailed_and_exited_validators_are_not_candidates() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=4 {
+            register_validator(id, 1_000, 10_000, 10_000);
+        }
+        pallet_eigen::Validators::<Test>::mutate(2, |p| p.as_mut().unwrap().status = ValidatorStatus::Jailed);
+        pallet_eigen::Validators::<Test>::mutate(3, |p| p.as_mut().unwrap().status = ValidatorStatus::Exited);
+        pallet_eigen::Validators::<Test>::mutate(4, |p| p.as_mut().unwrap().status = ValidatorStatus::Pending);
+
+        let mut ids: Vec<AccountId> = ValidatorCoordination::candidates().into_iter().map(|c| c.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 4]);
+    });
+}
+
+#[test]
+fn candidates_below_the_minimum_stake_or_uptime_are_never_selected() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 9_000, 10_000);
+        register_validator(2, 99, 9_000, 10_000);
+        register_validator(3, 1_000, 8_999, 10_000);
+
+        for strategy in [
+            RotationStrategy::Random,
+            RotationStrategy::Performance,
+            RotationStrategy::StakeWeighted,
+            RotationStrategy::Hybrid,
+        ] {
+            let selected =
+                ValidatorCoordination::select_validators(PARA_A, 3, strategy, &criteria(100, 9_000), [0; 32]);
+            assert_eq!(selected, vec![1]);
+        }
+    });
+}
+
+#[test]
+fn selection_depends_only_on_the_seed_and_parachain() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=8 {
+            register_validator(id, 1_000, 10_000, 10_000);
+        }
+
+        assert_eq!(select(4, RotationStrategy::Random, 1), select(4, RotationStrategy::Random, 1));
+        assert_ne!(select(8, RotationStrategy::Random, 1), select(8, RotationStrategy::Random, 2));
+
+        let other =
+            ValidatorCoordination::select_validators(PARA_A + 1, 8, RotationStrategy::Random, &criteria(0, 0), [1; 32]);
+        assert_ne!(select(8, RotationStrategy::Random, 1), other);
+    });
+}
+
+#[test]
+fn stake_weighted_selection_draws_in_proportion_to_stake() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 9_000, 10_000, 0);
+        register_validator(2, 1_000, 10_000, 0);
+
+        let first: Vec<AccountId> =
+            (0..=255).map(|seed| select(1, RotationStrategy::StakeWeighted, seed)[0]).collect();
+        let heavy = first.iter().filter(|id| **id == 1).count();
+        assert!((200..=250).contains(&heavy), "validator 1 drawn first {} times", heavy);
+
+        // Drawn without replacement, so everyone is ranked once
+        let mut all = select(2, RotationStrategy::StakeWeighted, 0);
+        all.sort();
+        assert_eq!(all, vec![1, 2]);
+    });
+}
+
+#[test]
+fn performance_orders_by_score_then_stake() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+        register_validator(2, 2_000, 10_000, 5_000);
+        register_validator(3, 500, 10_000, 9_000);
+
+        assert_eq!(select(3, RotationStrategy::Performance, 0), vec![3, 2, 1]);
+        assert_eq!(select(2, RotationStrategy::Performance, 0), vec![3, 2]);
+    });
+}
+
+#[test]
+fn hybrid_ranks_by_the_weighted_sum_of_score_stake_and_history() {
+    new_test_ext().execute_with(|| {
+        // 50% score, 30% stake relative to the largest, 20% history
+        register_validator(1, 1_000, 10_000, 3_000);
+        register_validator(2, 250, 10_000, 10_000);
+        register_validator(3, 1_000, 10_000, 6_000);
+        ScoreHistory::<Test>::mutate(3, |record| {
+            record.push(SessionScore { session_index: 0, score: 5_000 }, ScoreDecay::get())
+        });
+
+        let scores: Vec<(AccountId, u64)> = ValidatorCoordination::candidates()
+            .iter()
+            .map(|c| (c.id, selection::hybrid_score(c, &criteria(0, 0), 1_000)))
+            .collect();
+        for (id, score) in scores {
+            let expected = match id {
+                // Not scored yet, so its history is its current score
+                1 => 5_000 * 3_000 + 3_000 * 10_000 + 2_000 * 3_000,
+                2 => 5_000 * 10_000 + 3_000 * 2_500 + 2_000 * 10_000,
+                _ => 5_000 * 6_000 + 3_000 * 10_000 + 2_000 * 5_000,
+            };
+            assert_eq!(score, expected);
+        }
+
+        assert_eq!(select(3, RotationStrategy::Hybrid, 0), vec![2, 3, 1]);
+    });
+}
+
+#[test]
+fn configs_are_validated_before_they_are_stored() {
+    new_test_ext().execute_with(|| {
+        let set = |config| ValidatorCoordination::set_coordination_config(RuntimeOrigin::root(), PARA_A, config);
+
+        let mut weights = config();
+        weights.selection_criteria.history_weight = 1_999;
+        assert_noop!(set(weights), Error::<Test>::InvalidWeights);
+
+        let mut uptime = config();
+        uptime.selection_criteria.min_uptime = 10_001;
+        assert_noop!(set(uptime), Error::<Test>::InvalidUptime);
+
+        let mut frequency = config();
+        frequency.rotation_frequency = 0;
+        assert_noop!(set(frequency), Error::<Test>::ZeroRotationFrequency);
+
+        for (min, target, max) in [(5, 4, 6), (4, 7, 6)] {
+            let sizes =
+                CoordinationConfig { min_validators: min, target_validators: target, max_validators: max, ..config() };
+            assert_noop!(set(sizes), Error::<Test>::InvalidSetSize);
+        }
+
+        // `MaxValidatorsPerSet` is 8
+        let oversized = CoordinationConfig { max_validators: 9, ..config() };
+        assert_noop!(set(oversized), Error::<Test>::TooManyValidators);
+
+        assert_noop!(
+            ValidatorCoordination::set_coordination_config(RuntimeOrigin::signed(1), PARA_A, config()),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(set(config()));
+        assert_eq!(CoordinationConfigs::<Test>::get(PARA_A), Some(config()));
+    });
+}
+
+#[test]
+fn config_updates_emit_the_fields_that_changed() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(ValidatorCoordination::set_coordination_config(RuntimeOrigin::root(), PARA_A, config()));
+        System::assert_last_event(
+            Event::ConfigUpdated { parachain_id: PARA_A, diff: ConfigDiff::between(None, &config()) }.into(),
+        );
+
+        let updated = CoordinationConfig { rotation_frequency: 20, target_validators: 6, ..config() };
+        assert_ok!(ValidatorCoordination::set_coordination_config(RuntimeOrigin::root(), PARA_A, updated.clone()));
+        let diff = ConfigDiff::between(Some(&config()), &updated);
+        assert_eq!(diff.rotation_frequency, Some(FieldChange { old: Some(10), new: 20 }));
+        assert_eq!(diff.target_validators, Some(FieldChange { old: Some(5), new: 6 }));
+        assert!(diff.rotation_strategy.is_none() && diff.selection_criteria.is_none());
+        assert!(diff.max_validators.is_none() && diff.min_validators.is_none());
+        System::assert_last_event(Event::ConfigUpdated { parachain_id: PARA_A, diff }.into());
+
+        // Setting the same configuration again changes nothing
+        assert_ok!(ValidatorCoordination::set_coordination_config(RuntimeOrigin::root(), PARA_A, updated.clone()));
+        assert!(ConfigDiff::between(Some(&updated), &updated).is_empty());
+    });
+}
+
+#[test]
+fn validator_sets_rotate_at_multiples_of_the_rotation_frequency() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=6 {
+            register_validator(id, 1_000, 10_000, 1_000 * id as u16);
+        }
+        configure(PARA_A, performance_config());
+
+        run_to_block(9);
+        assert!(members(PARA_A).is_empty());
+
+        // Nobody leaves the first set, so there is no handover
+        run_to_block(10);
+        assert_eq!(members(PARA_A), vec![4, 5, 6]);
+        assert_eq!(PendingRotations::<Test>::get(PARA_A), None);
+        System::assert_has_event(
+            Event::RotationStarted { parachain_id: PARA_A, added: vec![6, 5, 4], removed: vec![], handover_ends: 10 }
+                .into(),
+        );
+        System::assert_last_event(
+            Event::RotationCompleted { parachain_id: PARA_A, added: vec![6, 5, 4], removed: vec![] }.into(),
+        );
+        assert_eq!(pallet_eigen::Validators::<Test>::get(6).unwrap().active_parachains.to_vec(), vec![PARA_A]);
+    });
+}
+
+#[test]
+fn outgoing_validators_stay_for_the_handover_period() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=6 {
+            register_validator(id, 1_000, 10_000, 1_000 * id as u16);
+        }
+        configure(PARA_A, performance_config());
+        run_to_block(12);
+
+        // The end of the first session rescored the selected validators
+        for id in 4..=6 {
+            set_scores([id], 1_000 * id as u16);
+        }
+        set_scores([1, 2], 9_000);
+        run_to_block(20);
+        assert_eq!(members(PARA_A), vec![1, 2, 4, 5, 6]);
+        System::assert_last_event(
+            Event::RotationStarted { parachain_id: PARA_A, added: vec![1, 2], removed: vec![5, 4], handover_ends: 23 }
+                .into(),
+        );
+        assert_eq!(PendingRotations::<Test>::get(PARA_A).unwrap().completes_at, 23);
+
+        run_to_block(22);
+        assert_eq!(members(PARA_A), vec![1, 2, 4, 5, 6]);
+
+        run_to_block(23);
+        assert_eq!(members(PARA_A), vec![1, 2, 6]);
+        assert_eq!(PendingRotations::<Test>::get(PARA_A), None);
+        System::assert_last_event(
+            Event::RotationCompleted { parachain_id: PARA_A, added: vec![1, 2], removed: vec![5, 4] }.into(),
+        );
+        assert!(pallet_eigen::Validators::<Test>::get(4).unwrap().active_parachains.is_empty());
+    });
+}
+
+#[test]
+fn rotations_without_enough_eligible_validators_keep_the_current_set() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=3 {
+            register_validator(id, 1_000, 10_000, 5_000);
+        }
+        configure(PARA_A, performance_config());
+        run_to_block(10);
+        assert_eq!(members(PARA_A), vec![1, 2, 3]);
+
+        for id in 1..=2 {
+            pallet_eigen::Validators::<Test>::mutate(id, |p| p.as_mut().unwrap().status = ValidatorStatus::Jailed);
+        }
+        run_to_block(20);
+        System::assert_last_event(Event::RotationSkipped { parachain_id: PARA_A, available: 1 }.into());
+        assert_eq!(members(PARA_A), vec![1, 2, 3]);
+    });
+}
+
+#[test]
+fn rotations_beyond_the_block_budget_wait_in_the_queue() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=6 {
+            register_validator(id, 1_000, 10_000, 1_000 * id as u16);
+        }
+        for parachain_id in [PARA_A, PARA_B, PARA_C] {
+            configure(parachain_id, performance_config());
+        }
+
+        // Two rotations per block
+        run_to_block(10);
+        let rotated = [PARA_A, PARA_B, PARA_C].iter().filter(|id| !members(**id).is_empty()).count();
+        assert_eq!(rotated, 2);
+        let waiting = RotationQueue::<Test>::get();
+        assert_eq!(waiting.len(), 1);
+        assert!(members(waiting[0]).is_empty());
+
+        run_to_block(11);
+        assert_eq!(members(waiting[0]), vec![4, 5, 6]);
+        assert!(RotationQueue::<Test>::get().is_empty());
+    });
+}
+
+#[test]
+fn rotation_weight_is_bounded_by_the_candidates_read() {
+    let rotation_weight = |validators: u64| {
+        new_test_ext().execute_with(|| {
+            for id in 1..=validators {
+                register_validator(id, 1_000, 10_000, 5_000);
+            }
+            configure(PARA_A, performance_config());
+            System::set_block_number(10);
+            ValidatorCoordination::on_initialize(10)
+        })
+    };
+
+    // `MaxCandidates` is 16
+    assert!(rotation_weight(8).ref_time() < rotation_weight(16).ref_time());
+    assert_eq!(rotation_weight(16), rotation_weight(40));
+
+    new_test_ext().execute_with(|| {
+        configure(PARA_A, performance_config());
+        let db = <Test as frame_system::Config>::DbWeight::get();
+        // Taking the block's jail expiries, then reading the queue and one config
+        assert_eq!(ValidatorCoordination::on_initialize(11), db.reads_writes(1, 1) + db.reads(2 + 2));
+    });
+}
+
+#[test]
+fn heartbeat_keys_are_set_from_the_validator_account() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+        register_validator(2, 1_000, 10_000, 5_000);
+
+        assert_noop!(
+            ValidatorCoordination::set_heartbeat_key(RuntimeOrigin::signed(2), 1, UintAuthorityId(1)),
+            Error::<Test>::NotValidatorAccount
+        );
+        assert_noop!(
+            ValidatorCoordination::set_heartbeat_key(RuntimeOrigin::signed(3), 3, UintAuthorityId(3)),
+            Error::<Test>::ValidatorNotFound
+        );
+
+        set_key(1);
+        System::assert_last_event(Event::HeartbeatKeySet { validator: 1, key: UintAuthorityId(1) }.into());
+        assert_noop!(
+            ValidatorCoordination::set_heartbeat_key(RuntimeOrigin::signed(2), 2, UintAuthorityId(1)),
+            Error::<Test>::KeyInUse
+        );
+
+        // Rotating the key frees the old one
+        assert_ok!(ValidatorCoordination::set_heartbeat_key(RuntimeOrigin::signed(1), 1, UintAuthorityId(11)));
+        assert_eq!(HeartbeatKeys::<Test>::get(1), Some(UintAuthorityId(11)));
+        assert_eq!(HeartbeatAuthorities::<Test>::get(UintAuthorityId(1)), None);
+        set_key(2);
+        // Re-registering the key of a validator is allowed
+        assert_ok!(ValidatorCoordination::set_heartbeat_key(RuntimeOrigin::signed(2), 2, UintAuthorityId(2)));
+    });
+}
+
+#[test]
+fn unsigned_heartbeats_are_validated() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+        register_validator(2, 1_000, 10_000, 5_000);
+        set_key(1);
+
+        // No session yet
+        assert_eq!(validate(&heartbeat(1)), Err(InvalidTransaction::Stale.into()));
+        run_to_block(12);
+        assert_eq!(Eigen::current_session_index(), 1);
+
+        let valid = ValidatorCoordination::validate_unsigned(TransactionSource::External, &heartbeat(1)).unwrap();
+        assert_eq!(valid.priority, 100);
+        assert_eq!(valid.provides, vec![("ValidatorHeartbeat", (1u32, 1u64)).encode()]);
+        assert_eq!(valid.longevity, 10);
+
+        let Call::heartbeat { heartbeat: mut beat, signature } = heartbeat(1) else { unreachable!() };
+        beat.session_index = 0;
+        let stale = Call::heartbeat { heartbeat: beat.clone(), signature: signature.clone() };
+        assert_eq!(validate(&stale), Err(InvalidTransaction::Stale.into()));
+        beat.session_index = 2;
+        let future = Call::heartbeat { heartbeat: beat, signature };
+        assert_eq!(validate(&future), Err(InvalidTransaction::Future.into()));
+
+        // Validator 2 has no key, and 1's heartbeat is not signed by 1
+        assert_eq!(validate(&heartbeat(2)), Err(InvalidTransaction::BadSigner.into()));
+        let Call::heartbeat { heartbeat: beat, .. } = heartbeat(1) else { unreachable!() };
+        let forged = Call::heartbeat { heartbeat: beat.clone(), signature: TestSignature(2, beat.encode()) };
+        assert_eq!(validate(&forged), Err(InvalidTransaction::BadProof.into()));
+
+        let signature = TestSignature(1, beat.encode());
+        assert_noop!(
+            ValidatorCoordination::heartbeat(RuntimeOrigin::signed(1), beat.clone(), signature.clone()),
+            DispatchError::BadOrigin
+        );
+        submit(heartbeat(1));
+        System::assert_last_event(Event::HeartbeatReceived { validator: 1, session_index: 1 }.into());
+        assert!(ReceivedHeartbeats::<Test>::contains_key(1, 1));
+
+        // One heartbeat per session
+        assert_eq!(validate(&heartbeat(1)), Err(InvalidTransaction::Stale.into()));
+        assert_noop!(
+            ValidatorCoordination::heartbeat(RuntimeOrigin::none(), beat, signature),
+            Error::<Test>::DuplicateHeartbeat
+        );
+    });
+}
+
+#[test]
+fn session_end_records_uptime_and_authored_blocks_of_selected_validators() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=4 {
+            register_validator(id, 1_000, 10_000, 5_000);
+            set_key(id);
+        }
+        assert_ok!(Eigen::set_validator_set(PARA_A, vec![1, 2, 3]));
+        let author = |id: AccountId, blocks: u32| {
+            for _ in 0..blocks {
+                <ValidatorCoordination as pallet_authorship::EventHandler<_, _>>::note_author(id);
+            }
+        };
+
+        // Session 0 runs from block 2 to 11
+        run_to_block(2);
+        submit(heartbeat(1));
+        submit(heartbeat(2));
+        submit(heartbeat(4));
+        author(1, 3);
+        author(2, 1);
+        assert_eq!(AuthoredBlocks::<Test>::get(0, 1), 3);
+
+        run_to_block(12);
+        let performance = |id| pallet_eigen::Validators::<Test>::get(id).unwrap().performance;
+        let blocks = |id| (performance(id).uptime, performance(id).blocks_proposed, performance(id).blocks_settled);
+        assert_eq!(blocks(1), (10_000, 3, 0));
+        assert_eq!(blocks(3), (0, 0, 0));
+        // Validator 4 is in no set, so its heartbeat is not counted
+        assert_eq!(performance(4).uptime, 10_000);
+        System::assert_has_event(Event::ValidatorsOffline { session_index: 0, validators: vec![3] }.into());
+        let (offence, fraction) = Offences::get()[0].clone();
+        assert_eq!((offence.session_index, offence.validator_set_count, offence.offenders), (0, 3, vec![3]));
+        assert_eq!(fraction, Perbill::zero());
+        assert!(!ReceivedHeartbeats::<Test>::contains_key(0, 1));
+
+        // Blocks authored in session 0 are settled once session 1 ends
+        submit(heartbeat(1));
+        author(1, 2);
+        run_to_block(22);
+        assert_eq!(blocks(1), (10_000, 5, 3));
+        assert_eq!(performance(2).uptime, 5_000);
+        assert_eq!(AuthoredBlocks::<Test>::get(0, 1), 0);
+        System::assert_has_event(Event::ValidatorsOffline { session_index: 1, validators: vec![2, 3] }.into());
+        assert_eq!(Offences::get().len(), 2);
+    });
+}
+
+fn metrics(uptime: u16, blocks_proposed: u32, blocks_settled: u32, slashes: u32) -> SessionMetrics {
+    SessionMetrics { uptime, blocks_proposed, blocks_settled, expected_blocks: 4, slashes }
+}
+
+#[test]
+fn session_scores_weigh_uptime_proposals_and_settled_blocks() {
+    assert_eq!(scoring::session_score(&metrics(10_000, 4, 4, 0)), 10_000);
+    assert_eq!(scoring::session_score(&metrics(10_000, 0, 0, 0)), 5_000);
+    assert_eq!(scoring::session_score(&metrics(0, 4, 0, 0)), 3_000);
+    assert_eq!(scoring::session_score(&metrics(0, 0, 4, 0)), 2_000);
+    assert_eq!(scoring::session_score(&metrics(5_000, 2, 1, 0)), 2_500 + 1_500 + 500);
+    // Rates are capped at the fair share
+    assert_eq!(scoring::session_score(&metrics(10_000, 12, 8, 0)), 10_000);
+    assert_eq!(scoring::session_score(&SessionMetrics { expected_blocks: 0, ..metrics(0, 0, 0, 0) }), 5_000);
+
+    // Every slash halves the score
+    assert_eq!(scoring::session_score(&metrics(10_000, 4, 4, 1)), 5_000);
+    assert_eq!(scoring::session_score(&metrics(10_000, 4, 4, 2)), 2_500);
+    assert_eq!(scoring::session_score(&metrics(10_000, 4, 4, 40)), 0);
+}
+
+#[test]
+fn score_records_keep_a_decayed_average_and_the_latest_scores() {
+    let decay = Perbill::from_percent(80);
+    assert_eq!(scoring::decayed_average(None, 6_000, decay), 6_000);
+    assert_eq!(scoring::decayed_average(Some(6_000), 1_000, decay), 5_000);
+
+    let mut record = ScoreRecord::<frame_support::traits::ConstU32<3>>::default();
+    for (session_index, score) in [(0, 10_000), (1, 5_000), (2, 0), (3, 10_000), (4, 5_000)] {
+        record.push(SessionScore { session_index, score }, decay);
+    }
+    // 10000 -> 9000 -> 7200 -> 7760 -> 7208
+    assert_eq!(record.average, 7_208);
+    let sessions: Vec<u32> = record.in_order().map(|entry| entry.session_index).collect();
+    assert_eq!(sessions, vec![2, 3, 4]);
+    assert_eq!(record.cursor, 2);
+}
+
+#[test]
+fn session_end_scores_selected_validators() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=3 {
+            register_validator(id, 1_000, 10_000, 9_000);
+            set_key(id);
+        }
+        assert_ok!(Eigen::set_validator_set(PARA_A, vec![1, 2, 3]));
+        let author = |id: AccountId, blocks: u32| {
+            for _ in 0..blocks {
+                <ValidatorCoordination as pallet_authorship::EventHandler<_, _>>::note_author(id);
+            }
+        };
+        let score = |id| pallet_eigen::Validators::<Test>::get(id).unwrap().performance.score;
+
+        // Ten blocks shared by three validators, a fair share of three each
+        run_to_block(2);
+        submit(heartbeat(1));
+        submit(heartbeat(2));
+        author(1, 3);
+        run_to_block(12);
+        assert_eq!((score(1), score(2), score(3)), (8_000, 5_000, 0));
+        assert_eq!(ScoreHistory::<Test>::get(1).average, 8_000);
+
+        // The blocks of the first session are settled, and a slash halves the score of the session it lands in
+        submit(heartbeat(1));
+        submit(heartbeat(2));
+        pallet_eigen::Validators::<Test>::mutate(2, |p| p.as_mut().unwrap().performance.slashes = 1);
+        run_to_block(22);
+        assert_eq!((score(1), score(2)), (7_000, 2_500));
+        assert_eq!(ScoreHistory::<Test>::get(1).average, 7_800);
+        assert_eq!(ScoreHistory::<Test>::get(2).average, 4_500);
+        assert_eq!(ScoreHistory::<Test>::get(2).slashes_seen, 1);
+        let history: Vec<SessionScore> = ScoreHistory::<Test>::get(1).in_order().copied().collect();
+        assert_eq!(
+            history,
+            vec![SessionScore { session_index: 0, score: 8_000 }, SessionScore { session_index: 1, score: 7_000 }]
+        );
+
+        // Selection judges validators on their average
+        let history = |id| ValidatorCoordination::candidates().into_iter().find(|c| c.id == id).unwrap().history;
+        assert_eq!((history(1), history(2), history(3)), (7_800, 4_500, 0));
+    });
+}
+
+fn jail(id: AccountId, duration: BlockNumber) {
+    assert_ok!(ValidatorCoordination::jail_validator(&id, duration, b"offline".to_vec()));
+}
+
+fn status(id: AccountId) -> ValidatorStatus {
+    pallet_eigen::Validators::<Test>::get(id).unwrap().status
+}
+
+#[test]
+fn jailed_validators_leave_every_set_and_cannot_be_selected() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=4 {
+            register_validator(id, 1_000, 10_000, 5_000);
+        }
+        assert_ok!(Eigen::set_validator_set(PARA_A, vec![1, 2, 3]));
+        assert_ok!(Eigen::set_validator_set(PARA_B, vec![1, 4]));
+
+        assert_ok!(ValidatorCoordination::jail_validator(&1, 5, b"equivocation".to_vec()));
+        assert_eq!(status(1), ValidatorStatus::Jailed);
+        assert_eq!((members(PARA_A), members(PARA_B)), (vec![2, 3], vec![4]));
+        assert!(pallet_eigen::Validators::<Test>::get(1).unwrap().active_parachains.is_empty());
+        let jail = Jails::<Test>::get(1).unwrap();
+        assert_eq!((jail.until, jail.previous_status), (6, ValidatorStatus::Active));
+        // The stored reason is truncated, the event keeps it whole
+        assert_eq!(jail.reason.to_vec(), b"equivoca".to_vec());
+        let RuntimeEvent::ValidatorCoordination(Event::ValidatorJailed { validator, until, reason, mut parachains }) =
+            System::events().last().unwrap().event.clone()
+        else {
+            panic!("no jail event")
+        };
+        parachains.sort();
+        assert_eq!((validator, until, reason, parachains), (1, 6, b"equivocation".to_vec(), vec![PARA_A, PARA_B]));
+
+        assert!(ValidatorCoordination::candidates().iter().all(|c| c.id != 1));
+        assert_ok!(Eigen::set_validator_set(PARA_A, vec![1, 2, 3]));
+        assert_eq!(members(PARA_A), vec![2, 3]);
+        assert_noop!(ValidatorCoordination::jail_validator(&9, 5, vec![]), Error::<Test>::ValidatorNotFound);
+    });
+}
+
+#[test]
+fn jail_terms_double_with_every_jailing_and_expire() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+
+        jail(1, 5);
+        assert_eq!(Jails::<Test>::get(1).unwrap().until, 6);
+        run_to_block(5);
+        assert_eq!(status(1), ValidatorStatus::Jailed);
+        run_to_block(6);
+        assert_eq!(status(1), ValidatorStatus::Active);
+        assert_eq!(Jails::<Test>::get(1), None);
+        System::assert_last_event(Event::ValidatorReleased { validator: 1 }.into());
+
+        jail(1, 5);
+        assert_eq!(Jails::<Test>::get(1).unwrap().until, 16);
+        // A shorter jail does not cut the current one short, but still escalates the next
+        jail(1, 1);
+        assert_eq!(Jails::<Test>::get(1).unwrap().until, 16);
+        assert_eq!(JailCount::<Test>::get(1), 3);
+        // A longer one moves the expiry
+        jail(1, 2);
+        assert_eq!(Jails::<Test>::get(1).unwrap().until, 22);
+        assert!(JailExpiries::<Test>::get(16).is_empty());
+        assert_eq!(Jails::<Test>::get(1).unwrap().previous_status, ValidatorStatus::Active);
+
+        run_to_block(22);
+        assert_eq!(status(1), ValidatorStatus::Active);
+        // 5 * 2^4 is capped at MaxJailDuration
+        jail(1, 5);
+        assert_eq!(Jails::<Test>::get(1).unwrap().until, 62);
+    });
+}
+
+#[test]
+fn jails_expiring_in_a_full_block_move_to_the_next() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=5 {
+            register_validator(id, 1_000, 10_000, 5_000);
+        }
+        for id in 1..=4 {
+            jail(id, 5);
+        }
+        assert_eq!(JailExpiries::<Test>::get(6).to_vec(), vec![1, 2]);
+        assert_eq!(JailExpiries::<Test>::get(7).to_vec(), vec![3, 4]);
+        // Only `MaxJailExpiriesPerBlock` blocks are tried
+        assert_noop!(ValidatorCoordination::jail_validator(&5, 5, vec![]), Error::<Test>::TooManyJailExpiries);
+
+        run_to_block(6);
+        assert_eq!(status(2), ValidatorStatus::Active);
+        assert_eq!(status(3), ValidatorStatus::Jailed);
+        run_to_block(7);
+        assert_eq!(status(4), ValidatorStatus::Active);
+    });
+}
+
+#[test]
+fn validators_below_the_minimum_stake_stay_jailed_until_they_unjail() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+        register_validator(2, 1_000, 10_000, 5_000);
+        assert_noop!(ValidatorCoordination::unjail(RuntimeOrigin::signed(1), 1), Error::<Test>::NotJailed);
+
+        jail(1, 5);
+        pallet_eigen::TotalStake::<Test>::insert(1, 99);
+        assert_noop!(ValidatorCoordination::unjail(RuntimeOrigin::signed(1), 1), Error::<Test>::JailNotExpired);
+        run_to_block(6);
+        System::assert_last_event(Event::JailExpired { validator: 1 }.into());
+        assert_eq!(status(1), ValidatorStatus::Jailed);
+        assert_eq!(JailExpiries::<Test>::get(6).len(), 0);
+        assert_noop!(ValidatorCoordination::unjail(RuntimeOrigin::signed(1), 1), Error::<Test>::InsufficientStake);
+
+        pallet_eigen::TotalStake::<Test>::insert(1, 100);
+        assert_noop!(ValidatorCoordination::unjail(RuntimeOrigin::signed(2), 1), Error::<Test>::NotValidatorAccount);
+        assert_ok!(ValidatorCoordination::unjail(RuntimeOrigin::signed(1), 1));
+        assert_eq!(status(1), ValidatorStatus::Active);
+        System::assert_last_event(Event::ValidatorReleased { validator: 1 }.into());
+    });
+}
+
+#[test]
+fn sets_are_not_changed_when_a_member_is_on_too_many_parachains() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+        register_validator(2, 1_000, 10_000, 5_000);
+        // `MaxValidatorsPerSet` is 8
+        for parachain_id in 0..8 {
+            assert_ok!(Eigen::set_validator_set(parachain_id, vec![1]));
+        }
+        assert_noop!(Eigen::set_validator_set(PARA_A, vec![2, 1]), pallet_eigen::Error::<Test>::TooManyParachains);
+        assert!(pallet_eigen::Validators::<Test>::get(2).unwrap().active_parachains.is_empty());
+    });
+}
+
+fn curve(base: u32, max: u32, saturation: u32) -> SlashCurve {
+    SlashCurve {
+        base: Perbill::from_percent(base),
+        max: Perbill::from_percent(max),
+        saturation: Perbill::from_percent(saturation),
+    }
+}
+
+fn set_curve(offence: Offence, curve: SlashCurve) {
+    assert_ok!(ValidatorCoordination::set_slash_curve(RuntimeOrigin::root(), offence, curve));
+}
+
+fn balance(id: AccountId) -> Balance {
+    Balances::free_balance(id)
+}
+
+#[test]
+fn slash_fractions_grow_with_the_share_of_offenders() {
+    let curve = curve(10, 50, 50);
+    assert_eq!(curve.fraction(1, 10), Perbill::from_percent(18));
+    assert_eq!(curve.fraction(2, 10), Perbill::from_percent(26));
+    assert_eq!(curve.fraction(5, 10), Perbill::from_percent(50));
+    assert_eq!(curve.fraction(9, 10), Perbill::from_percent(50));
+    // Offenders are always counted among the validators
+    assert_eq!(curve.fraction(2, 0), Perbill::from_percent(50));
+    // A flat curve
+    assert_eq!(self::curve(20, 20, 50).fraction(5, 10), Perbill::from_percent(20));
+
+    // Topping up 10% to 28% of the original stake takes 20% of the remaining 90%
+    assert_eq!(slashing::top_up(Perbill::from_percent(10), Perbill::from_percent(28)), Perbill::from_percent(20));
+    assert_eq!(slashing::top_up(Perbill::zero(), Perbill::from_percent(30)), Perbill::from_percent(30));
+    assert_eq!(slashing::top_up(Perbill::from_percent(30), Perbill::from_percent(30)), Perbill::zero());
+}
+
+#[test]
+fn slash_curves_are_set_by_the_config_origin() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ValidatorCoordination::set_slash_curve(RuntimeOrigin::signed(1), Offence::Equivocation, curve(10, 50, 50)),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            ValidatorCoordination::set_slash_curve(RuntimeOrigin::root(), Offence::Equivocation, curve(60, 50, 50)),
+            Error::<Test>::InvalidSlashCurve
+        );
+        set_curve(Offence::Equivocation, curve(10, 50, 50));
+        assert_eq!(SlashCurves::<Test>::get(Offence::Equivocation), curve(10, 50, 50));
+        System::assert_last_event(
+            Event::SlashCurveSet { offence: Offence::Equivocation, curve: curve(10, 50, 50) }.into(),
+        );
+
+        // Offences without a curve are not slashed
+        register_validator(1, 1_000, 10_000, 5_000);
+        assert_eq!(ValidatorCoordination::slash_validator(&1, Offence::Unavailability), Ok((0, 0)));
+        assert_eq!(balance(1), 1_000);
+    });
+}
+
+#[test]
+fn coordinated_offences_top_up_earlier_offenders() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=4 {
+            register_validator(id, 1_000, 10_000, 5_000);
+        }
+        assert_ok!(Eigen::set_validator_set(PARA_A, vec![1, 2, 3, 4]));
+        Balances::make_free_balance_be(&10, 500);
+        assert_ok!(Eigen::add_stake(&10, &1, 500));
+        assert_eq!(pallet_eigen::TotalStake::<Test>::get(1), 1_500);
+        set_curve(Offence::Equivocation, curve(10, 50, 50));
+
+        // One of four validators offending is a 25% share, 30% on the curve
+        assert_eq!(ValidatorCoordination::slash_validator(&1, Offence::Equivocation), Ok((450, 1)));
+        assert_eq!((balance(1), balance(10)), (700, 350));
+        assert_eq!(pallet_eigen::Stakes::<Test>::get(10, 1).unwrap().amount, 350);
+        assert_eq!(pallet_eigen::TotalStake::<Test>::get(1), 1_050);
+        assert_eq!(pallet_eigen::Validators::<Test>::get(1).unwrap().performance.slashes, 1);
+
+        // A second offender raises both to 50%
+        assert_eq!(ValidatorCoordination::slash_validator(&2, Offence::Equivocation), Ok((500, 2)));
+        assert_eq!((balance(1), balance(10), balance(2)), (500, 250, 500));
+        assert_eq!(pallet_eigen::TotalStake::<Test>::get(1), 750);
+        System::assert_has_event(
+            pallet_eigen::Event::ValidatorSlashed { validator: 1, amount: 300, offence: Offence::Equivocation }.into(),
+        );
+        assert_eq!(SessionOffences::<Test>::get((0, Offence::Equivocation), 1), Some(Perbill::from_percent(50)));
+
+        // Reporting the same offence again in the session changes nothing
+        assert_eq!(ValidatorCoordination::slash_validator(&1, Offence::Equivocation), Ok((0, 0)));
+        assert_eq!(pallet_eigen::Validators::<Test>::get(1).unwrap().performance.slashes, 1);
+
+        // The next session starts over
+        run_to_block(12);
+        assert_eq!(SessionOffences::<Test>::get((0, Offence::Equivocation), 1), None);
+        assert_eq!(ValidatorCoordination::slash_validator(&1, Offence::Equivocation), Ok((225, 1)));
+    });
+}
+
+#[test]
+fn offenders_per_session_are_bounded() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=5 {
+            register_validator(id, 1_000, 10_000, 5_000);
+        }
+        set_curve(Offence::Unavailability, curve(1, 1, 50));
+        for id in 1..=4 {
+            assert_ok!(ValidatorCoordination::slash_validator(&id, Offence::Unavailability));
+        }
+        // `MaxOffendersPerSession` is 4
+        assert_noop!(
+            ValidatorCoordination::slash_validator(&5, Offence::Unavailability),
+            Error::<Test>::TooManyOffenders
+        );
+        assert_ok!(ValidatorCoordination::slash_validator(&1, Offence::Unavailability));
+        assert_noop!(
+            ValidatorCoordination::slash_validator(&9, Offence::Unavailability),
+            Error::<Test>::ValidatorNotFound
+        );
+    });
+}
+
+#[test]
+fn stakers_per_validator_are_bounded() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+        for staker in 10..14 {
+            assert_ok!(Eigen::add_stake(&staker, &1, 100));
+        }
+        // `MaxNominatorsPerValidator` is 4
+        assert_noop!(Eigen::add_stake(&14, &1, 100), pallet_eigen::Error::<Test>::TooManyNominators);
+        assert_ok!(Eigen::add_stake(&10, &1, 100));
+        assert_eq!(pallet_eigen::Stakes::<Test>::get(10, 1).unwrap().amount, 200);
+        assert_eq!(pallet_eigen::ValidatorStakers::<Test>::get(1).len(), 4);
+        assert_eq!(pallet_eigen::TotalStake::<Test>::get(1), 1_500);
+        assert_noop!(Eigen::add_stake(&10, &2, 100), pallet_eigen::Error::<Test>::ValidatorNotFound);
+    });
+}
+
+#[test]
+fn reported_offences_slash_jail_and_charge_for_the_offenders_slashed() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=2 {
+            register_validator(id, 1_000, 10_000, 5_000);
+        }
+        assert_ok!(Eigen::set_validator_set(PARA_A, vec![1, 2]));
+        set_curve(Offence::Equivocation, curve(10, 10, 50));
+        assert_noop!(
+            ValidatorCoordination::report_offence(RuntimeOrigin::signed(1), 2, Offence::Equivocation, None),
+            DispatchError::BadOrigin
+        );
+
+        let info =
+            ValidatorCoordination::report_offence(RuntimeOrigin::root(), 1, Offence::Equivocation, Some(5)).unwrap();
+        assert_eq!(info.actual_weight, Some(ValidatorCoordination::offence_weight(1, true)));
+        assert_eq!(balance(1), 900);
+        assert_eq!(status(1), ValidatorStatus::Jailed);
+        assert_eq!(Jails::<Test>::get(1).unwrap().reason.to_vec(), b"equivoca".to_vec());
+        assert_eq!(members(PARA_A), vec![2]);
+
+        let info =
+            ValidatorCoordination::report_offence(RuntimeOrigin::root(), 2, Offence::Equivocation, None).unwrap();
+        assert_eq!(info.actual_weight, Some(ValidatorCoordination::offence_weight(1, false)));
+        assert_eq!(status(2), ValidatorStatus::Active);
+
+        // The declared weight covers every offender of the session being slashed
+        let declared = ValidatorCoordination::offence_weight(4, true);
+        assert!(ValidatorCoordination::offence_weight(1, true).ref_time() < declared.ref_time());
+    });
+}
+
+fn tags(operator: AccountId, region: Option<&[u8; 2]>) -> DiversityTags<AccountId> {
+    DiversityTags { operator, region: region.copied(), asn: None }
+}
+
+fn set_tags(id: AccountId, tags: DiversityTags<AccountId>) {
+    assert_ok!(ValidatorCoordination::set_diversity_tags(RuntimeOrigin::signed(id), id, tags));
+}
+
+#[test]
+fn diverse_selection_is_greedy_then_repaired() {
+    let operator_cap = DiversityCaps { max_per_operator: Some(1), ..Default::default() };
+    let ranked = [tags(1, None), tags(1, None), tags(2, None), tags(1, None), tags(3, None)];
+    assert_eq!(diversity::select_diverse(&ranked, 3, &operator_cap), vec![0, 2, 4]);
+    assert_eq!(diversity::select_diverse(&ranked, 3, &DiversityCaps::default()), vec![0, 1, 2]);
+    // Not enough distinct operators
+    assert_eq!(diversity::select_diverse(&ranked, 4, &operator_cap), vec![0, 2, 4]);
+
+    // Greedy takes 0 and 1, which block 2 by operator and 3 by region. Swapping 0
+    // for 2 lets 3 in.
+    let caps = DiversityCaps { max_per_operator: Some(1), max_per_region: Some(1), max_per_asn: None };
+    let ranked = [tags(1, Some(b"DE")), tags(2, Some(b"FR")), tags(1, Some(b"IT")), tags(3, Some(b"DE"))];
+    assert_eq!(diversity::select_diverse(&ranked, 3, &caps), vec![1, 2, 3]);
+
+    let asn_cap = DiversityCaps { max_per_asn: Some(2), ..Default::default() };
+    let ranked: Vec<_> = (0..4).map(|operator| DiversityTags { operator, region: None, asn: Some(7) }).collect();
+    assert_eq!(diversity::select_diverse(&ranked, 3, &asn_cap), vec![0, 1]);
+}
+
+#[test]
+fn diversity_tags_and_caps_are_set_by_their_owners() {
+    new_test_ext().execute_with(|| {
+        register_validator(1, 1_000, 10_000, 5_000);
+        assert_noop!(
+            ValidatorCoordination::set_diversity_tags(RuntimeOrigin::signed(2), 1, tags(2, None)),
+            Error::<Test>::NotValidatorAccount
+        );
+        assert_noop!(
+            ValidatorCoordination::set_diversity_tags(RuntimeOrigin::signed(2), 2, tags(2, None)),
+            Error::<Test>::ValidatorNotFound
+        );
+        set_tags(1, tags(100, Some(b"DE")));
+        assert_eq!(ValidatorTags::<Test>::get(1), Some(tags(100, Some(b"DE"))));
+        System::assert_last_event(Event::DiversityTagsSet { validator: 1, tags: tags(100, Some(b"DE")) }.into());
+
+        let caps = DiversityCaps { max_per_operator: Some(2), ..Default::default() };
+        assert_noop!(
+            ValidatorCoordination::set_diversity_caps(RuntimeOrigin::signed(1), PARA_A, caps),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(ValidatorCoordination::set_diversity_caps(RuntimeOrigin::root(), PARA_A, caps));
+        assert_eq!(ParachainDiversityCaps::<Test>::get(PARA_A), caps);
+        System::assert_last_event(Event::DiversityCapsSet { parachain_id: PARA_A, caps }.into());
+    });
+}
+
+#[test]
+fn rotations_respect_the_diversity_caps_of_each_parachain() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=6 {
+            register_validator(id, 1_000, 10_000, 1_000 * id as u16);
+        }
+        // All run in one region, 3 to 6 share an operator and 1 and 2 run alone
+        for id in 1..=6 {
+            set_tags(id, tags(if id > 2 { 100 } else { id }, Some(b"DE")));
+        }
+        configure(PARA_A, performance_config());
+        configure(PARA_B, performance_config());
+        configure(PARA_C, performance_config());
+        let caps = DiversityCaps { max_per_operator: Some(1), ..Default::default() };
+        assert_ok!(ValidatorCoordination::set_diversity_caps(RuntimeOrigin::root(), PARA_A, caps));
+        let strategy = RotationStrategy::Performance;
+        let selected = ValidatorCoordination::select_validators(PARA_A, 3, strategy, &criteria(0, 0), [0; 32]);
+        assert_eq!(selected, vec![6, 2, 1]);
+        // Two per region leaves PARA_B short of its target, but above its minimum
+        let caps = DiversityCaps { max_per_region: Some(2), ..Default::default() };
+        assert_ok!(ValidatorCoordination::set_diversity_caps(RuntimeOrigin::root(), PARA_B, caps));
+
+        // Two rotations per block
+        run_to_block(11);
+        assert_eq!(members(PARA_A), vec![1, 2, 6]);
+        assert_eq!(members(PARA_B), vec![5, 6]);
+        System::assert_has_event(Event::DiversityShortfall { parachain_id: PARA_B, selected: 2, target: 3 }.into());
+        // PARA_C has no caps
+        assert_eq!(members(PARA_C), vec![4, 5, 6]);
+    });
+}
+
+fn pool(parachain_id: u32) -> Vec<AccountId> {
+    StandbyPools::<Test>::get(parachain_id).to_vec()
+}
+
+#[test]
+fn standbys_replace_validators_that_leave() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=6 {
+            register_validator(id, 1_000, 10_000, 1_000 * id as u16);
+        }
+        configure(PARA_A, performance_config());
+        run_to_block(10);
+        assert_eq!(members(PARA_A), vec![4, 5, 6]);
+        assert_eq!(pool(PARA_A), vec![3, 2, 1]);
+
+        // 3 shares an operator with 5, so 2 takes the seat of 6
+        set_tags(3, tags(100, None));
+        set_tags(5, tags(100, None));
+        let caps = DiversityCaps { max_per_operator: Some(1), ..Default::default() };
+        assert_ok!(ValidatorCoordination::set_diversity_caps(RuntimeOrigin::root(), PARA_A, caps));
+        jail(6, 5);
+        assert_eq!(members(PARA_A), vec![2, 4, 5]);
+        assert_eq!(pool(PARA_A), vec![3, 1]);
+        System::assert_last_event(Event::StandbyPromoted { parachain_id: PARA_A, validator: 2, replaced: 6 }.into());
+
+        assert_noop!(ValidatorCoordination::exit(RuntimeOrigin::signed(5), 4), Error::<Test>::NotValidatorAccount);
+        assert_noop!(ValidatorCoordination::exit(RuntimeOrigin::signed(6), 6), Error::<Test>::ValidatorJailed);
+        assert_ok!(ValidatorCoordination::exit(RuntimeOrigin::signed(4), 4));
+        assert_eq!(status(4), ValidatorStatus::Exited);
+        System::assert_has_event(Event::ValidatorExited { validator: 4, parachains: vec![PARA_A] }.into());
+        assert_eq!(members(PARA_A), vec![1, 2, 5]);
+
+        // With 5 gone, 3 fits
+        jail(5, 6);
+        assert_eq!(members(PARA_A), vec![1, 2, 3]);
+        assert!(pool(PARA_A).is_empty());
+
+        // The pool is empty, the set shrinks
+        jail(1, 7);
+        assert_eq!(members(PARA_A), vec![2, 3]);
+        jail(2, 8);
+        assert_eq!(members(PARA_A), vec![3]);
+        System::assert_last_event(Event::BelowMinimum { parachain_id: PARA_A, validators: 1 }.into());
+    });
+}
+
+#[test]
+fn standbys_promoted_during_a_handover_join_the_incoming_set() {
+    new_test_ext().execute_with(|| {
+        for id in 1..=6 {
+            register_validator(id, 1_000, 10_000, 1_000 * id as u16);
+        }
+        configure(PARA_A, performance_config());
+        run_to_block(12);
+        for id in 3..=6 {
+            set_scores([id], 1_000 * id as u16);
+        }
+        set_scores([1, 2], 9_000);
+        run_to_block(20);
+        assert_eq!(members(PARA_A), vec![1, 2, 4, 5, 6]);
+        assert_eq!(pool(PARA_A), vec![5, 4, 3]);
+
+        // Outgoing 4 and 5 are still members, so 3 is promoted
+        jail(1, 5);
+        assert_eq!(members(PARA_A), vec![2, 3, 4, 5, 6]);
+        let mut incoming = PendingRotations::<Test>::get(PARA_A).unwrap().incoming.to_vec();
+        incoming.sort();
+        assert_eq!(incoming, vec![2, 3, 6]);
+        System::assert_last_event(Event::StandbyPromoted { parachain_id: PARA_A, validator: 3, replaced: 1 }.into());
+
+        // An outgoing validator leaving needs no replacement
+        jail(4, 5);
+        assert_eq!(members(PARA_A), vec![2, 3, 5, 6]);
+
+        run_to_block(23);
+        assert_eq!(members(PARA_A), vec![2, 3, 6]);
+    });
+}
@@ -0,0 +1,370 @@
+//! Offline restaking risk and yield simulator
+//!
+//! Runs Monte Carlo simulations of a `RestakingConfig` before it is used on
+//! chain. Allocations, stake splits, lock boosts and reward accrual are
+//! computed with the same functions pallet_restaking uses, so the only
+//! modelled parts are the per-parachain slash probabilities, reward budgets
+//! and the shares held by other restakers.
+//!
+//! Each trial steps through the horizon one `reward_frequency` period at a
+//! time. Every period each allocated parachain spreads its fixed reward
+//! budget over all of its shares, where the simulated account holds the
+//! lock-boosted stake allocated to the parachain until its lock ends. Each
+//! parachain then independently slashes the raw stake allocated to it with
+//! its per-period probability. As in the pallet, a slash reduces
+//! `total_staked`, which is then re-split across all allocations. The reward
+//! pot is assumed to cover every budget.
+//!
+//! At the end of the horizon the account unstakes everything. As in the
+//! pallet's withdrawal queue, the stake then waits out `unbonding_period`
+//! without earning rewards while the parachains it leaves can still slash
+//! it, so returns are annualised over the horizon plus the unbonding period.
+
+use pallet_restaking::{allocation, rewards, RestakeAllocation, RestakeStrategy, RestakingConfig};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use sp_runtime::{traits::One, FixedU128};
+use std::fmt;
+
+#[cfg(test)]
+mod tests;
+
+/// Restaking configuration with simulator account, balance and block types
+pub type SimConfig = RestakingConfig<String, u128, u64>;
+
+/// Restake strategy as written in JSON
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategySpec {
+    Proportional,
+    Equal,
+    Custom,
+    SingleParachain,
+}
+
+impl From<StrategySpec> for RestakeStrategy {
+    fn from(strategy: StrategySpec) -> Self {
+        match strategy {
+            StrategySpec::Proportional => RestakeStrategy::Proportional,
+            StrategySpec::Equal => RestakeStrategy::Equal,
+            StrategySpec::Custom => RestakeStrategy::Custom,
+            StrategySpec::SingleParachain => RestakeStrategy::SingleParachain,
+        }
+    }
+}
+
+/// Allocation as written in JSON
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AllocationSpec {
+    /// Parachain ID
+    pub parachain_id: u32,
+    /// Percentage (0-10000) or, for `proportional`, relative weight
+    pub percentage: u16,
+}
+
+/// Restaking configuration as written in JSON
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigSpec {
+    pub account: String,
+    pub strategy: StrategySpec,
+    pub allocations: Vec<AllocationSpec>,
+    pub total_staked: u128,
+    pub unbonding_period: u64,
+    pub min_stake_duration: u64,
+    pub max_stake_duration: u64,
+    pub reward_frequency: u64,
+    pub lock_duration: u64,
+}
+
+impl ConfigSpec {
+    /// Build the on-chain configuration, resolving allocations as the pallet would
+    ///
+    /// `proportional` uses the given percentages as weights, since the
+    /// simulator has no parachain registry to read security weights from.
+    pub fn to_config(&self) -> Result<SimConfig, SimulationError> {
+        let requested: Vec<RestakeAllocation> = self
+            .allocations
+            .iter()
+            .map(|a| RestakeAllocation { parachain_id: a.parachain_id, percentage: a.percentage })
+            .collect();
+        let allocations = allocation::resolve_allocations(self.strategy.into(), &requested)
+            .map_err(|e| SimulationError::Allocation(format!("{:?}", e)))?;
+
+        if self.lock_duration < self.min_stake_duration || self.lock_duration > self.max_stake_duration {
+            return Err(SimulationError::InvalidParameter("lock_duration outside stake duration range"));
+        }
+
+        Ok(RestakingConfig {
+            account: self.account.clone(),
+            strategy: self.strategy.into(),
+            allocations,
+            total_staked: self.total_staked,
+            unbonding_period: self.unbonding_period,
+            min_stake_duration: self.min_stake_duration,
+            max_stake_duration: self.max_stake_duration,
+            reward_frequency: self.reward_frequency,
+            lock_duration: self.lock_duration,
+            locked_until: self.lock_duration,
+        })
+    }
+}
+
+/// Risk and reward model of a parachain
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ParachainModel {
+    /// Parachain ID
+    pub parachain_id: u32,
+    /// Probability of at least one slash within a year
+    pub annual_slash_probability: f64,
+    /// Fraction of the allocated stake lost per slash
+    pub slash_fraction: f64,
+    /// Reward paid to the parachain's restakers every period, as set by `set_reward_budget`
+    pub reward_budget: u128,
+    /// Lock-boosted stake other restakers allocate to the parachain
+    #[serde(default)]
+    pub other_shares: u128,
+}
+
+/// Simulation input
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SimulationSpec {
+    /// Configuration to evaluate
+    pub config: ConfigSpec,
+    /// Models of the allocated parachains
+    pub parachains: Vec<ParachainModel>,
+    /// Blocks per year, used to annualise rates
+    pub blocks_per_year: u64,
+    /// Blocks to simulate, defaults to the lock duration
+    #[serde(default)]
+    pub horizon_blocks: Option<u64>,
+    /// Number of Monte Carlo trials
+    #[serde(default = "default_trials")]
+    pub trials: u32,
+    /// Seed for the random number generator
+    #[serde(default)]
+    pub seed: u64,
+    /// Maximum boost of the runtime's linear lock curve, 1.0 disables boosting
+    #[serde(default = "default_max_boost")]
+    pub max_boost: f64,
+    /// Restake rewards every period instead of holding them
+    #[serde(default)]
+    pub compound: bool,
+    /// Confidence level for value-at-risk
+    #[serde(default = "default_confidence")]
+    pub confidence: f64,
+}
+
+fn default_trials() -> u32 {
+    10_000
+}
+
+fn default_max_boost() -> f64 {
+    1.0
+}
+
+fn default_confidence() -> f64 {
+    0.95
+}
+
+/// Simulation results
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    /// Number of trials run
+    pub trials: u32,
+    /// Reward periods per trial
+    pub periods: u64,
+    /// Lock multiplier applied to rewards until the lock ends
+    pub reward_multiplier: f64,
+    /// Mean annualised return, e.g. 0.07 for 7%
+    pub expected_apr: f64,
+    /// Confidence level of `value_at_risk`
+    pub confidence: f64,
+    /// Loss over the horizon not exceeded at the confidence level
+    pub value_at_risk: u128,
+    /// Mean amount slashed over the horizon
+    pub expected_slash: u128,
+    /// Largest amount slashed in any trial
+    pub max_simulated_slash: u128,
+    /// Amount lost if every allocated parachain slashes at once
+    pub worst_case_slash: u128,
+}
+
+/// Reasons a simulation cannot run
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimulationError {
+    /// Allocations are invalid for the strategy
+    Allocation(String),
+    /// An allocated parachain has no model
+    MissingParachainModel(u32),
+    /// A parameter is out of range
+    InvalidParameter(&'static str),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::Allocation(e) => write!(f, "invalid allocations: {}", e),
+            SimulationError::MissingParachainModel(id) => write!(f, "no model for parachain {}", id),
+            SimulationError::InvalidParameter(e) => write!(f, "invalid parameter: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+/// Per-period model of an allocated parachain
+struct PeriodModel {
+    slash_probability: f64,
+    unbonding_slash_probability: f64,
+    slash_fraction: f64,
+    reward_budget: u128,
+    other_shares: u128,
+}
+
+/// Rewards paid on `shares` when a parachain spreads `budget` over them and `other_shares`
+///
+/// Matches what the account accrues against the pallet's reward-per-share
+/// accumulator over one period.
+pub fn period_reward(budget: u128, shares: u128, other_shares: u128) -> u128 {
+    if shares == 0 {
+        return 0;
+    }
+    let accumulator = rewards::reward_per_share(budget, shares.saturating_add(other_shares));
+    rewards::accrued_rewards(accumulator, shares, 0)
+}
+
+/// Run the simulation described by `spec`
+pub fn simulate(spec: &SimulationSpec) -> Result<Report, SimulationError> {
+    let config = spec.config.to_config()?;
+    if spec.blocks_per_year == 0 {
+        return Err(SimulationError::InvalidParameter("blocks_per_year must be positive"));
+    }
+    if spec.trials == 0 {
+        return Err(SimulationError::InvalidParameter("trials must be positive"));
+    }
+    if !(0.0..1.0).contains(&spec.confidence) {
+        return Err(SimulationError::InvalidParameter("confidence must be in [0, 1)"));
+    }
+    if !(spec.max_boost >= 1.0 && spec.max_boost.is_finite()) {
+        return Err(SimulationError::InvalidParameter("max_boost must be a finite multiplier of at least 1"));
+    }
+
+    let period = config.reward_frequency.max(1);
+    let horizon = spec.horizon_blocks.unwrap_or(config.lock_duration).max(period);
+    let periods = horizon / period;
+    let period_years = period as f64 / spec.blocks_per_year as f64;
+    let unbonding_years = config.unbonding_period as f64 / spec.blocks_per_year as f64;
+    let horizon_years = (periods * period + config.unbonding_period) as f64 / spec.blocks_per_year as f64;
+
+    let models = config
+        .allocations
+        .iter()
+        .map(|a| {
+            let model = spec
+                .parachains
+                .iter()
+                .find(|m| m.parachain_id == a.parachain_id)
+                .ok_or(SimulationError::MissingParachainModel(a.parachain_id))?;
+            if !(0.0..=1.0).contains(&model.annual_slash_probability) {
+                return Err(SimulationError::InvalidParameter("annual_slash_probability must be in [0, 1]"));
+            }
+            if !(0.0..=1.0).contains(&model.slash_fraction) {
+                return Err(SimulationError::InvalidParameter("slash_fraction must be in [0, 1]"));
+            }
+            let slash_probability = 1.0 - (1.0 - model.annual_slash_probability).powf(period_years);
+            let unbonding_slash_probability = 1.0 - (1.0 - model.annual_slash_probability).powf(unbonding_years);
+            Ok(PeriodModel {
+                slash_probability: slash_probability.clamp(0.0, 1.0),
+                unbonding_slash_probability: unbonding_slash_probability.clamp(0.0, 1.0),
+                slash_fraction: model.slash_fraction,
+                reward_budget: model.reward_budget,
+                other_shares: model.other_shares,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let multiplier = rewards::linear_multiplier(
+        config.lock_duration,
+        config.min_stake_duration,
+        config.max_stake_duration,
+        FixedU128::from_float(spec.max_boost),
+    );
+
+    let initial = config.total_staked;
+    let mut rng = ChaCha8Rng::seed_from_u64(spec.seed);
+    let mut returns = Vec::with_capacity(spec.trials as usize);
+    let mut total_slashed = 0u128;
+    let mut max_simulated_slash = 0u128;
+
+    for _ in 0..spec.trials {
+        let mut stake = initial;
+        let mut held_rewards = 0u128;
+        let mut slashed = 0u128;
+
+        for index in 0..periods {
+            // Rewards are distributed before the lock sweep of the block the lock ends at
+            let locked = (index + 1) * period <= config.locked_until;
+            let boost = if locked { multiplier } else { FixedU128::one() };
+            let parts = allocation::split_amount(stake, &config.allocations);
+            let shares = allocation::split_amount(rewards::boosted_stake(stake, boost), &config.allocations);
+
+            let mut reward = 0u128;
+            let mut slash = 0u128;
+            for (((_, part), (_, share)), model) in parts.iter().zip(shares.iter()).zip(models.iter()) {
+                reward += period_reward(model.reward_budget, *share, model.other_shares);
+                if rng.gen_bool(model.slash_probability) {
+                    slash += (*part as f64 * model.slash_fraction) as u128;
+                }
+            }
+
+            let slash = slash.min(stake);
+            stake -= slash;
+            slashed += slash;
+            if spec.compound {
+                stake += reward;
+            } else {
+                held_rewards += reward;
+            }
+        }
+
+        // The unstaked amount stays slashable by the parachains it leaves until it matures
+        let mut slash = 0u128;
+        for ((_, part), model) in allocation::split_amount(stake, &config.allocations).iter().zip(models.iter()) {
+            if rng.gen_bool(model.unbonding_slash_probability) {
+                slash += (*part as f64 * model.slash_fraction) as u128;
+            }
+        }
+        let slash = slash.min(stake);
+        stake -= slash;
+        slashed += slash;
+
+        let final_value = stake + held_rewards;
+        returns.push((final_value as f64 - initial as f64) / initial.max(1) as f64);
+        total_slashed += slashed;
+        max_simulated_slash = max_simulated_slash.max(slashed);
+    }
+
+    let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
+    returns.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let tail_index = (((1.0 - spec.confidence) * returns.len() as f64) as usize).min(returns.len() - 1);
+    let tail_return = returns[tail_index];
+
+    let worst_case_slash = allocation::split_amount(initial, &config.allocations)
+        .iter()
+        .zip(models.iter())
+        .map(|((_, part), model)| (*part as f64 * model.slash_fraction) as u128)
+        .sum();
+
+    Ok(Report {
+        trials: spec.trials,
+        periods,
+        reward_multiplier: multiplier.to_float(),
+        expected_apr: if horizon_years > 0.0 { mean_return / horizon_years } else { 0.0 },
+        confidence: spec.confidence,
+        value_at_risk: if tail_return < 0.0 { (-tail_return * initial as f64) as u128 } else { 0 },
+        expected_slash: total_slashed / spec.trials as u128,
+        max_simulated_slash,
+        worst_case_slash,
+    })
+}
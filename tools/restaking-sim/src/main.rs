@@ -0,0 +1,45 @@
+//! Command line interface for the restaking simulator
+//!
+//! Usage: `restaking-sim <spec.json> [--trials N] [--seed S]`
+//!
+//! Reads a simulation spec from JSON and prints the report as JSON.
+
+use restaking_sim::{simulate, SimulationSpec};
+use std::{env, fs, process::ExitCode};
+
+const USAGE: &str = "usage: restaking-sim <spec.json> [--trials N] [--seed S]";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(report) => {
+            println!("{}", report);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<String, String> {
+    let mut args = args.into_iter();
+    let path = args.next().ok_or("missing spec file")?;
+
+    let contents = fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let mut spec: SimulationSpec =
+        serde_json::from_str(&contents).map_err(|e| format!("cannot parse {}: {}", path, e))?;
+
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--trials" => spec.trials = value.parse().map_err(|_| format!("invalid trials: {}", value))?,
+            "--seed" => spec.seed = value.parse().map_err(|_| format!("invalid seed: {}", value))?,
+            _ => return Err(format!("unknown flag {}", flag)),
+        }
+    }
+
+    let report = simulate(&spec).map_err(|e| e.to_string())?;
+    serde_json::to_string_pretty(&report).map_err(|e| e.to_string())
+}
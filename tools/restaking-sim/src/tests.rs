@@ -0,0 +1,137 @@
+//! Simulator tests
+
+use crate::*;
+
+/// One parachain taking all of `total_staked` over ten periods of ten blocks, in a year of 100 blocks
+///
+/// Stake is withdrawn immediately after the horizon.
+fn spec(model: ParachainModel) -> SimulationSpec {
+    SimulationSpec {
+        config: ConfigSpec {
+            account: "alice".into(),
+            strategy: StrategySpec::SingleParachain,
+            allocations: vec![AllocationSpec { parachain_id: model.parachain_id, percentage: 10_000 }],
+            total_staked: 1_000,
+            unbonding_period: 0,
+            min_stake_duration: 0,
+            max_stake_duration: 100,
+            reward_frequency: 10,
+            lock_duration: 0,
+        },
+        parachains: vec![model],
+        blocks_per_year: 100,
+        horizon_blocks: Some(100),
+        trials: 10,
+        seed: 0,
+        max_boost: 1.0,
+        compound: false,
+        confidence: 0.95,
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+}
+
+fn model(reward_budget: u128, other_shares: u128) -> ParachainModel {
+    ParachainModel {
+        parachain_id: 2000,
+        annual_slash_probability: 0.0,
+        slash_fraction: 0.0,
+        reward_budget,
+        other_shares,
+    }
+}
+
+#[test]
+fn period_rewards_split_the_budget_by_share() {
+    assert_eq!(period_reward(100, 1_000, 3_000), 25);
+    assert_eq!(period_reward(100, 1_000, 0), 100);
+    assert_eq!(period_reward(100, 1_000, 2_000), 33);
+    assert_eq!(period_reward(100, 0, 2_000), 0);
+}
+
+#[test]
+fn rewards_follow_the_budget_not_the_stake() {
+    let report = simulate(&spec(model(100, 3_000))).unwrap();
+    assert_eq!(report.periods, 10);
+    assert_close(report.expected_apr, 0.25);
+
+    // Doubling the stake only doubles the share of the same budget
+    let mut doubled = spec(model(100, 3_000));
+    doubled.config.total_staked = 2_000;
+    let report = simulate(&doubled).unwrap();
+    assert_close(report.expected_apr, 0.2);
+}
+
+#[test]
+fn lock_boost_ends_with_the_lock() {
+    let mut boosted = spec(model(100, 2_000));
+    boosted.max_boost = 2.0;
+    boosted.config.max_stake_duration = 50;
+    boosted.config.lock_duration = 50;
+
+    // Five periods at 2_000 of 4_000 shares, then five at 1_000 of 3_000
+    let report = simulate(&boosted).unwrap();
+    assert_eq!(report.reward_multiplier, 2.0);
+    assert_close(report.expected_apr, (5.0 * 50.0 + 5.0 * 33.0) / 1_000.0);
+}
+
+#[test]
+fn slashes_split_stake_across_allocations() {
+    let mut slashed = spec(model(0, 0));
+    slashed.config.strategy = StrategySpec::Custom;
+    slashed.config.allocations = vec![
+        AllocationSpec { parachain_id: 2000, percentage: 7_000 },
+        AllocationSpec { parachain_id: 2001, percentage: 3_000 },
+    ];
+    slashed.parachains = vec![
+        ParachainModel { annual_slash_probability: 1.0, slash_fraction: 0.1, ..model(0, 0) },
+        ParachainModel { parachain_id: 2001, slash_fraction: 0.5, ..model(0, 0) },
+    ];
+    slashed.horizon_blocks = Some(10);
+
+    let report = simulate(&slashed).unwrap();
+    assert_eq!(report.expected_slash, 70);
+    assert_eq!(report.max_simulated_slash, 70);
+    assert_eq!(report.worst_case_slash, 70 + 150);
+}
+
+#[test]
+fn unbonding_stake_earns_nothing_but_stays_slashable() {
+    let mut unbonding =
+        spec(ParachainModel { annual_slash_probability: 1.0, slash_fraction: 0.1, ..model(100, 3_000) });
+    unbonding.horizon_blocks = Some(10);
+    unbonding.config.unbonding_period = 30;
+
+    // One period of rewards, a slash in it, and another while unbonding, over 40 blocks
+    let report = simulate(&unbonding).unwrap();
+    assert_eq!(report.expected_slash, 100 + 90);
+    assert_close(report.expected_apr, (25.0 - 190.0) / 1_000.0 / 0.4);
+
+    unbonding.parachains[0].annual_slash_probability = 0.0;
+    let report = simulate(&unbonding).unwrap();
+    assert_eq!(report.expected_slash, 0);
+    assert_close(report.expected_apr, 25.0 / 1_000.0 / 0.4);
+}
+
+#[test]
+fn out_of_range_probabilities_are_rejected() {
+    for probability in [-0.1, 1.5, f64::NAN] {
+        let spec = spec(ParachainModel { annual_slash_probability: probability, ..model(100, 0) });
+        assert_eq!(
+            simulate(&spec),
+            Err(SimulationError::InvalidParameter("annual_slash_probability must be in [0, 1]"))
+        );
+    }
+
+    let spec = spec(ParachainModel { slash_fraction: f64::NAN, ..model(100, 0) });
+    assert_eq!(simulate(&spec), Err(SimulationError::InvalidParameter("slash_fraction must be in [0, 1]")));
+}
+
+#[test]
+fn missing_parachain_models_are_rejected() {
+    let mut spec = spec(model(100, 0));
+    spec.parachains.clear();
+    assert_eq!(simulate(&spec), Err(SimulationError::MissingParachainModel(2000)));
+}
@@ -0,0 +1,640 @@
+//! Deterministic validator rotation simulator
+//!
+//! Evaluates a `CoordinationConfig` against a synthetic validator population
+//! over thousands of sessions without running a chain. The simulation runs
+//! pallet_validator_coordination and pallet_eigen in an in-memory runtime,
+//! so selection, diversity caps, standby promotion, liveness, scoring, slash
+//! curves and jail escalation are the pallets' own; only validator
+//! behaviour is simulated, from a seeded random number generator.
+//!
+//! The simulation models a single parachain and steps one session at a
+//! time. Expired jails are released when a session starts. Every
+//! `rotation_sessions` sessions the set is reselected with
+//! `rotate_validators`, which keeps the current set if fewer than
+//! `min_validators` are eligible. During a session each member is online,
+//! heartbeating and authoring its share of the blocks, with its
+//! availability probability, and misbehaves with the population's
+//! misbehaviour probability. Misbehaving validators are slashed for
+//! equivocation and jailed with `jail_validator`, which replaces them from
+//! the standby pool. pallet_eigen then ends the session, scoring its
+//! members.
+
+use frame_support::traits::Hooks;
+use pallet_eigen::{Offence, ValidatorPerformance, ValidatorProfile, ValidatorStatus};
+use pallet_validator_coordination::{
+    diversity::{DiversityCaps, DiversityTags},
+    selection,
+    slashing::SlashCurve,
+    CoordinationConfig, RotationStrategy, SelectionCriteria,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use runtime::{
+    AccountId, Balances, BlockNumber, Eigen, Runtime, RuntimeEvent, RuntimeOrigin, System, ValidatorCoordination,
+    PARACHAIN,
+};
+use serde::{Deserialize, Serialize};
+use sp_runtime::Perbill;
+use std::{collections::BTreeMap, fmt, fmt::Write as _};
+
+pub mod runtime;
+
+#[cfg(test)]
+mod tests;
+
+/// Rotation strategy as written in JSON
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategySpec {
+    Random,
+    Performance,
+    StakeWeighted,
+    Hybrid,
+}
+
+impl From<StrategySpec> for RotationStrategy {
+    fn from(strategy: StrategySpec) -> Self {
+        match strategy {
+            StrategySpec::Random => RotationStrategy::Random,
+            StrategySpec::Performance => RotationStrategy::Performance,
+            StrategySpec::StakeWeighted => RotationStrategy::StakeWeighted,
+            StrategySpec::Hybrid => RotationStrategy::Hybrid,
+        }
+    }
+}
+
+impl std::str::FromStr for StrategySpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|_| format!("unknown strategy {}", s))
+    }
+}
+
+/// Selection criteria as written in JSON
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CriteriaSpec {
+    pub min_stake: u128,
+    pub min_uptime: u16,
+    pub performance_weight: u16,
+    pub stake_weight: u16,
+    pub history_weight: u16,
+}
+
+/// Coordination configuration as written in JSON
+///
+/// The rotation frequency is given in sessions rather than blocks.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigSpec {
+    pub rotation_sessions: u32,
+    pub strategy: StrategySpec,
+    pub criteria: CriteriaSpec,
+    pub max_validators: u32,
+    pub target_validators: u32,
+    pub min_validators: u32,
+}
+
+impl ConfigSpec {
+    /// Build the on-chain configuration for sessions of `session_blocks` and validate it as the pallet would
+    pub fn to_config(&self, session_blocks: u32) -> Result<CoordinationConfig<BlockNumber>, SimulationError> {
+        let config = CoordinationConfig {
+            rotation_frequency: self.rotation_sessions as BlockNumber * session_blocks as BlockNumber,
+            rotation_strategy: self.strategy.into(),
+            selection_criteria: SelectionCriteria {
+                min_stake: self.criteria.min_stake,
+                min_uptime: self.criteria.min_uptime,
+                performance_weight: self.criteria.performance_weight,
+                stake_weight: self.criteria.stake_weight,
+                history_weight: self.criteria.history_weight,
+            },
+            max_validators: self.max_validators,
+            target_validators: self.target_validators,
+            min_validators: self.min_validators,
+        };
+        config.validate().map_err(|e| SimulationError::Config(format!("{:?}", e)))?;
+        Ok(config)
+    }
+}
+
+/// Diversity caps as written in JSON
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CapsSpec {
+    #[serde(default)]
+    pub max_per_operator: Option<u32>,
+    #[serde(default)]
+    pub max_per_region: Option<u32>,
+    #[serde(default)]
+    pub max_per_asn: Option<u32>,
+}
+
+/// Synthetic validator population
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PopulationSpec {
+    /// Number of validators
+    pub count: u32,
+    /// Number of distinct operators validators are spread over
+    pub operators: u32,
+    /// Two-letter region codes validators are spread over
+    #[serde(default)]
+    pub regions: Vec<String>,
+    /// Number of distinct ASNs validators are spread over, 0 for none
+    #[serde(default)]
+    pub asns: u32,
+    /// Smallest stake, stakes are log-uniform between the bounds
+    pub min_stake: u128,
+    /// Largest stake
+    pub max_stake: u128,
+    /// Lowest per-session probability of being online
+    pub min_availability: f64,
+    /// Highest per-session probability of being online
+    pub max_availability: f64,
+    /// Per-session probability of a selected validator misbehaving
+    pub misbehaviour_probability: f64,
+}
+
+/// Slash curve as written in JSON, fractions between 0 and 1
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CurveSpec {
+    pub base: f64,
+    pub max: f64,
+    pub saturation: f64,
+}
+
+impl Default for CurveSpec {
+    fn default() -> Self {
+        Self { base: 0.01, max: 0.1, saturation: 0.33 }
+    }
+}
+
+/// Jail durations in sessions
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JailSpec {
+    pub base_sessions: u32,
+    pub max_sessions: u32,
+}
+
+impl Default for JailSpec {
+    fn default() -> Self {
+        Self { base_sessions: 1, max_sessions: 64 }
+    }
+}
+
+/// Simulation input
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SimulationSpec {
+    /// Configuration to evaluate
+    pub config: ConfigSpec,
+    /// Diversity caps of the parachain
+    #[serde(default)]
+    pub caps: CapsSpec,
+    /// Validator population
+    pub population: PopulationSpec,
+    /// Number of sessions to simulate
+    #[serde(default = "default_sessions")]
+    pub sessions: u32,
+    /// Blocks per session
+    #[serde(default = "default_session_blocks")]
+    pub session_blocks: u32,
+    /// Seed for the population and the behaviour
+    #[serde(default)]
+    pub seed: u64,
+    /// Weight of the previous score average, between 0 and 1
+    #[serde(default = "default_score_decay")]
+    pub score_decay: f64,
+    /// Slash curve of misbehaviour
+    #[serde(default)]
+    pub slash_curve: CurveSpec,
+    /// Jail durations
+    #[serde(default)]
+    pub jail: JailSpec,
+    /// Standby validators kept
+    #[serde(default = "default_standby")]
+    pub standby: u32,
+}
+
+fn default_sessions() -> u32 {
+    10_000
+}
+
+fn default_session_blocks() -> u32 {
+    600
+}
+
+fn default_score_decay() -> f64 {
+    0.9
+}
+
+fn default_standby() -> u32 {
+    16
+}
+
+/// Metrics of one simulated session
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SessionRow {
+    /// Session index
+    pub session: u32,
+    /// Set size at session end
+    pub set_size: u32,
+    /// Nakamoto coefficient of the set by operator
+    pub nakamoto: u32,
+    /// Share of the previous set replaced, if the set was rotated
+    pub churn: Option<f64>,
+    /// Whether the set was below `min_validators` at any point
+    pub floor_hit: bool,
+    /// Validators in jail at session end
+    pub jailed: u32,
+    /// Standby validators promoted into the set during the session
+    pub promoted: u32,
+    /// Stake slashed during the session
+    pub slashed: u128,
+}
+
+/// Simulation results
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    /// Strategy evaluated
+    pub strategy: StrategySpec,
+    /// Sessions simulated
+    pub sessions: u32,
+    /// Rotations performed, including skipped ones
+    pub rotations: u32,
+    /// Mean Nakamoto coefficient by operator over all sessions
+    pub mean_nakamoto: f64,
+    /// Lowest Nakamoto coefficient by operator in any session
+    pub min_nakamoto: u32,
+    /// Mean share of the set replaced per rotation
+    pub churn_rate: f64,
+    /// Sessions in which the set was below `min_validators`
+    pub floor_hits: u32,
+    /// `floor_hits` as a share of all sessions
+    pub floor_hit_rate: f64,
+    /// Number of jailings
+    pub jailings: u32,
+    /// Standby validators promoted into the set
+    pub promotions: u32,
+    /// Stake slashed over the whole simulation
+    pub total_slashed: u128,
+    /// Per-session metrics, exported with `to_csv`
+    #[serde(skip)]
+    pub rows: Vec<SessionRow>,
+}
+
+impl Report {
+    /// Per-session metrics as CSV with a header line
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("session,set_size,nakamoto,churn,floor_hit,jailed,promoted,slashed\n");
+        for row in &self.rows {
+            let churn = row.churn.map(|churn| format!("{:.6}", churn)).unwrap_or_default();
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                row.session,
+                row.set_size,
+                row.nakamoto,
+                churn,
+                row.floor_hit,
+                row.jailed,
+                row.promoted,
+                row.slashed
+            );
+        }
+        csv
+    }
+}
+
+/// Reasons a simulation cannot run
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimulationError {
+    /// Configuration is rejected by the pallet's validation
+    Config(String),
+    /// A parameter is out of range
+    InvalidParameter(&'static str),
+    /// The runtime rejected a simulated call
+    Rejected(String),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::Config(e) => write!(f, "invalid configuration: {}", e),
+            SimulationError::InvalidParameter(e) => write!(f, "invalid parameter: {}", e),
+            SimulationError::Rejected(e) => write!(f, "rejected by the runtime: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+
+/// Simulated validator
+struct Validator {
+    stake: u128,
+    tags: DiversityTags<AccountId>,
+    availability: f64,
+}
+
+/// What the pallets reported during a session
+#[derive(Default)]
+struct SessionEvents {
+    skipped: bool,
+    below_minimum: bool,
+    removed: Option<u32>,
+    jailings: u32,
+    promoted: u32,
+    slashed: u128,
+}
+
+impl SessionEvents {
+    /// Collect the events deposited since the last `System::reset_events`
+    fn collect() -> Self {
+        use pallet_validator_coordination::Event;
+
+        let mut events = Self::default();
+        for record in System::events() {
+            match record.event {
+                RuntimeEvent::ValidatorCoordination(Event::RotationSkipped { .. }) => events.skipped = true,
+                RuntimeEvent::ValidatorCoordination(Event::BelowMinimum { .. }) => events.below_minimum = true,
+                RuntimeEvent::ValidatorCoordination(Event::RotationCompleted { removed, .. }) => {
+                    events.removed = Some(removed.len() as u32)
+                }
+                RuntimeEvent::ValidatorCoordination(Event::ValidatorJailed { .. }) => events.jailings += 1,
+                RuntimeEvent::ValidatorCoordination(Event::StandbyPromoted { .. }) => events.promoted += 1,
+                RuntimeEvent::Eigen(pallet_eigen::Event::ValidatorSlashed { amount, .. }) => {
+                    events.slashed += amount
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// `value` if it is a probability or fraction between 0 and 1
+fn probability(value: f64, name: &'static str) -> Result<f64, SimulationError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(SimulationError::InvalidParameter(name))
+    }
+}
+
+fn to_perbill(value: f64, name: &'static str) -> Result<Perbill, SimulationError> {
+    probability(value, name).map(Perbill::from_float)
+}
+
+fn rejected(error: impl fmt::Debug) -> SimulationError {
+    SimulationError::Rejected(format!("{:?}", error))
+}
+
+/// Generate the validator population
+fn populate(spec: &PopulationSpec, rng: &mut ChaCha8Rng) -> Result<Vec<Validator>, SimulationError> {
+    if spec.count == 0 || spec.operators == 0 {
+        return Err(SimulationError::InvalidParameter("population needs validators and operators"));
+    }
+    if spec.count > runtime::MAX_VALIDATORS {
+        return Err(SimulationError::InvalidParameter("population has more than 1024 validators"));
+    }
+    if spec.min_stake == 0 || spec.max_stake < spec.min_stake {
+        return Err(SimulationError::InvalidParameter("stake bounds must satisfy 0 < min_stake <= max_stake"));
+    }
+    let regions = spec
+        .regions
+        .iter()
+        .map(|region| {
+            <[u8; 2]>::try_from(region.as_bytes())
+                .map_err(|_| SimulationError::InvalidParameter("regions must be two letters"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (low, high) = ((spec.min_stake as f64).ln(), (spec.max_stake as f64).ln());
+    let min_availability = probability(spec.min_availability, "min_availability must be in [0, 1]")?;
+    let max_availability = probability(spec.max_availability, "max_availability must be in [0, 1]")?;
+    if max_availability < min_availability {
+        return Err(SimulationError::InvalidParameter("max_availability is below min_availability"));
+    }
+
+    Ok((0..spec.count)
+        .map(|_| Validator {
+            stake: rng.gen_range(low..=high).exp() as u128,
+            tags: DiversityTags {
+                operator: rng.gen_range(0..spec.operators).into(),
+                region: if regions.is_empty() { None } else { Some(regions[rng.gen_range(0..regions.len())]) },
+                asn: if spec.asns == 0 { None } else { Some(64_512 + rng.gen_range(0..spec.asns)) },
+            },
+            availability: rng.gen_range(min_availability..=max_availability),
+        })
+        .collect())
+}
+
+/// Register the population with pallet_eigen, each validator bonded by its own account
+///
+/// Validator `i` of the population has ID and account `i`.
+fn register(validators: &[Validator]) -> Result<(), SimulationError> {
+    use frame_support::traits::Currency as _;
+
+    for (id, validator) in validators.iter().enumerate() {
+        let id = id as AccountId;
+        pallet_eigen::Validators::<Runtime>::insert(
+            id,
+            ValidatorProfile {
+                id,
+                account: id,
+                commission_rate: 0,
+                active_parachains: Default::default(),
+                performance: ValidatorPerformance {
+                    uptime: selection::FULL_WEIGHT as u16,
+                    blocks_proposed: 0,
+                    blocks_settled: 0,
+                    slashes: 0,
+                    score: selection::FULL_WEIGHT as u16,
+                },
+                status: ValidatorStatus::Active,
+                joined_at: 0,
+                last_updated: 0,
+            },
+        );
+        pallet_eigen::TotalStake::<Runtime>::insert(id, validator.stake);
+        Balances::make_free_balance_be(&id, validator.stake);
+        ValidatorCoordination::set_diversity_tags(RuntimeOrigin::signed(id), id, validator.tags.clone())
+            .map_err(rejected)?;
+    }
+    Ok(())
+}
+
+/// Members of the simulated parachain's set
+fn members() -> Vec<AccountId> {
+    pallet_eigen::ValidatorSets::<Runtime>::get(PARACHAIN).iter().map(|member| member.id).collect()
+}
+
+/// Release every validator whose jail has expired by `now`
+fn release_expired(now: BlockNumber) -> Result<(), SimulationError> {
+    let expired: Vec<AccountId> = pallet_validator_coordination::Jails::<Runtime>::iter()
+        .filter(|(_, jail)| jail.until <= now)
+        .map(|(id, _)| id)
+        .collect();
+    for id in expired {
+        ValidatorCoordination::release_validator(&id).map_err(rejected)?;
+    }
+    Ok(())
+}
+
+/// Minimum number of operators whose combined stake in the set exceeds a third
+pub fn nakamoto_coefficient<Operator: Ord>(stakes: impl IntoIterator<Item = (Operator, u128)>) -> u32 {
+    let mut by_operator = BTreeMap::<Operator, u128>::new();
+    for (operator, stake) in stakes {
+        *by_operator.entry(operator).or_default() += stake;
+    }
+    let mut totals: Vec<u128> = by_operator.into_values().collect();
+    totals.sort_unstable_by(|a, b| b.cmp(a));
+
+    let threshold = totals.iter().sum::<u128>() / 3;
+    let mut cumulative = 0u128;
+    for (index, stake) in totals.iter().enumerate() {
+        cumulative += stake;
+        if cumulative > threshold {
+            return index as u32 + 1;
+        }
+    }
+    totals.len() as u32
+}
+
+/// Run the simulation described by `spec`
+pub fn simulate(spec: &SimulationSpec) -> Result<Report, SimulationError> {
+    if spec.sessions == 0 {
+        return Err(SimulationError::InvalidParameter("sessions must be positive"));
+    }
+    if spec.session_blocks == 0 {
+        return Err(SimulationError::InvalidParameter("session_blocks must be positive"));
+    }
+    let config = spec.config.to_config(spec.session_blocks)?;
+    let curve = SlashCurve {
+        base: to_perbill(spec.slash_curve.base, "slash curve fractions must be in [0, 1]")?,
+        max: to_perbill(spec.slash_curve.max, "slash curve fractions must be in [0, 1]")?,
+        saturation: to_perbill(spec.slash_curve.saturation, "slash curve fractions must be in [0, 1]")?,
+    };
+    if !curve.is_valid() {
+        return Err(SimulationError::InvalidParameter("slash curve base is above its max"));
+    }
+    let caps = DiversityCaps {
+        max_per_operator: spec.caps.max_per_operator,
+        max_per_region: spec.caps.max_per_region,
+        max_per_asn: spec.caps.max_per_asn,
+    };
+    let misbehaviour =
+        probability(spec.population.misbehaviour_probability, "misbehaviour_probability must be in [0, 1]")?;
+
+    let session_blocks = spec.session_blocks as BlockNumber;
+    runtime::SessionDuration::set(session_blocks);
+    runtime::MaxJailDuration::set(spec.jail.max_sessions as BlockNumber * session_blocks);
+    runtime::ScoreDecay::set(to_perbill(spec.score_decay, "score_decay must be in [0, 1]")?);
+    runtime::MaxStandby::set(spec.standby);
+    let jail_duration = spec.jail.base_sessions as BlockNumber * session_blocks;
+
+    let mut rng = ChaCha8Rng::seed_from_u64(spec.seed);
+    let validators = populate(&spec.population, &mut rng)?;
+
+    let storage = frame_system::GenesisConfig::default()
+        .build_storage::<Runtime>()
+        .expect("system genesis has no fallible parts");
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        register(&validators)?;
+        ValidatorCoordination::set_coordination_config(RuntimeOrigin::root(), PARACHAIN, config.clone())
+            .map_err(|e| SimulationError::Config(format!("{:?}", e)))?;
+        ValidatorCoordination::set_diversity_caps(RuntimeOrigin::root(), PARACHAIN, caps).map_err(rejected)?;
+        ValidatorCoordination::set_slash_curve(RuntimeOrigin::root(), Offence::Equivocation, curve)
+            .map_err(rejected)?;
+        Eigen::on_initialize(1);
+        Ok(())
+    })?;
+
+    let mut rows = Vec::with_capacity(spec.sessions as usize);
+    let (mut rotations, mut jailings, mut promotions, mut total_slashed) = (0u32, 0u32, 0u32, 0u128);
+
+    for session in 0..spec.sessions {
+        let start = 1 + session as BlockNumber * session_blocks;
+        let row = ext.execute_with(|| {
+            System::reset_events();
+            release_expired(start)?;
+
+            let previous = members();
+            if session % spec.config.rotation_sessions == 0 {
+                rotations += 1;
+                runtime::SelectionSeed::set(rng.gen());
+                ValidatorCoordination::rotate_validators(PARACHAIN, &config).map_err(|e| rejected(e.error))?;
+            }
+
+            // Behaviour during the session
+            let set = members();
+            let online: BTreeMap<AccountId, bool> =
+                set.iter().map(|id| (*id, rng.gen_bool(validators[*id as usize].availability))).collect();
+            let offenders: Vec<AccountId> = set.iter().copied().filter(|_| rng.gen_bool(misbehaviour)).collect();
+            for offender in offenders.iter() {
+                ValidatorCoordination::slash_validator(offender, Offence::Equivocation).map_err(rejected)?;
+                ValidatorCoordination::jail_validator(offender, jail_duration, Offence::Equivocation.name().to_vec())
+                    .map_err(rejected)?;
+            }
+
+            // Online members heartbeat and author their share of the blocks,
+            // promoted standbys are treated as online
+            let session_index = Eigen::current_session_index();
+            let set = members();
+            let expected_blocks = spec.session_blocks / (set.len() as u32).max(1);
+            for id in set.iter().filter(|id| online.get(id).copied().unwrap_or(true)) {
+                pallet_validator_coordination::ReceivedHeartbeats::<Runtime>::insert(session_index, id, ());
+                pallet_validator_coordination::AuthoredBlocks::<Runtime>::insert(session_index, id, expected_blocks);
+            }
+
+            let end = start + session_blocks;
+            System::set_block_number(end);
+            Eigen::on_initialize(end);
+
+            let events = SessionEvents::collect();
+            let churn = events
+                .removed
+                .filter(|_| !previous.is_empty())
+                .map(|removed| removed as f64 / previous.len() as f64);
+            jailings += events.jailings;
+            promotions += events.promoted;
+            total_slashed += events.slashed;
+            Ok(SessionRow {
+                session,
+                set_size: set.len() as u32,
+                nakamoto: nakamoto_coefficient(set.iter().map(|id| {
+                    (validators[*id as usize].tags.operator, pallet_eigen::TotalStake::<Runtime>::get(id))
+                })),
+                churn,
+                floor_hit: events.skipped || events.below_minimum || (set.len() as u32) < config.min_validators,
+                jailed: pallet_validator_coordination::Jails::<Runtime>::iter_keys().count() as u32,
+                promoted: events.promoted,
+                slashed: events.slashed,
+            })
+        })?;
+        rows.push(row);
+
+        // Storage iteration slows down as uncommitted changes pile up
+        if session % 20 == 19 {
+            ext.commit_all().map_err(SimulationError::Rejected)?;
+        }
+    }
+
+    let sessions = rows.len() as f64;
+    let churns: Vec<f64> = rows.iter().filter_map(|row| row.churn).collect();
+    let floor_hits = rows.iter().filter(|row| row.floor_hit).count() as u32;
+
+    Ok(Report {
+        strategy: spec.config.strategy,
+        sessions: spec.sessions,
+        rotations,
+        mean_nakamoto: rows.iter().map(|row| row.nakamoto as f64).sum::<f64>() / sessions,
+        min_nakamoto: rows.iter().map(|row| row.nakamoto).min().unwrap_or(0),
+        churn_rate: if churns.is_empty() { 0.0 } else { churns.iter().sum::<f64>() / churns.len() as f64 },
+        floor_hits,
+        floor_hit_rate: floor_hits as f64 / sessions,
+        jailings,
+        promotions,
+        total_slashed,
+        rows,
+    })
+}
@@ -0,0 +1,52 @@
+//! Command line interface for the rotation simulator
+//!
+//! Usage: `rotation-sim <spec.json> [--sessions N] [--seed S] [--strategy X] [--csv out.csv]`
+//!
+//! Reads a simulation spec from JSON, prints the report as JSON and, with
+//! `--csv`, writes the per-session metrics to a CSV file.
+
+use rotation_sim::{simulate, SimulationSpec};
+use std::{env, fs, process::ExitCode};
+
+const USAGE: &str = "usage: rotation-sim <spec.json> [--sessions N] [--seed S] [--strategy X] [--csv out.csv]";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(report) => {
+            println!("{}", report);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<String, String> {
+    let mut args = args.into_iter();
+    let path = args.next().ok_or("missing spec file")?;
+
+    let contents = fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let mut spec: SimulationSpec =
+        serde_json::from_str(&contents).map_err(|e| format!("cannot parse {}: {}", path, e))?;
+
+    let mut csv = None;
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--sessions" => spec.sessions = value.parse().map_err(|_| format!("invalid sessions: {}", value))?,
+            "--seed" => spec.seed = value.parse().map_err(|_| format!("invalid seed: {}", value))?,
+            "--strategy" => spec.config.strategy = value.parse()?,
+            "--csv" => csv = Some(value),
+            _ => return Err(format!("unknown flag {}", flag)),
+        }
+    }
+
+    let report = simulate(&spec).map_err(|e| e.to_string())?;
+    if let Some(csv) = csv {
+        fs::write(&csv, report.to_csv()).map_err(|e| format!("cannot write {}: {}", csv, e))?;
+    }
+    serde_json::to_string_pretty(&report).map_err(|e| e.to_string())
+}
@@ -0,0 +1,177 @@
+//! Runtime the simulation runs against
+//!
+//! Wires pallet_validator_coordination to pallet_eigen and pallet_balances
+//! like a parachain runtime would. Rotations complete at once, as with a
+//! zero `HandoverPeriod`. Values that depend on the simulation spec are
+//! thread-local, so simulations on different threads don't interfere.
+
+use frame_support::{
+    construct_runtime, parameter_types,
+    traits::{ConstU128, ConstU32, ConstU64, Everything, Randomness},
+};
+use frame_system::{offchain::SendTransactionTypes, EnsureRoot};
+use pallet_eigen::ExistenceRequirement;
+use sp_core::H256;
+use sp_runtime::{
+    testing::{Header, UintAuthorityId},
+    traits::{BlakeTwo256, Hash, IdentityLookup},
+    DispatchResult, Perbill,
+};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type BlockNumber = u64;
+
+/// Parachain the simulated set validates
+pub const PARACHAIN: u32 = 2000;
+
+/// Most validators in a population, and in a validator set
+pub const MAX_VALIDATORS: u32 = 1024;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
+type Block = frame_system::mocking::MockBlock<Runtime>;
+
+construct_runtime!(
+    pub enum Runtime where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Eigen: pallet_eigen,
+        ValidatorCoordination: pallet_validator_coordination,
+    }
+);
+
+impl frame_system::Config for Runtime {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = BlockNumber;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type WeightInfo = ();
+    type Balance = Balance;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type ReserveIdentifier = [u8; 8];
+    type HoldIdentifier = ();
+    type FreezeIdentifier = ();
+    type MaxLocks = ConstU32<10>;
+    type MaxReserves = ConstU32<10>;
+    type MaxHolds = ConstU32<0>;
+    type MaxFreezes = ConstU32<0>;
+}
+
+/// pallet_eigen's currency backed by `Balances`
+pub struct EigenCurrency;
+
+impl pallet_eigen::Currency<AccountId> for EigenCurrency {
+    type Balance = Balance;
+
+    fn free_balance(who: &AccountId) -> Balance {
+        Balances::free_balance(who)
+    }
+
+    fn transfer(
+        source: &AccountId,
+        dest: &AccountId,
+        value: Balance,
+        existence_requirement: ExistenceRequirement,
+    ) -> DispatchResult {
+        let existence_requirement = match existence_requirement {
+            ExistenceRequirement::KeepAlive => frame_support::traits::ExistenceRequirement::KeepAlive,
+            ExistenceRequirement::AllowDeath => frame_support::traits::ExistenceRequirement::AllowDeath,
+        };
+        <Balances as frame_support::traits::Currency<AccountId>>::transfer(source, dest, value, existence_requirement)
+    }
+
+    fn slash(who: &AccountId, value: Balance) -> Balance {
+        <Balances as frame_support::traits::Currency<AccountId>>::slash(who, value).1
+    }
+}
+
+parameter_types! {
+    /// Blocks per session
+    pub static SessionDuration: BlockNumber = 600;
+    /// Longest jail, in blocks
+    pub static MaxJailDuration: BlockNumber = 600 * 64;
+    /// Weight of the previous score average
+    pub static ScoreDecay: Perbill = Perbill::from_percent(90);
+    /// Standby validators kept per parachain
+    pub static MaxStandby: u32 = 16;
+    /// Seed the next rotation selects with
+    pub static SelectionSeed: [u8; 32] = [0; 32];
+}
+
+impl pallet_eigen::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type ValidatorId = AccountId;
+    type Currency = EigenCurrency;
+    type MaxValidatorsPerSet = ConstU32<MAX_VALIDATORS>;
+    type MinStakeAmount = ConstU128<0>;
+    type SessionDuration = SessionDuration;
+    type MaxNominatorsPerValidator = ConstU32<16>;
+    type OnSessionEnd = ValidatorCoordination;
+}
+
+impl<C> SendTransactionTypes<C> for Runtime
+where
+    RuntimeCall: From<C>,
+{
+    type OverarchingCall = RuntimeCall;
+    type Extrinsic = UncheckedExtrinsic;
+}
+
+/// Randomness drawn from `SelectionSeed`, set by the simulation before each rotation
+pub struct SeededRandomness;
+
+impl Randomness<H256, BlockNumber> for SeededRandomness {
+    fn random(subject: &[u8]) -> (H256, BlockNumber) {
+        (BlakeTwo256::hash_of(&(subject, SelectionSeed::get())), System::block_number())
+    }
+}
+
+impl pallet_validator_coordination::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type ConfigOrigin = EnsureRoot<AccountId>;
+    type Randomness = SeededRandomness;
+    type MaxParachains = ConstU32<1>;
+    type HandoverPeriod = ConstU64<0>;
+    type MaxCandidates = ConstU32<MAX_VALIDATORS>;
+    type MaxRotationsPerBlock = ConstU32<1>;
+    type AuthorityId = UintAuthorityId;
+    type UnsignedPriority = ConstU64<100>;
+    type ScoreDecay = ScoreDecay;
+    type MaxScoreHistory = ConstU32<16>;
+    type MaxJailDuration = MaxJailDuration;
+    type MaxReasonLength = ConstU32<16>;
+    type MaxJailExpiriesPerBlock = ConstU32<MAX_VALIDATORS>;
+    type MaxOffendersPerSession = ConstU32<MAX_VALIDATORS>;
+    type MaxStandby = MaxStandby;
+    type ReportUnresponsiveness = ();
+}
@@ -0,0 +1,122 @@
+//! Simulator tests
+
+use crate::*;
+
+/// `count` validators run by distinct operators, selecting four of them for a single session
+fn spec(count: u32, misbehaviour_probability: f64) -> SimulationSpec {
+    SimulationSpec {
+        config: ConfigSpec {
+            rotation_sessions: 10,
+            strategy: StrategySpec::StakeWeighted,
+            criteria: CriteriaSpec {
+                min_stake: 0,
+                min_uptime: 0,
+                performance_weight: 0,
+                stake_weight: 10_000,
+                history_weight: 0,
+            },
+            max_validators: 8,
+            target_validators: 4,
+            min_validators: 2,
+        },
+        caps: CapsSpec::default(),
+        population: PopulationSpec {
+            count,
+            operators: count,
+            regions: Vec::new(),
+            asns: 0,
+            min_stake: 1_000,
+            max_stake: 1_000_000,
+            min_availability: 0.9,
+            max_availability: 1.0,
+            misbehaviour_probability,
+        },
+        sessions: 1,
+        session_blocks: 600,
+        seed: 7,
+        score_decay: 0.9,
+        slash_curve: CurveSpec::default(),
+        jail: JailSpec { base_sessions: 10, max_sessions: 100 },
+        standby: 16,
+    }
+}
+
+#[test]
+fn runs_are_reproducible() {
+    let mut spec = spec(20, 0.05);
+    spec.sessions = 200;
+    assert_eq!(simulate(&spec), simulate(&spec));
+}
+
+#[test]
+fn offenders_are_jailed_and_replaced_from_standby() {
+    let report = simulate(&spec(8, 1.0)).unwrap();
+    let row = &report.rows[0];
+    assert_eq!(report.jailings, 4);
+    assert_eq!(row.jailed, 4);
+    assert_eq!(row.set_size, 4);
+    assert_eq!(row.promoted, 4);
+    assert_eq!(report.promotions, 4);
+    assert!(!row.floor_hit);
+    assert!(row.slashed > 0);
+}
+
+#[test]
+fn jails_expire_when_a_session_starts() {
+    let mut spec = spec(8, 1.0);
+    spec.config.rotation_sessions = 1;
+    spec.jail = JailSpec { base_sessions: 1, max_sessions: 100 };
+    spec.sessions = 2;
+    let report = simulate(&spec).unwrap();
+
+    // The first session's offenders are released before the second rotation
+    assert_eq!(report.rows[0].jailed, 4);
+    assert_eq!(report.rows[1].jailed, 4);
+    assert!(report.rows[1].churn.is_some());
+    assert_eq!(report.jailings, 8);
+}
+
+#[test]
+fn diversity_caps_bound_the_set() {
+    let mut spec = spec(8, 0.0);
+    spec.population.operators = 2;
+    spec.caps.max_per_operator = Some(1);
+    let report = simulate(&spec).unwrap();
+    assert_eq!(report.rows[0].set_size, 2);
+    assert!(!report.rows[0].floor_hit);
+}
+
+#[test]
+fn sets_without_standbys_hit_the_floor() {
+    let report = simulate(&spec(4, 1.0)).unwrap();
+    let row = &report.rows[0];
+    assert_eq!(row.set_size, 0);
+    assert!(row.floor_hit);
+    assert_eq!(report.floor_hits, 1);
+}
+
+#[test]
+fn out_of_range_probabilities_are_rejected() {
+    for probability in [-0.1, 1.5, f64::NAN] {
+        assert_eq!(
+            simulate(&spec(8, probability)),
+            Err(SimulationError::InvalidParameter("misbehaviour_probability must be in [0, 1]"))
+        );
+    }
+
+    let mut invalid = spec(8, 0.0);
+    invalid.population.min_availability = f64::NAN;
+    assert_eq!(simulate(&invalid), Err(SimulationError::InvalidParameter("min_availability must be in [0, 1]")));
+
+    let mut invalid = spec(8, 0.0);
+    invalid.population.min_availability = 0.95;
+    invalid.population.max_availability = 0.9;
+    assert_eq!(
+        simulate(&invalid),
+        Err(SimulationError::InvalidParameter("max_availability is below min_availability"))
+    );
+
+    let mut invalid = spec(8, 0.0);
+    invalid.score_decay = 2.0;
+    assert_eq!(simulate(&invalid), Err(SimulationError::InvalidParameter("score_decay must be in [0, 1]")));
+}